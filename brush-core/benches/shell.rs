@@ -111,13 +111,15 @@ mod unix {
                 fname: String::from("testfunc"),
                 body: brush_parser::ast::FunctionBody(
                     brush_parser::ast::CompoundCommand::BraceGroup(
-                        brush_parser::ast::BraceGroupCommand(brush_parser::ast::CompoundList(
-                            vec![],
-                        )),
+                        brush_parser::ast::BraceGroupCommand {
+                            list: brush_parser::ast::CompoundList(vec![]),
+                            loc: brush_parser::TokenLocation::default(),
+                        },
                     ),
                     None,
                 ),
                 source: String::from("/some/path"),
+                loc: brush_parser::TokenLocation::default(),
             }),
         );
         c.bench_function("function_call", |b| {
@@ -14,6 +14,7 @@ mod bind;
 mod break_;
 mod brushinfo;
 mod builtin_;
+mod caller;
 mod cd;
 mod colon;
 mod command;
@@ -21,6 +22,7 @@ mod complete;
 mod continue_;
 mod declare;
 mod dirs;
+mod disown;
 mod dot;
 mod echo;
 mod enable;
@@ -31,10 +33,12 @@ mod exit;
 mod export;
 mod factory;
 mod false_;
+mod fc;
 mod fg;
 mod getopts;
 mod hash;
 mod help;
+mod history;
 mod jobs;
 #[cfg(unix)]
 mod kill;
@@ -57,6 +61,8 @@ mod trap;
 mod true_;
 mod type_;
 #[cfg(unix)]
+mod ulimit;
+#[cfg(unix)]
 mod umask;
 mod unalias;
 mod unimp;
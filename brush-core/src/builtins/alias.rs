@@ -1,5 +1,6 @@
 use clap::Parser;
 use std::io::Write;
+use std::sync::Arc;
 
 use crate::{builtins, commands};
 
@@ -23,15 +24,13 @@ impl builtins::Command for AliasCommand {
         let mut exit_code = builtins::ExitCode::Success;
 
         if self.print || self.aliases.is_empty() {
-            for (name, value) in &context.shell.aliases {
+            for (name, value) in context.shell.aliases.iter() {
                 writeln!(context.stdout(), "alias {name}='{value}'")?;
             }
         } else {
             for alias in &self.aliases {
                 if let Some((name, unexpanded_value)) = alias.split_once('=') {
-                    context
-                        .shell
-                        .aliases
+                    Arc::make_mut(&mut context.shell.aliases)
                         .insert(name.to_owned(), unexpanded_value.to_owned());
                 } else if let Some(value) = context.shell.aliases.get(alias) {
                     writeln!(context.stdout(), "alias {alias}='{value}'")?;
@@ -1,7 +1,10 @@
 use clap::{Parser, ValueEnum};
+use std::collections::HashMap;
 use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
 
-use crate::{builtins, commands, error};
+use crate::{builtins, commands, error, readline};
 
 /// Identifier for a keymap
 #[derive(Clone, ValueEnum)]
@@ -18,6 +21,18 @@ enum BindKeyMap {
     ViInsert,
 }
 
+impl From<&BindKeyMap> for readline::KeyMap {
+    fn from(keymap: &BindKeyMap) -> Self {
+        match keymap {
+            BindKeyMap::EmacsStandard => Self::Emacs,
+            BindKeyMap::EmacsMeta => Self::EmacsMeta,
+            BindKeyMap::EmacsCtlx => Self::EmacsCtlx,
+            BindKeyMap::ViCommand => Self::ViCommand,
+            BindKeyMap::ViInsert => Self::ViInsert,
+        }
+    }
+}
+
 /// Inspect and modify key bindings and other input configuration.
 #[derive(Parser)]
 pub(crate) struct BindCommand {
@@ -63,77 +78,332 @@ pub(crate) struct BindCommand {
     /// List key sequence bindings.
     #[arg(short = 'X')]
     list_key_seq_bindings: bool,
-    /// Key sequence binding to readline function or command.
-    key_sequence: Option<String>,
+    /// Key sequence bindings to readline functions or macros, or variable settings, in
+    /// the syntax used by readline init files.
+    key_sequences: Vec<String>,
 }
 
 impl builtins::Command for BindCommand {
     async fn execute(
         &self,
-        context: commands::ExecutionContext<'_>,
+        mut context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        // Make sure we're looking at (and updating) the full configuration.
+        context.shell.load_readline_init_file();
+        context.shell.update_readline_editing_mode();
+
+        let result = self.execute_with_config(&mut context);
+
+        // Changes may have switched editing modes.
+        context.shell.apply_readline_editing_mode();
+
+        result
+    }
+}
+
+impl BindCommand {
+    fn execute_with_config(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let keymap = self.keymap.as_ref().map_or_else(
+            || context.shell.readline_config.current_keymap(),
+            readline::KeyMap::from,
+        );
+
+        let mut exit_code = builtins::ExitCode::Success;
+
         if self.list_funcs {
-            return error::unimp("bind -l is not yet implemented");
+            for name in readline::FUNCTION_NAMES {
+                writeln!(context.stdout(), "{name}")?;
+            }
         }
 
         if self.list_funcs_and_bindings {
-            return error::unimp("bind -P is not yet implemented");
+            display_function_bindings(context, keymap, false)?;
         }
 
         if self.list_funcs_and_bindings_reusable {
-            return error::unimp("bind -p is not yet implemented");
+            display_function_bindings(context, keymap, true)?;
         }
 
         if self.list_key_seqs_that_invoke_macros {
-            return error::unimp("bind -S is not yet implemented");
+            display_macros(context, keymap, false)?;
         }
 
         if self.list_key_seqs_that_invoke_macros_reusable {
-            return error::unimp("bind -s is not yet implemented");
+            display_macros(context, keymap, true)?;
         }
 
         if self.list_vars {
-            return error::unimp("bind -V is not yet implemented");
+            for (name, value) in context.shell.readline_config.variables() {
+                writeln!(context.stdout(), "{name} is set to `{value}'")?;
+            }
         }
 
         if self.list_vars_reusable {
-            // For now we'll just display a few items and show defaults.
-            writeln!(context.stdout(), "set mark-directories on")?;
-            writeln!(context.stdout(), "set mark-symlinked-directories off")?;
+            for (name, value) in context.shell.readline_config.variables() {
+                writeln!(context.stdout(), "set {name} {value}")?;
+            }
         }
 
-        if self.query_func_bindings.is_some() {
-            return error::unimp("bind -q is not yet implemented");
+        if let Some(file) = &self.bindings_file {
+            if !load_bindings_file(context, keymap, file)? {
+                return Ok(builtins::ExitCode::Custom(1));
+            }
         }
 
-        if self.remove_func_bindings.is_some() {
-            return error::unimp("bind -u is not yet implemented");
+        if let Some(function) = &self.query_func_bindings {
+            if !is_known_function(context, function)? {
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+
+            let key_sequences = context
+                .shell
+                .readline_config
+                .key_sequences_for_function(keymap, function);
+
+            if key_sequences.is_empty() {
+                writeln!(context.stdout(), "{function} is not bound to any keys.")?;
+                exit_code = builtins::ExitCode::Custom(1);
+            } else {
+                writeln!(
+                    context.stdout(),
+                    "{function} can be invoked via {}",
+                    format_key_sequence_list(&key_sequences)
+                )?;
+            }
         }
 
-        if self.remove_key_seq_binding.is_some() {
-            return error::unimp("bind -r is not yet implemented");
+        if let Some(function) = &self.remove_func_bindings {
+            if !is_known_function(context, function)? {
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+
+            Arc::make_mut(&mut context.shell.readline_config).unbind_function(keymap, function);
         }
 
-        if self.bindings_file.is_some() {
-            return error::unimp("bind -f is not yet implemented");
+        if let Some(key_sequence) = &self.remove_key_seq_binding {
+            let key_sequence = readline::parse_key_sequence(key_sequence);
+            Arc::make_mut(&mut context.shell.readline_config)
+                .unbind(keymap, key_sequence.as_slice());
         }
 
-        if !self.key_seq_bindings.is_empty() {
-            return error::unimp("bind -x is not yet implemented");
+        if !bind_shell_commands(context, keymap, &self.key_seq_bindings)? {
+            return Ok(builtins::ExitCode::Custom(1));
         }
 
         if self.list_key_seq_bindings {
-            return error::unimp("bind -X is not yet implemented");
+            display_shell_commands(context, keymap)?;
         }
 
-        if let Some(key_sequence) = &self.key_sequence {
+        if !self.key_sequences.is_empty() {
+            let term = context
+                .shell
+                .get_env_str("TERM")
+                .unwrap_or_default()
+                .to_string();
+            let mut parse_context = readline::ParseContext::new(keymap, term.as_str());
+
+            for line in &self.key_sequences {
+                // N.B. Like readline, we report problems but don't treat them as failures.
+                if let Err(e) = Arc::make_mut(&mut context.shell.readline_config)
+                    .parse_line(line, &mut parse_context)
+                {
+                    writeln!(context.stderr(), "readline: {e}")?;
+                }
+            }
+        }
+
+        Ok(exit_code)
+    }
+}
+
+/// Loads bindings and variable settings from the given file, returning whether the file
+/// could be read.
+fn load_bindings_file(
+    context: &mut commands::ExecutionContext<'_>,
+    keymap: readline::KeyMap,
+    file: &str,
+) -> Result<bool, error::Error> {
+    let path = context.shell.get_absolute_path(Path::new(file));
+    let term = context
+        .shell
+        .get_env_str("TERM")
+        .unwrap_or_default()
+        .to_string();
+    let mut parse_context = readline::ParseContext::new(keymap, term.as_str());
+
+    match Arc::make_mut(&mut context.shell.readline_config)
+        .load_file(path.as_path(), &mut parse_context)
+    {
+        Ok(errors) => {
+            for e in errors {
+                writeln!(context.stderr(), "readline: {e}")?;
+            }
+        }
+        Err(readline::ConfigError::UnreadableFile(_, e)) => {
             writeln!(
                 context.stderr(),
-                "bind: key seq not implemented: {key_sequence}"
+                "{}: {file}: cannot read: {e}",
+                context.command_name
+            )?;
+            return Ok(false);
+        }
+        Err(e) => {
+            writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Binds key sequences to shell commands, as given in `bind -x` syntax, returning whether
+/// all of the bindings were valid.
+fn bind_shell_commands(
+    context: &mut commands::ExecutionContext<'_>,
+    keymap: readline::KeyMap,
+    bindings: &[String],
+) -> Result<bool, error::Error> {
+    for binding in bindings {
+        match readline::parse_shell_command_binding(binding) {
+            Ok((key_sequence, command)) => Arc::make_mut(&mut context.shell.readline_config).bind(
+                keymap,
+                key_sequence.as_slice(),
+                readline::KeyAction::ShellCommand(command),
+            ),
+            Err(e) => {
+                writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn display_function_bindings(
+    context: &commands::ExecutionContext<'_>,
+    keymap: readline::KeyMap,
+    reusable: bool,
+) -> Result<(), error::Error> {
+    let bindings = context.shell.readline_config.bindings(keymap);
+
+    let mut key_sequences_by_function: HashMap<&str, Vec<Vec<u8>>> = HashMap::new();
+    for (key_sequence, action) in &bindings {
+        if let readline::KeyAction::Function(function) = action {
+            key_sequences_by_function
+                .entry(function.as_str())
+                .or_default()
+                .push(key_sequence.clone());
+        }
+    }
+
+    writeln!(context.stdout())?;
+
+    for function in readline::FUNCTION_NAMES {
+        let key_sequences = key_sequences_by_function
+            .get(readline::canonical_function_name(function))
+            .map_or(&[][..], |seqs| seqs.as_slice());
+
+        if reusable {
+            if key_sequences.is_empty() {
+                writeln!(context.stdout(), "# {function} (not bound)")?;
+            }
+            for key_sequence in key_sequences {
+                writeln!(
+                    context.stdout(),
+                    "\"{}\": {function}",
+                    readline::format_key_sequence(key_sequence)
+                )?;
+            }
+        } else if key_sequences.is_empty() {
+            writeln!(context.stdout(), "{function} is not bound to any keys")?;
+        } else {
+            writeln!(
+                context.stdout(),
+                "{function} can be found on {}",
+                format_key_sequence_list(key_sequences)
             )?;
-            return Ok(builtins::ExitCode::Unimplemented);
         }
+    }
+
+    Ok(())
+}
+
+fn display_macros(
+    context: &commands::ExecutionContext<'_>,
+    keymap: readline::KeyMap,
+    reusable: bool,
+) -> Result<(), error::Error> {
+    for (key_sequence, action) in context.shell.readline_config.bindings(keymap) {
+        if let readline::KeyAction::Macro(text) = action {
+            // N.B. The key sequence is displayed the same way as the macro's text.
+            let key_sequence = readline::format_macro(&String::from_utf8_lossy(&key_sequence));
+            let text = readline::format_macro(text.as_str());
+
+            if reusable {
+                writeln!(context.stdout(), "\"{key_sequence}\": \"{text}\"")?;
+            } else {
+                writeln!(context.stdout(), "{key_sequence} outputs {text}")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn display_shell_commands(
+    context: &commands::ExecutionContext<'_>,
+    keymap: readline::KeyMap,
+) -> Result<(), error::Error> {
+    for (key_sequence, action) in context.shell.readline_config.bindings(keymap) {
+        if let readline::KeyAction::ShellCommand(command) = action {
+            // N.B. As with macros, the key sequence is displayed the same way as the text.
+            let key_sequence = readline::format_macro(&String::from_utf8_lossy(&key_sequence));
+            let command = readline::format_macro(command.as_str());
+
+            writeln!(context.stdout(), "\"{key_sequence}\": \"{command}\"")?;
+        }
+    }
 
-        Ok(builtins::ExitCode::Success)
+    Ok(())
+}
+
+/// Checks whether the named readline function exists, reporting an error if it doesn't.
+fn is_known_function(
+    context: &commands::ExecutionContext<'_>,
+    function: &str,
+) -> Result<bool, error::Error> {
+    if readline::FUNCTION_NAMES.contains(&function) {
+        Ok(true)
+    } else {
+        writeln!(
+            context.stderr(),
+            "{}: `{function}': unknown function name",
+            context.command_name
+        )?;
+        Ok(false)
+    }
+}
+
+/// Formats a list of key sequences for display, abbreviating long lists.
+fn format_key_sequence_list(key_sequences: &[Vec<u8>]) -> String {
+    const MAX_DISPLAYED: usize = 5;
+
+    let mut result = key_sequences
+        .iter()
+        .take(MAX_DISPLAYED)
+        .map(|key_sequence| std::format!("\"{}\"", readline::format_key_sequence(key_sequence)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if key_sequences.len() > MAX_DISPLAYED {
+        result.push_str(", ...");
+    } else {
+        result.push('.');
     }
+
+    result
 }
@@ -0,0 +1,61 @@
+use clap::Parser;
+use std::io::Write;
+
+use crate::{builtins, commands};
+
+/// Display the context of an active function call or sourced script.
+#[derive(Parser)]
+pub(crate) struct CallerCommand {
+    /// Number of frames to go back up the call stack; when given, the name of the calling
+    /// function is also displayed.
+    expr: Option<String>,
+}
+
+impl builtins::Command for CallerCommand {
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        let call_stack = context.shell.call_stack();
+
+        let Some(innermost) = call_stack.front() else {
+            return Ok(builtins::ExitCode::Custom(1));
+        };
+
+        // Without an expression, report the line and file from which the current function
+        // or sourced script was invoked.
+        let Some(expr) = &self.expr else {
+            let source = call_stack.get(1).map_or("NULL", |frame| frame.source());
+            writeln!(context.stdout(), "{} {source}", innermost.call_line())?;
+            return Ok(builtins::ExitCode::Success);
+        };
+
+        let Ok(frame_index) = expr.parse::<i64>() else {
+            writeln!(
+                context.stderr(),
+                "{}: {expr}: invalid number",
+                context.command_name
+            )?;
+            writeln!(context.stderr(), "caller: usage: caller [expr]")?;
+            return Ok(builtins::ExitCode::InvalidUsage);
+        };
+
+        let frames = usize::try_from(frame_index)
+            .ok()
+            .and_then(|i| Some((call_stack.get(i)?, call_stack.get(i + 1)?)));
+
+        let Some((frame, calling_frame)) = frames else {
+            return Ok(builtins::ExitCode::Custom(1));
+        };
+
+        writeln!(
+            context.stdout(),
+            "{} {} {}",
+            frame.call_line(),
+            calling_frame.name(),
+            calling_frame.source()
+        )?;
+
+        Ok(builtins::ExitCode::Success)
+    }
+}
@@ -1,11 +1,12 @@
 use clap::Parser;
 use itertools::Itertools;
+use std::borrow::Cow;
 use std::io::Write;
 
 use crate::{
     builtins, commands,
-    env::{EnvironmentLookup, EnvironmentScope},
-    error,
+    env::{self, EnvironmentLookup, EnvironmentScope},
+    error, functions,
     variables::{
         self, ArrayLiteral, ShellValue, ShellValueLiteral, ShellValueUnsetType, ShellVariable,
         ShellVariableUpdateTransform,
@@ -190,7 +191,8 @@ impl DeclareCommand {
             if let Some(func_registration) = context.shell.funcs.get(name) {
                 if self.function_names_only {
                     if self.print {
-                        writeln!(context.stdout(), "declare -f {name}")?;
+                        let attrs = Self::function_attribute_flags(func_registration);
+                        writeln!(context.stdout(), "declare -{attrs} {name}")?;
                     } else {
                         writeln!(context.stdout(), "{name}")?;
                     }
@@ -228,6 +230,7 @@ impl DeclareCommand {
         }
     }
 
+    #[allow(clippy::too_many_lines)]
     fn process_declaration(
         &self,
         context: &mut crate::commands::ExecutionContext<'_>,
@@ -238,6 +241,10 @@ impl DeclareCommand {
             || (context.shell.in_function() && !self.create_global);
 
         if self.function_names_or_defs_only || self.function_names_only {
+            if let Some(value) = self.make_traced.to_bool() {
+                return Self::update_function_trace_attribute(context, declaration, value);
+            }
+
             return self.try_display_declaration(context, declaration, verb);
         }
 
@@ -246,12 +253,63 @@ impl DeclareCommand {
             Self::declaration_to_name_and_value(declaration)?;
 
         // Figure out where we should look.
-        let lookup = if create_var_local {
+        let mut lookup = if create_var_local {
             EnvironmentLookup::OnlyInCurrentLocal
         } else {
             EnvironmentLookup::Anywhere
         };
 
+        let mut name = name;
+        let mut create_var_local = create_var_local;
+        if let Some(make_nameref) = self.make_nameref.to_bool() {
+            if make_nameref
+                && matches!(&initial_value, Some(ShellValueLiteral::Scalar(target)) if *target == name)
+            {
+                // Within a function, a local name reference may refer to a variable of the
+                // same name in a calling scope; that's only worth a warning.
+                if create_var_local {
+                    writeln!(
+                        context.stderr(),
+                        "{}: warning: {name}: circular name reference",
+                        context.command_name
+                    )?;
+                } else {
+                    writeln!(
+                        context.stderr(),
+                        "{}: {name}: nameref variable self references not allowed",
+                        context.command_name
+                    )?;
+                    return Ok(false);
+                }
+            }
+        } else if let Cow::Owned(resolved) = context
+            .shell
+            .env
+            .resolve_nameref_using_policy(name.as_str(), lookup)?
+        {
+            // Declaring a name reference updates the variable it refers to, wherever it is.
+            if let (array_name, Some(index)) = env::split_subscript(resolved.as_str()) {
+                if let Some(ShellValueLiteral::Scalar(value)) = initial_value {
+                    context.shell.env.update_or_add_array_element(
+                        array_name,
+                        index.to_owned(),
+                        value,
+                        |_| Ok(()),
+                        EnvironmentLookup::Anywhere,
+                        EnvironmentScope::Global,
+                    )?;
+                    return Ok(true);
+                }
+
+                array_name.clone_into(&mut name);
+            } else {
+                name = resolved;
+            }
+
+            lookup = EnvironmentLookup::Anywhere;
+            create_var_local = false;
+        }
+
         // Look up the variable.
         if let Some(var) = context
             .shell
@@ -498,13 +556,47 @@ impl DeclareCommand {
         Ok(())
     }
 
+    fn function_attribute_flags(registration: &functions::FunctionRegistration) -> &'static str {
+        if registration.is_trace_enabled() {
+            "ft"
+        } else {
+            "f"
+        }
+    }
+
+    fn update_function_trace_attribute(
+        context: &mut crate::commands::ExecutionContext<'_>,
+        declaration: &commands::CommandArg,
+        value: bool,
+    ) -> Result<bool, error::Error> {
+        let name = match declaration {
+            commands::CommandArg::String(s) => s,
+            commands::CommandArg::Assignment(_) => {
+                writeln!(context.stderr(), "declare: {declaration}: not found")?;
+                return Ok(false);
+            }
+        };
+
+        if let Some(registration) = context.shell.funcs.get_mut(name) {
+            if value {
+                registration.enable_trace();
+            } else {
+                registration.disable_trace();
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     fn display_matching_functions(
         &self,
         context: &mut crate::commands::ExecutionContext<'_>,
     ) -> Result<(), error::Error> {
         for (name, registration) in context.shell.funcs.iter().sorted_by_key(|v| v.0) {
             if self.function_names_only {
-                writeln!(context.stdout(), "declare -f {name}")?;
+                let attrs = Self::function_attribute_flags(registration);
+                writeln!(context.stdout(), "declare -{attrs} {name}")?;
             } else {
                 writeln!(context.stdout(), "{}", registration.definition)?;
             }
@@ -0,0 +1,92 @@
+use clap::Parser;
+use std::io::Write;
+
+use crate::{builtins, commands, jobs, sys};
+
+/// Remove jobs from the shell's job table.
+#[derive(Parser)]
+#[clap(disable_help_flag = true)]
+pub(crate) struct DisownCommand {
+    /// Keep the jobs in the table, but don't send them SIGHUP when the shell exits.
+    #[arg(short = 'h')]
+    nohup: bool,
+
+    /// Operate on all jobs.
+    #[arg(short = 'a')]
+    all_jobs: bool,
+
+    /// Operate only on running jobs.
+    #[arg(short = 'r')]
+    running_jobs: bool,
+
+    /// Specs of jobs (or process IDs) to operate on; defaults to the current job.
+    job_specs: Vec<String>,
+}
+
+impl builtins::Command for DisownCommand {
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        let mut ids = vec![];
+        let mut exit_code = builtins::ExitCode::Success;
+
+        if self.job_specs.is_empty() {
+            if self.all_jobs || self.running_jobs {
+                ids.extend(
+                    context
+                        .shell
+                        .jobs
+                        .jobs
+                        .iter()
+                        .filter(|job| {
+                            !self.running_jobs || matches!(job.state, jobs::JobState::Running)
+                        })
+                        .map(|job| job.id),
+                );
+            } else if let Some(job) = context.shell.jobs.current_job() {
+                ids.push(job.id);
+            } else {
+                writeln!(
+                    context.stderr(),
+                    "{}: current: no such job",
+                    context.command_name
+                )?;
+                exit_code = builtins::ExitCode::Custom(1);
+            }
+        }
+
+        for job_spec in &self.job_specs {
+            let job = if job_spec.starts_with('%') {
+                context.shell.jobs.resolve_job_spec(job_spec)
+            } else if let Ok(pid) = job_spec.parse::<sys::process::ProcessId>() {
+                context.shell.jobs.resolve_pid(pid)
+            } else {
+                None
+            };
+
+            if let Some(job) = job {
+                ids.push(job.id);
+            } else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {job_spec}: no such job",
+                    context.command_name
+                )?;
+                exit_code = builtins::ExitCode::Custom(1);
+            }
+        }
+
+        for id in ids {
+            if self.nohup {
+                if let Some(job) = context.shell.jobs.jobs.iter_mut().find(|j| j.id == id) {
+                    job.nohup = true;
+                }
+            } else {
+                context.shell.jobs.remove_job(id);
+            }
+        }
+
+        Ok(exit_code)
+    }
+}
@@ -31,6 +31,10 @@ impl builtins::Command for DotCommand {
             )
             .await?;
 
+        if result.exit_shell {
+            return Ok(builtins::ExitCode::ExitShell(result.exit_code));
+        }
+
         if result.exit_code != 0 {
             return Ok(builtins::ExitCode::Custom(result.exit_code));
         }
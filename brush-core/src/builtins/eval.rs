@@ -20,9 +20,16 @@ impl builtins::Command for EvalCommand {
             tracing::debug!("Applying eval to: {:?}", args_concatenated);
 
             let params = context.params.clone();
-            let exec_result = context.shell.run_string(args_concatenated, &params).await?;
+            let exec_result = context
+                .shell
+                .run_embedded_string(args_concatenated, &params)
+                .await?;
 
-            Ok(builtins::ExitCode::Custom(exec_result.exit_code))
+            if exec_result.exit_shell {
+                Ok(builtins::ExitCode::ExitShell(exec_result.exit_code))
+            } else {
+                Ok(builtins::ExitCode::Custom(exec_result.exit_code))
+            }
         } else {
             Ok(builtins::ExitCode::Success)
         }
@@ -1,4 +1,5 @@
 use clap::Parser;
+use std::io::Write;
 
 use crate::{builtins, commands};
 
@@ -14,15 +15,58 @@ impl builtins::Command for ExitCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        let code_8bit: u8;
+        exit_shell(context, self.code).await
+    }
+}
 
-        #[allow(clippy::cast_sign_loss)]
-        if let Some(code_32bit) = &self.code {
-            code_8bit = (code_32bit & 0xFF) as u8;
-        } else {
-            code_8bit = context.shell.last_exit_status;
+/// Exit a login shell.
+#[derive(Parser)]
+pub(crate) struct LogoutCommand {
+    /// The exit code to return.
+    code: Option<i32>,
+}
+
+impl builtins::Command for LogoutCommand {
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        if !context.shell.options.login_shell {
+            writeln!(
+                context.stderr(),
+                "{}: not login shell: use `exit'",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
         }
 
-        Ok(builtins::ExitCode::ExitShell(code_8bit))
+        exit_shell(context, self.code).await
     }
 }
+
+async fn exit_shell(
+    context: commands::ExecutionContext<'_>,
+    code: Option<i32>,
+) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+    if !context.shell.confirm_exit()? {
+        return Ok(builtins::ExitCode::Custom(1));
+    }
+
+    let mut code_8bit: u8;
+
+    #[allow(clippy::cast_sign_loss)]
+    if let Some(code_32bit) = &code {
+        code_8bit = (code_32bit & 0xFF) as u8;
+    } else {
+        code_8bit = context.shell.last_exit_status;
+    }
+
+    // A login shell sources the user's logout files on the way out; they may choose to
+    // exit with a different status.
+    let logout_result = context.shell.source_logout_files(&context.params).await?;
+    if logout_result.exit_shell {
+        code_8bit = logout_result.exit_code;
+    }
+
+    Ok(builtins::ExitCode::ExitShell(code_8bit))
+}
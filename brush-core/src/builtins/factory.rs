@@ -211,6 +211,7 @@ pub(crate) fn get_default_builtins(
     m.insert("cd".into(), builtin::<cd::CdCommand>());
     m.insert("command".into(), builtin::<command::CommandCommand>());
     m.insert("false".into(), builtin::<false_::FalseCommand>());
+    m.insert("fc".into(), builtin::<fc::FcCommand>());
     m.insert("fg".into(), builtin::<fg::FgCommand>());
     m.insert("getopts".into(), builtin::<getopts::GetOptsCommand>());
     m.insert("hash".into(), builtin::<hash::HashCommand>());
@@ -224,22 +225,24 @@ pub(crate) fn get_default_builtins(
     m.insert("true".into(), builtin::<true_::TrueCommand>());
     m.insert("type".into(), builtin::<type_::TypeCommand>());
     #[cfg(unix)]
+    m.insert("ulimit".into(), builtin::<ulimit::UlimitCommand>());
+    #[cfg(unix)]
     m.insert("umask".into(), builtin::<umask::UmaskCommand>());
     m.insert("unalias".into(), builtin::<unalias::UnaliasCommand>());
     m.insert("wait".into(), builtin::<wait::WaitCommand>());
 
-    // TODO: Unimplemented non-special builtins
-    m.insert("fc".into(), builtin::<unimp::UnimplementedCommand>());
-    m.insert("ulimit".into(), builtin::<unimp::UnimplementedCommand>());
-
     if !options.sh_mode {
         m.insert("builtin".into(), builtin::<builtin_::BuiltinCommand>());
+        m.insert("caller".into(), builtin::<caller::CallerCommand>());
         m.insert("declare".into(), decl_builtin::<declare::DeclareCommand>());
+        m.insert("disown".into(), builtin::<disown::DisownCommand>());
         m.insert("echo".into(), builtin::<echo::EchoCommand>());
         m.insert("enable".into(), builtin::<enable::EnableCommand>());
         m.insert("let".into(), builtin::<let_::LetCommand>());
+        m.insert("logout".into(), builtin::<exit::LogoutCommand>());
         m.insert("mapfile".into(), builtin::<mapfile::MapFileCommand>());
         m.insert("printf".into(), builtin::<printf::PrintfCommand>());
+        m.insert("readarray".into(), builtin::<mapfile::MapFileCommand>());
         m.insert("shopt".into(), builtin::<shopt::ShoptCommand>());
         m.insert("source".into(), builtin::<dot::DotCommand>().special());
         #[cfg(unix)]
@@ -258,15 +261,11 @@ pub(crate) fn get_default_builtins(
         m.insert("popd".into(), builtin::<popd::PopdCommand>());
         m.insert("pushd".into(), builtin::<pushd::PushdCommand>());
 
+        // History builtins
+        m.insert("history".into(), builtin::<history::HistoryCommand>());
+
         // Input configuration builtins
         m.insert("bind".into(), builtin::<bind::BindCommand>());
-
-        // TODO: Unimplemented builtins
-        m.insert("caller".into(), builtin::<unimp::UnimplementedCommand>());
-        m.insert("disown".into(), builtin::<unimp::UnimplementedCommand>());
-        m.insert("history".into(), builtin::<unimp::UnimplementedCommand>());
-        m.insert("logout".into(), builtin::<unimp::UnimplementedCommand>());
-        m.insert("readarray".into(), builtin::<unimp::UnimplementedCommand>());
     }
 
     //
@@ -0,0 +1,363 @@
+use clap::Parser;
+use std::io::Write;
+
+use crate::{builtins, commands, error, escape, ExecutionResult};
+
+/// Display, edit, or re-execute commands from the history list.
+#[derive(Parser)]
+pub(crate) struct FcCommand {
+    /// Editor used to edit the commands; `-` re-executes them without editing.
+    #[arg(short = 'e', value_name = "EDITOR")]
+    editor: Option<String>,
+
+    /// List the commands instead of editing them.
+    #[arg(short = 'l')]
+    list: bool,
+
+    /// Omit history numbers when listing commands.
+    #[arg(short = 'n')]
+    omit_numbers: bool,
+
+    /// Reverse the order of the commands.
+    #[arg(short = 'r')]
+    reverse: bool,
+
+    /// Re-execute a command without editing it, after applying any `old=new`
+    /// substitutions.
+    #[arg(short = 's')]
+    reexecute: bool,
+
+    /// The first and last commands of the range to operate on; with -s, any substitutions
+    /// followed by the command to re-execute.
+    #[arg(allow_negative_numbers = true)]
+    args: Vec<String>,
+}
+
+impl builtins::Command for FcCommand {
+    async fn execute(
+        &self,
+        mut context: commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        // When history is enabled, the most recent entry in the history list is this
+        // command itself; it's not a candidate for listing or editing.
+        let history_len = context.shell.history().len();
+        let current = if context.shell.options.enable_command_history {
+            history_len.saturating_sub(1)
+        } else {
+            history_len
+        };
+
+        let result = if self.list {
+            self.list_commands(&context, current)
+        } else if self.reexecute || self.editor.as_deref() == Some("-") {
+            self.reexecute_command(&mut context, current).await
+        } else {
+            self.edit_commands(&mut context, current).await
+        };
+
+        match result {
+            Ok(exit_code) => Ok(exit_code),
+            Err(FcError::Message(message)) => {
+                writeln!(context.stderr(), "{}: {message}", context.command_name)?;
+                Ok(builtins::ExitCode::Custom(1))
+            }
+            Err(FcError::Shell(e)) => Err(e),
+        }
+    }
+}
+
+enum FcError {
+    Message(&'static str),
+    Shell(error::Error),
+}
+
+impl From<error::Error> for FcError {
+    fn from(e: error::Error) -> Self {
+        Self::Shell(e)
+    }
+}
+
+impl From<std::io::Error> for FcError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Shell(e.into())
+    }
+}
+
+impl FcCommand {
+    fn list_commands(
+        &self,
+        context: &commands::ExecutionContext<'_>,
+        current: usize,
+    ) -> Result<builtins::ExitCode, FcError> {
+        let (first, last) = match self.args.as_slice() {
+            [] => ("-16", "-1"),
+            [first] => (first.as_str(), "-1"),
+            [first, last, ..] => (first.as_str(), last.as_str()),
+        };
+
+        let history = context.shell.history();
+        let (indices, reversed) = resolve_range(history, current, first, last)?;
+
+        for index in order(indices, reversed != self.reverse) {
+            let Some(item) = history.get(index) else {
+                continue;
+            };
+
+            if self.omit_numbers {
+                writeln!(context.stdout(), "\t {}", item.command_line)?;
+            } else {
+                let number = history.base() + index;
+                writeln!(context.stdout(), "{number}\t {}", item.command_line)?;
+            }
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+
+    async fn reexecute_command(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        current: usize,
+    ) -> Result<builtins::ExitCode, FcError> {
+        let mut args = self.args.iter().peekable();
+
+        let mut substitutions = vec![];
+        while let Some(substitution) = args.next_if(|arg| arg.contains('=')) {
+            if let Some((old, new)) = substitution.split_once('=') {
+                substitutions.push((old, new));
+            }
+        }
+
+        let spec = args.next().map_or("-1", |arg| arg.as_str());
+        let index = resolve_command(context.shell.history(), current, spec)?;
+
+        let mut command = context
+            .shell
+            .history()
+            .get(index)
+            .map(|item| item.command_line.clone())
+            .unwrap_or_default();
+
+        for (old, new) in substitutions {
+            if !old.is_empty() {
+                command = command.replace(old, new);
+            }
+        }
+
+        remove_from_history(context);
+        Self::run_commands(context, command.as_str()).await
+    }
+
+    async fn edit_commands(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        current: usize,
+    ) -> Result<builtins::ExitCode, FcError> {
+        let (first, last) = match self.args.as_slice() {
+            [] => ("-1", "-1"),
+            [first] => (first.as_str(), first.as_str()),
+            [first, last, ..] => (first.as_str(), last.as_str()),
+        };
+
+        let history = context.shell.history();
+        let (indices, reversed) = resolve_range(history, current, first, last)?;
+
+        let mut contents = String::new();
+        for index in order(indices, reversed != self.reverse) {
+            if let Some(item) = history.get(index) {
+                contents.push_str(item.command_line.as_str());
+                contents.push('\n');
+            }
+        }
+
+        let editor = self
+            .editor
+            .clone()
+            .or_else(|| context.shell.get_env_str("FCEDIT").map(|s| s.into_owned()))
+            .or_else(|| context.shell.get_env_str("EDITOR").map(|s| s.into_owned()))
+            .unwrap_or_else(|| "vi".to_owned());
+
+        let path = create_temp_file(contents.as_str())?;
+
+        let editor_command = format!(
+            "{editor} {}",
+            escape::quote_if_needed(
+                path.to_string_lossy().as_ref(),
+                escape::QuoteMode::SingleQuote
+            )
+        );
+
+        remove_from_history(context);
+
+        let params = context.params.clone();
+        let editor_result = context
+            .shell
+            .run_embedded_string(editor_command, &params)
+            .await;
+
+        let edited = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let editor_result = editor_result?;
+        if !editor_result.is_success() {
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
+        Self::run_commands(context, edited?.as_str()).await
+    }
+
+    /// Runs the given commands, echoing each before it's executed and adding it to the
+    /// history list.
+    async fn run_commands(
+        context: &mut commands::ExecutionContext<'_>,
+        commands: &str,
+    ) -> Result<builtins::ExitCode, FcError> {
+        let params = context.params.clone();
+        let mut result = ExecutionResult::success();
+        let mut pending = String::new();
+
+        let mut lines = commands.lines().peekable();
+        while let Some(line) = lines.next() {
+            pending.push_str(line);
+            pending.push('\n');
+
+            // Accumulate lines until we have a complete command (or run out of lines).
+            if lines.peek().is_some() && is_incomplete(context.shell, pending.as_str()) {
+                continue;
+            }
+
+            let command = std::mem::take(&mut pending);
+            if command.trim().is_empty() {
+                continue;
+            }
+
+            write!(context.stderr(), "{command}")?;
+            context.shell.add_to_history(command.as_str())?;
+
+            result = context.shell.run_string(command, &params).await?;
+            if result.exit_shell || result.return_from_function_or_script {
+                break;
+            }
+        }
+
+        if result.exit_shell {
+            Ok(builtins::ExitCode::ExitShell(result.exit_code))
+        } else if result.return_from_function_or_script {
+            Ok(builtins::ExitCode::ReturnFromFunctionOrScript(
+                result.exit_code,
+            ))
+        } else {
+            Ok(builtins::ExitCode::Custom(result.exit_code))
+        }
+    }
+}
+
+/// Removes the `fc` command itself from the history list; it's replaced there by the
+/// commands it runs.
+fn remove_from_history(context: &mut commands::ExecutionContext<'_>) {
+    if context.shell.options.enable_command_history {
+        context.shell.history_mut().remove_last();
+    }
+}
+
+/// Resolves a command specification, as given to `fc`, to an index in the history list. A
+/// positive number is a history number; a negative number is an offset back from the
+/// current command; and anything else selects the most recent command starting with it.
+/// Numbers outside of the history list are clamped to its bounds.
+fn resolve_command(history: &crate::History, current: usize, spec: &str) -> Result<usize, FcError> {
+    if current == 0 {
+        return Err(FcError::Message("history specification out of range"));
+    }
+    let last = current - 1;
+
+    if let Ok(number) = spec.parse::<i64>() {
+        let current = i64::try_from(current).unwrap_or(i64::MAX);
+        let base = i64::try_from(history.base()).unwrap_or(i64::MAX);
+
+        let index = match number {
+            0 => current - 1,
+            n if n < 0 => current + n,
+            n => n - base,
+        };
+
+        Ok(usize::try_from(index).unwrap_or(0).min(last))
+    } else {
+        (0..current)
+            .rev()
+            .find(|index| {
+                history
+                    .get(*index)
+                    .is_some_and(|item| item.command_line.starts_with(spec))
+            })
+            .ok_or(FcError::Message("no command found"))
+    }
+}
+
+/// Resolves the first and last commands of a range; if the first comes after the last, the
+/// range is swapped and reported as reversed.
+fn resolve_range(
+    history: &crate::History,
+    current: usize,
+    first: &str,
+    last: &str,
+) -> Result<(std::ops::RangeInclusive<usize>, bool), FcError> {
+    let first = resolve_command(history, current, first)?;
+    let last = resolve_command(history, current, last)?;
+
+    if first <= last {
+        Ok((first..=last, false))
+    } else {
+        Ok((last..=first, true))
+    }
+}
+
+fn order(indices: std::ops::RangeInclusive<usize>, reverse: bool) -> Vec<usize> {
+    if reverse {
+        indices.rev().collect()
+    } else {
+        indices.collect()
+    }
+}
+
+fn is_incomplete(shell: &crate::Shell, input: &str) -> bool {
+    match shell.parse_string(input.to_owned()) {
+        Err(brush_parser::ParseError::Tokenizing { inner, .. }) => inner.is_incomplete(),
+        Err(brush_parser::ParseError::ParsingAtEndOfInput) => true,
+        _ => false,
+    }
+}
+
+/// Creates a new temporary file holding the given contents, readable and writable only by
+/// the current user. The file is always freshly created, so an existing file or symlink
+/// at a guessed path is never written through.
+fn create_temp_file(contents: &str) -> Result<std::path::PathBuf, std::io::Error> {
+    const MAX_ATTEMPTS: u32 = 100;
+
+    let mut last_error = None;
+    for attempt in 0..MAX_ATTEMPTS {
+        let path = std::env::temp_dir().join(format!(
+            "brush-fc.{}.{}.{attempt}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        match options.open(&path) {
+            Ok(mut file) => {
+                if let Err(err) = file.write_all(contents.as_bytes()) {
+                    let _ = std::fs::remove_file(&path);
+                    return Err(err);
+                }
+                return Ok(path);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => last_error = Some(err),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| std::io::ErrorKind::AlreadyExists.into()))
+}
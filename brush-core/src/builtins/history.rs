@@ -0,0 +1,285 @@
+use clap::Parser;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::{builtins, commands, error};
+
+/// Display or manipulate the command history list.
+#[derive(Parser)]
+pub(crate) struct HistoryCommand {
+    /// Clear the history list.
+    #[arg(short = 'c')]
+    clear: bool,
+
+    /// Delete the history entry at the given offset, or the entries in the given range of
+    /// offsets.
+    #[arg(short = 'd', value_name = "OFFSET", allow_hyphen_values = true)]
+    delete_offset: Option<String>,
+
+    /// Append the history lines entered in this session to the history file.
+    #[arg(short = 'a')]
+    append_to_file: bool,
+
+    /// Append the lines of the history file not yet read to the history list.
+    #[arg(short = 'n')]
+    read_new_lines_from_file: bool,
+
+    /// Read the history file and append its contents to the history list.
+    #[arg(short = 'r')]
+    read_file: bool,
+
+    /// Write the current history list to the history file.
+    #[arg(short = 'w')]
+    write_file: bool,
+
+    /// Perform history expansion on the arguments and display the results, without
+    /// storing them in the history list.
+    #[arg(short = 'p')]
+    expand: bool,
+
+    /// Store the arguments in the history list as a single entry.
+    #[arg(short = 's')]
+    store: bool,
+
+    /// Arguments; their interpretation depends on the options given.
+    #[arg(allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl builtins::Command for HistoryCommand {
+    async fn execute(
+        &self,
+        mut context: commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        context.shell.apply_history_size();
+
+        if self.clear {
+            context.shell.history_mut().clear();
+            return Ok(builtins::ExitCode::Success);
+        }
+
+        if let Some(offset) = &self.delete_offset {
+            return Self::delete(&mut context, offset);
+        }
+
+        let file_ops = [
+            self.append_to_file,
+            self.read_new_lines_from_file,
+            self.read_file,
+            self.write_file,
+        ];
+        match file_ops.iter().filter(|op| **op).count() {
+            0 => (),
+            1 => return Ok(self.access_file(&mut context)),
+            _ => {
+                writeln!(
+                    context.stderr(),
+                    "{}: cannot use more than one of -anrw",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        }
+
+        if self.expand {
+            // The `history` command itself isn't retained in the history list.
+            if context.shell.options.enable_command_history {
+                context.shell.history_mut().remove_last();
+            }
+
+            let mut exit_code = builtins::ExitCode::Success;
+            for arg in &self.args {
+                if let Ok(expansion) = context.shell.expand_history(arg) {
+                    writeln!(context.stdout(), "{}", expansion.line)?;
+                } else {
+                    writeln!(
+                        context.stderr(),
+                        "{}: {arg}: history expansion failed",
+                        context.command_name
+                    )?;
+                    exit_code = builtins::ExitCode::Custom(1);
+                }
+            }
+
+            return Ok(exit_code);
+        }
+
+        if self.store {
+            // The `history` command itself is replaced by the stored entry.
+            if context.shell.options.enable_command_history {
+                context.shell.history_mut().remove_last();
+            }
+
+            if !self.args.is_empty() {
+                context.shell.add_history_entry(self.args.join(" "))?;
+            }
+
+            return Ok(builtins::ExitCode::Success);
+        }
+
+        self.display(&context)
+    }
+}
+
+impl HistoryCommand {
+    fn display(
+        &self,
+        context: &commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        if self.args.len() > 1 {
+            writeln!(
+                context.stderr(),
+                "{}: too many arguments",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
+        let history = context.shell.history();
+
+        let mut count = history.len();
+        if let Some(arg) = self.args.first() {
+            let Ok(requested) = arg.parse::<i64>() else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {arg}: numeric argument required",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            };
+
+            count = count.min(usize::try_from(requested).unwrap_or(0));
+        }
+
+        let time_format = context.shell.get_env_str("HISTTIMEFORMAT");
+
+        let first_index = history.len() - count;
+        for (index, item) in history.iter().enumerate().skip(first_index) {
+            let number = history.base() + index;
+
+            let mut time = String::new();
+            if let Some(time_format) = &time_format {
+                time = match item
+                    .timestamp
+                    .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+                {
+                    Some(timestamp) => format_timestamp(
+                        &timestamp.with_timezone(&chrono::Local),
+                        time_format.as_ref(),
+                    ),
+                    None => "??".to_owned(),
+                };
+            }
+
+            writeln!(context.stdout(), "{number:5}  {time}{}", item.command_line)?;
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+
+    fn delete(
+        context: &mut commands::ExecutionContext<'_>,
+        offset: &str,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let history = context.shell.history();
+
+        // A range is given as `start-end`, where either bound may itself be negative.
+        let range = offset
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '-')
+            .and_then(|(i, _)| {
+                let start = resolve_offset(history, &offset[..i])?;
+                let end = resolve_offset(history, &offset[i + 1..])?;
+                Some((start, end))
+            });
+
+        let Some((start, end)) = range.or_else(|| {
+            let index = resolve_offset(history, offset)?;
+            Some((index, index))
+        }) else {
+            writeln!(
+                context.stderr(),
+                "{}: {offset}: history position out of range",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        };
+
+        for index in (start..=end).rev() {
+            context.shell.history_mut().remove(index);
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+
+    fn access_file(&self, context: &mut commands::ExecutionContext<'_>) -> builtins::ExitCode {
+        let path = match self.args.first() {
+            Some(path) => Some(
+                context
+                    .shell
+                    .get_absolute_path(PathBuf::from(path).as_path()),
+            ),
+            None => context.shell.get_history_file_path(),
+        };
+
+        let Some(path) = path else {
+            return builtins::ExitCode::Success;
+        };
+
+        let multiline_items = context
+            .shell
+            .options
+            .embed_newlines_in_multiline_cmds_in_history;
+        let write_timestamps = context.shell.should_write_history_timestamps();
+
+        let history = context.shell.history_mut();
+        let result = if self.append_to_file {
+            history.write_file(&path, true, write_timestamps)
+        } else if self.write_file {
+            history.write_file(&path, false, write_timestamps)
+        } else {
+            history.read_file(&path, self.read_new_lines_from_file, multiline_items)
+        };
+
+        if result.is_err() {
+            return builtins::ExitCode::Custom(1);
+        }
+
+        context.shell.apply_history_size();
+
+        builtins::ExitCode::Success
+    }
+}
+
+/// Resolves a history offset, as given to `history -d`, to an index in the history list. A
+/// positive offset is a history number; a negative offset counts back from the end of the list.
+fn resolve_offset(history: &crate::History, offset: &str) -> Option<usize> {
+    let offset = offset.parse::<i64>().ok()?;
+
+    let index = if offset < 0 {
+        history
+            .len()
+            .checked_sub(usize::try_from(offset.unsigned_abs()).ok()?)?
+    } else {
+        usize::try_from(offset).ok()?.checked_sub(history.base())?
+    };
+
+    (index < history.len()).then_some(index)
+}
+
+fn format_timestamp(timestamp: &chrono::DateTime<chrono::Local>, format: &str) -> String {
+    // N.B. An unsupported format yields no output.
+    let mut formatted = String::new();
+    let items = chrono::format::StrftimeItems::new(format);
+    if std::fmt::Write::write_fmt(
+        &mut formatted,
+        format_args!("{}", timestamp.format_with_items(items)),
+    )
+    .is_err()
+    {
+        formatted.clear();
+    }
+
+    formatted
+}
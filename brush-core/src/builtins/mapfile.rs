@@ -1,26 +1,26 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
 use clap::Parser;
 
-use crate::{builtins, commands, env, error, openfiles, sys, variables};
+use crate::{builtins, commands, env, error, escape, openfiles, sys, variables};
 
-/// Inspect and modify key bindings and other input configuration.
+/// Read lines from standard input into an indexed array.
 #[derive(Parser)]
 pub(crate) struct MapFileCommand {
-    /// Delimiter to use (defaults to newline).
+    /// Delimiter to use (defaults to newline); if empty, the NUL character is used.
     #[arg(short = 'd', default_value = "\n")]
     delimiter: String,
 
     /// Maximum number of entries to read (0 means no limit).
-    #[arg(short = 'n', default_value = "0")]
+    #[arg(short = 'n', default_value = "0", allow_negative_numbers = true)]
     max_count: i64,
 
     /// Index into array at which to start assignment.
-    #[arg(short = 'O')]
+    #[arg(short = 'O', allow_negative_numbers = true)]
     origin: Option<i64>,
 
     /// Number of initial entries to skip.
-    #[arg(short = 's', default_value = "0")]
+    #[arg(short = 's', default_value = "0", allow_negative_numbers = true)]
     skip_count: i64,
 
     /// Whether or not to remove the delimiter from each read line.
@@ -31,115 +31,224 @@ pub(crate) struct MapFileCommand {
     #[arg(short = 'u', default_value = "0")]
     fd: u32,
 
-    /// Name of function to call for each group of lines.
+    /// Command to evaluate for each group of lines; it's passed the index of the next
+    /// array element to be assigned and the line to be assigned to it.
     #[arg(short = 'C')]
     callback: Option<String>,
 
     /// Number of lines to pass the callback for each group.
-    #[arg(short = 'c', default_value = "5000")]
+    #[arg(short = 'c', default_value = "5000", allow_negative_numbers = true)]
     callback_group_size: i64,
 
     /// Name of array to read into.
+    #[arg(default_value = "MAPFILE")]
     array_var_name: String,
 }
 
 impl builtins::Command for MapFileCommand {
     async fn execute(
         &self,
-        context: commands::ExecutionContext<'_>,
+        mut context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, error::Error> {
-        if self.delimiter != "\n" {
-            // This will require reading a single char at a time and stoping as soon as
-            // the delimiter is hit.
-            return error::unimp("mapfile with non-newline delimiter not yet implemented");
+        if self.callback_group_size <= 0 {
+            writeln!(
+                context.stderr(),
+                "{}: {}: invalid callback quantum",
+                context.command_name,
+                self.callback_group_size
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
         }
 
-        if self.max_count != 0 {
-            return error::unimp("mapfile -n is not yet implemented");
-        }
-
-        if self.origin.is_some() {
-            // This will require merging into a potentially already-existing array.
-            return error::unimp("mapfile -O is not yet implemented");
+        for count in [self.max_count, self.skip_count] {
+            if count < 0 {
+                writeln!(
+                    context.stderr(),
+                    "{}: {count}: invalid line count",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
         }
 
-        if self.skip_count != 0 {
-            return error::unimp("mapfile -s is not yet implemented");
+        if let Some(origin) = self.origin.filter(|origin| *origin < 0) {
+            writeln!(
+                context.stderr(),
+                "{}: {origin}: invalid array origin",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
         }
 
-        if self.callback.is_some() {
-            return error::unimp("mapfile -C is not yet implemented");
+        if context
+            .shell
+            .env
+            .get(&self.array_var_name)
+            .is_some_and(|(_, var)| {
+                matches!(
+                    var.value(),
+                    variables::ShellValue::AssociativeArray(_)
+                        | variables::ShellValue::Unset(
+                            variables::ShellValueUnsetType::AssociativeArray
+                        )
+                )
+            })
+        {
+            writeln!(
+                context.stderr(),
+                "{}: {}: not an indexed array",
+                context.command_name,
+                self.array_var_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
         }
 
-        let input_file = context
+        let mut input_file = context
             .params
             .fd(self.fd)
             .ok_or_else(|| error::Error::BadFileDescriptor(self.fd))?;
 
-        // Read!
-        let results = self.read_entries(input_file)?;
+        // Unless asked to add to the array at a given origin, we replace its contents.
+        if self.origin.is_none() {
+            context.shell.env.update_or_add(
+                &self.array_var_name,
+                variables::ShellValueLiteral::Array(variables::ArrayLiteral(vec![])),
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+        }
+
+        let orig_term_attr = setup_terminal_settings(&input_file)?;
 
-        // Assign!
-        context.shell.env.update_or_add(
-            &self.array_var_name,
-            variables::ShellValueLiteral::Array(results),
-            |_| Ok(()),
-            env::EnvironmentLookup::Anywhere,
-            env::EnvironmentScope::Global,
-        )?;
+        let result = self
+            .read_into_array(&mut context, &mut input_file, orig_term_attr.is_some())
+            .await;
 
-        Ok(builtins::ExitCode::Success)
+        if let Some(orig_term_attr) = &orig_term_attr {
+            input_file.set_term_attr(orig_term_attr)?;
+        }
+
+        result
     }
 }
 
 impl MapFileCommand {
-    fn read_entries(
+    async fn read_into_array(
         &self,
-        mut input_file: openfiles::OpenFile,
-    ) -> Result<variables::ArrayLiteral, error::Error> {
-        let mut entries = vec![];
+        context: &mut commands::ExecutionContext<'_>,
+        input_file: &mut openfiles::OpenFile,
+        is_terminal: bool,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        // An empty delimiter means the NUL character; otherwise, only the first byte of the
+        // delimiter is used.
+        let delimiter = self.delimiter.bytes().next().unwrap_or(b'\0');
+
+        for _ in 0..self.skip_count {
+            if self
+                .read_entry(input_file, delimiter, is_terminal)?
+                .is_none()
+            {
+                return Ok(builtins::ExitCode::Success);
+            }
+        }
 
-        let orig_term_attr = setup_terminal_settings(&input_file)?;
+        let mut index = self.origin.unwrap_or(0);
+        let mut line_count: i64 = 1;
+
+        while let Some(mut entry) = self.read_entry(input_file, delimiter, is_terminal)? {
+            // N.B. A NUL delimiter can't be stored in a shell variable, so it's always removed.
+            if (self.remove_delimiter || delimiter == b'\0') && entry.last() == Some(&delimiter) {
+                entry.pop();
+            }
+
+            let entry = String::from_utf8_lossy(&entry).into_owned();
+
+            if let Some(callback) = &self.callback {
+                if line_count % self.callback_group_size == 0 {
+                    let quote_options = escape::QuoteOptions {
+                        always_quote: true,
+                        preferred_mode: escape::QuoteMode::SingleQuote,
+                        avoid_ansi_c_quoting_newline: true,
+                    };
+                    let command = std::format!(
+                        "{callback} {index} {}",
+                        escape::quote(&entry, &quote_options)
+                    );
+
+                    let params = context.params.clone();
+                    let result = context.shell.run_embedded_string(command, &params).await?;
+                    if result.exit_shell {
+                        return Ok(builtins::ExitCode::ExitShell(result.exit_code));
+                    }
+                }
+            }
 
-        let mut current_entry = String::new();
+            context.shell.env.update_or_add_array_element(
+                &self.array_var_name,
+                index.to_string(),
+                entry,
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+
+            index += 1;
+            line_count += 1;
+
+            if self.max_count != 0 && line_count > self.max_count {
+                break;
+            }
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+
+    /// Reads the next entry from the given file, including its terminating delimiter (if
+    /// it has one). Returns `None` once there are no more entries.
+    #[allow(clippy::unused_self)]
+    fn read_entry(
+        &self,
+        input_file: &mut openfiles::OpenFile,
+        delimiter: u8,
+        is_terminal: bool,
+    ) -> Result<Option<Vec<u8>>, error::Error> {
+        let mut entry = vec![];
         let mut buffer: [u8; 1] = [0; 1]; // 1-byte buffer
 
+        // N.B. We read a byte at a time so we don't consume any input past the entry; this
+        // matters when input comes from a pipe shared with other commands.
         loop {
-            // TODO: Figure out how to restore terminal settings on error?
             let n = input_file.read(&mut buffer)?;
             if n == 0 {
                 // EOF reached.
                 break;
             }
 
-            let ch = buffer[0] as char;
+            let byte = buffer[0];
 
-            // Check for Ctrl+C.
-            if ch == '\x03' {
-                break;
-            // Ctrl+D is EOF *if* there's no entry in progress.
-            } else if ch == '\x04' && current_entry.is_empty() {
-                break;
+            if is_terminal {
+                // Check for Ctrl+C.
+                if byte == b'\x03' {
+                    return Ok(None);
+                // Ctrl+D is EOF *if* there's no entry in progress.
+                } else if byte == b'\x04' && entry.is_empty() {
+                    break;
+                }
             }
 
-            // Check for a delimiting newline char.
-            // TODO: Support other delimiters.
-            if ch == '\n' {
-                if !self.remove_delimiter {
-                    current_entry.push(ch);
-                }
+            entry.push(byte);
 
-                entries.push((None, std::mem::take(&mut current_entry)));
-            } else {
-                current_entry.push(ch);
+            if byte == delimiter {
+                break;
             }
         }
 
-        if let Some(orig_term_attr) = &orig_term_attr {
-            input_file.set_term_attr(orig_term_attr)?;
+        if entry.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(entry))
         }
-
-        Ok(variables::ArrayLiteral(entries))
     }
 }
 
@@ -1,7 +1,7 @@
 use clap::Parser;
 use std::io::Write;
 
-use crate::{builtins, commands, escape, expansion};
+use crate::{builtins, commands, error, escape, expansion, openfiles};
 
 /// Format a string.
 #[derive(Parser)]
@@ -21,85 +21,942 @@ impl builtins::Command for PrintfCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        let result = self.evaluate(&context)?;
+        let Some((format, args)) = self.format_and_args.split_first() else {
+            writeln!(
+                context.stderr(),
+                "{}: usage: printf [-v var] format [arguments]",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::InvalidUsage);
+        };
+
+        if let Some(variable_name) = &self.output_variable {
+            if !is_valid_output_variable(context.shell, variable_name) {
+                writeln!(
+                    context.stderr(),
+                    "{}: `{variable_name}': not a valid identifier",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::InvalidUsage);
+            }
+        }
+
+        // N.B. Output is only sent to stdout if it's not being assigned to a variable.
+        let mut formatter = Formatter {
+            command_name: context.command_name.as_str(),
+            stdout: self.output_variable.is_none().then(|| context.stdout()),
+            stderr: context.stderr(),
+            start_time: context.shell.start_time(),
+            args,
+            next_arg: 0,
+            output: vec![],
+            failed: false,
+        };
+
+        formatter.format(format)?;
+        formatter.flush()?;
+
+        let Formatter { output, failed, .. } = formatter;
 
         if let Some(variable_name) = &self.output_variable {
             expansion::assign_to_named_parameter(
                 context.shell,
                 &context.params,
                 variable_name,
-                result,
+                String::from_utf8_lossy(&output).into_owned(),
             )
             .await?;
+        }
+
+        if failed {
+            Ok(builtins::ExitCode::Custom(1))
         } else {
-            write!(context.stdout(), "{result}")?;
-            context.stdout().flush()?;
+            Ok(builtins::ExitCode::Success)
+        }
+    }
+}
+
+fn is_valid_output_variable(shell: &crate::Shell, name: &str) -> bool {
+    matches!(
+        brush_parser::word::parse_parameter(name, &shell.parser_options()),
+        Ok(brush_parser::word::Parameter::Named(_)
+            | brush_parser::word::Parameter::NamedWithIndex { .. })
+    )
+}
+
+/// A piece of a parsed format string.
+enum FormatPiece {
+    /// Literal text, with backslash escapes not yet expanded.
+    Literal(String),
+    /// A conversion directive (e.g., `%-5s`).
+    Directive(Directive),
+    /// A directive with an invalid conversion character.
+    InvalidDirective(char),
+    /// A `%` at the end of the format string.
+    MissingConversion,
+}
+
+/// A width or precision given in a directive.
+#[derive(Clone, Copy)]
+enum Count {
+    /// The count was given literally in the directive.
+    Literal(usize),
+    /// The count is taken from the next argument (i.e., `*`).
+    FromArg,
+}
+
+#[derive(Default)]
+struct DirectiveFlags {
+    left_justify: bool,
+    plus_sign: bool,
+    space_sign: bool,
+    alternate_form: bool,
+    zero_pad: bool,
+}
+
+struct Directive {
+    flags: DirectiveFlags,
+    width: Option<Count>,
+    precision: Option<Count>,
+    conversion: char,
+    /// The `strftime`-style format given to a `%(...)T` directive.
+    date_format: Option<String>,
+}
+
+/// Parses a format string into its pieces.
+fn parse_format(format: &str) -> Vec<FormatPiece> {
+    let mut pieces = vec![];
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            // Keep escape sequences intact for later expansion; a '%' is never escaped.
+            literal.push(c);
+            if let Some(next) = chars.next_if(|next| *next != '%') {
+                literal.push(next);
+            }
+            continue;
+        } else if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        if chars.next_if_eq(&'%').is_some() {
+            literal.push('%');
+            continue;
         }
 
-        Ok(builtins::ExitCode::Success)
+        if !literal.is_empty() {
+            pieces.push(FormatPiece::Literal(std::mem::take(&mut literal)));
+        }
+
+        let mut flags = DirectiveFlags::default();
+        while let Some(flag) = chars.next_if(|c| matches!(c, '-' | '+' | ' ' | '#' | '0' | '\'')) {
+            match flag {
+                '-' => flags.left_justify = true,
+                '+' => flags.plus_sign = true,
+                ' ' => flags.space_sign = true,
+                '#' => flags.alternate_form = true,
+                '0' => flags.zero_pad = true,
+                // Thousands grouping has no effect outside of locales that define it.
+                _ => (),
+            }
+        }
+
+        let width = parse_count(&mut chars);
+        let precision = if chars.next_if_eq(&'.').is_some() {
+            Some(parse_count(&mut chars).unwrap_or(Count::Literal(0)))
+        } else {
+            None
+        };
+
+        // Length modifiers are accepted but have no effect.
+        while chars
+            .next_if(|c| matches!(c, 'h' | 'j' | 'l' | 'L' | 't' | 'z'))
+            .is_some()
+        {}
+
+        let date_format = if chars.next_if_eq(&'(').is_some() {
+            Some(chars.by_ref().take_while(|c| *c != ')').collect())
+        } else {
+            None
+        };
+
+        let piece = match chars.next() {
+            Some('T') if date_format.is_some() => FormatPiece::Directive(Directive {
+                flags,
+                width,
+                precision,
+                conversion: 'T',
+                date_format,
+            }),
+            Some(
+                conversion @ ('d' | 'i' | 'o' | 'u' | 'x' | 'X' | 'e' | 'E' | 'f' | 'F' | 'g' | 'G'
+                | 'a' | 'A' | 'c' | 's' | 'b' | 'q' | 'Q'),
+            ) if date_format.is_none() => FormatPiece::Directive(Directive {
+                flags,
+                width,
+                precision,
+                conversion,
+                date_format,
+            }),
+            Some(c) => FormatPiece::InvalidDirective(c),
+            None => FormatPiece::MissingConversion,
+        };
+
+        pieces.push(piece);
+    }
+
+    if !literal.is_empty() {
+        pieces.push(FormatPiece::Literal(literal));
     }
+
+    pieces
 }
 
-impl PrintfCommand {
-    fn evaluate(
-        &self,
-        context: &commands::ExecutionContext<'_>,
-    ) -> Result<String, crate::error::Error> {
-        match self.format_and_args.as_slice() {
-            // Special-case common format string: "%s".
-            [fmt, arg] if fmt == "%s" => Ok(arg.clone()),
-            // Special-case invocation of printf with %q-based format string from bash-completion.
-            // It has hard-coded expectation of backslash-style escaping instead of quoting.
-            [fmt, arg] if fmt == "%q" => Ok(Self::evaluate_format_with_percent_q(None, arg)),
-            [fmt, arg] if fmt == "~%q" => Ok(Self::evaluate_format_with_percent_q(Some("~"), arg)),
-            // Fallback to external command.
-            _ => self.evaluate_via_external_command(context),
+fn parse_count(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<Count> {
+    if chars.next_if_eq(&'*').is_some() {
+        return Some(Count::FromArg);
+    }
+
+    let mut count: Option<usize> = None;
+    while let Some(digit) = chars
+        .next_if(char::is_ascii_digit)
+        .and_then(|c| c.to_digit(10))
+    {
+        count = Some(
+            count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit as usize),
+        );
+    }
+
+    count.map(Count::Literal)
+}
+
+/// Whether formatting should continue after a piece of the format string is processed.
+#[derive(PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Stop,
+}
+
+/// Applies a format string to arguments, accumulating the output.
+struct Formatter<'a> {
+    command_name: &'a str,
+    stdout: Option<openfiles::OpenFile>,
+    stderr: openfiles::OpenFile,
+    start_time: std::time::SystemTime,
+    args: &'a [String],
+    next_arg: usize,
+    output: Vec<u8>,
+    /// Whether a (non-fatal) error has been encountered.
+    failed: bool,
+}
+
+impl<'a> Formatter<'a> {
+    /// Formats the arguments, reusing the format string as many times as needed to
+    /// consume all of them.
+    fn format(&mut self, format: &str) -> Result<(), error::Error> {
+        let pieces = parse_format(format);
+
+        loop {
+            let first_arg = self.next_arg;
+
+            for piece in &pieces {
+                if self.apply(piece)? == Flow::Stop {
+                    return Ok(());
+                }
+            }
+
+            // Output is written out after each pass through the format.
+            self.flush()?;
+
+            // Stop if all arguments have been consumed, or if the format doesn't consume any.
+            if self.next_arg >= self.args.len() || self.next_arg == first_arg {
+                return Ok(());
+            }
+        }
+    }
+
+    fn apply(&mut self, piece: &FormatPiece) -> Result<Flow, error::Error> {
+        match piece {
+            FormatPiece::Literal(text) => {
+                let (expanded, _) = escape::expand_backslash_escapes(
+                    text,
+                    escape::EscapeExpansionMode::PrintfFormat,
+                )?;
+                self.output.extend(expanded);
+                Ok(Flow::Continue)
+            }
+            FormatPiece::Directive(directive) => self.apply_directive(directive),
+            FormatPiece::InvalidDirective(c) => {
+                self.report_error(format!("`{c}': invalid format character"))?;
+                Ok(Flow::Stop)
+            }
+            FormatPiece::MissingConversion => {
+                self.report_error("`%': missing format character")?;
+                Ok(Flow::Stop)
+            }
         }
     }
 
-    fn evaluate_format_with_percent_q(prefix: Option<&str>, arg: &str) -> String {
-        let mut result =
-            escape::quote_if_needed(arg, escape::QuoteMode::BackslashEscape).to_string();
+    fn apply_directive(&mut self, directive: &Directive) -> Result<Flow, error::Error> {
+        let mut left_justify = directive.flags.left_justify;
+
+        let width = match directive.width {
+            Some(Count::Literal(width)) => width,
+            Some(Count::FromArg) => {
+                // A negative width requests left justification.
+                let width = self.next_signed_arg()?;
+                if width < 0 {
+                    left_justify = true;
+                }
+                usize::try_from(width.unsigned_abs()).unwrap_or(usize::MAX)
+            }
+            None => 0,
+        };
+
+        let precision = match directive.precision {
+            Some(Count::Literal(precision)) => Some(precision),
+            // A negative precision is treated as if it were omitted.
+            Some(Count::FromArg) => usize::try_from(self.next_signed_arg()?).ok(),
+            None => None,
+        };
+
+        let mut flow = Flow::Continue;
+
+        let field = match directive.conversion {
+            'd' | 'i' => {
+                let value = self.next_signed_arg()?;
+                let sign = sign_prefix(value < 0, &directive.flags);
+                let digits = format_integer_digits(value.unsigned_abs(), 10, false, precision);
+                Field::numeric(sign, digits, precision.is_none())
+            }
+            conversion @ ('o' | 'u' | 'x' | 'X') => {
+                let value = self.next_unsigned_arg()?;
+                let (radix, uppercase) = match conversion {
+                    'o' => (8, false),
+                    'u' => (10, false),
+                    'x' => (16, false),
+                    _ => (16, true),
+                };
+
+                let mut digits = format_integer_digits(value, radix, uppercase, precision);
+                let mut prefix = "";
+                if directive.flags.alternate_form {
+                    if radix == 8 && !digits.starts_with('0') {
+                        digits.insert(0, '0');
+                    } else if radix == 16 && value != 0 {
+                        prefix = if uppercase { "0X" } else { "0x" };
+                    }
+                }
+
+                Field::numeric(prefix, digits, precision.is_none())
+            }
+            conversion @ ('e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'a' | 'A') => {
+                let value = self.next_float_arg()?;
+                let sign = sign_prefix(value.is_sign_negative(), &directive.flags);
+                let body = format_float(value.abs(), conversion, precision, &directive.flags);
+                Field::numeric(sign, body, value.is_finite())
+            }
+            'c' => {
+                let byte = self.next_arg().and_then(|arg| arg.bytes().next());
+                Field::text(vec![byte.unwrap_or(0)])
+            }
+            's' => {
+                let arg = self.next_arg().unwrap_or_default();
+                Field::text(truncate(arg.as_bytes(), precision).to_vec())
+            }
+            'b' => {
+                let arg = self.next_arg().unwrap_or_default();
+                let (mut expanded, keep_going) = escape::expand_backslash_escapes(
+                    arg,
+                    escape::EscapeExpansionMode::PrintfArgument,
+                )?;
+                if !keep_going {
+                    flow = Flow::Stop;
+                }
 
-        if let Some(prefix) = prefix {
-            result.insert_str(0, prefix);
+                expanded.truncate(truncate(&expanded, precision).len());
+                Field::text(expanded)
+            }
+            'q' => {
+                let quoted = quote(self.next_arg().unwrap_or_default());
+                Field::text(truncate(quoted.as_bytes(), precision).to_vec())
+            }
+            'Q' => {
+                // The precision applies to the argument before it's quoted.
+                let arg = self.next_arg().unwrap_or_default();
+                let mut end = truncate(arg.as_bytes(), precision).len();
+                while !arg.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                Field::text(quote(&arg[..end]).into_bytes())
+            }
+            'T' => {
+                let date_format = directive.date_format.as_deref().unwrap_or_default();
+                let formatted = self.format_date_time(date_format)?;
+                Field::text(truncate(formatted.as_bytes(), precision).to_vec())
+            }
+            _ => unreachable!("unexpected conversion character"),
+        };
+
+        field.write(
+            &mut self.output,
+            width,
+            left_justify,
+            directive.flags.zero_pad,
+        );
+
+        Ok(flow)
+    }
+
+    fn next_arg(&mut self) -> Option<&'a str> {
+        let arg = self.args.get(self.next_arg)?;
+        self.next_arg += 1;
+        Some(arg.as_str())
+    }
+
+    fn next_signed_arg(&mut self) -> Result<i64, error::Error> {
+        let Some(arg) = self.next_arg() else {
+            return Ok(0);
+        };
+
+        let value = self.parse_integer_arg(arg)?;
+        if let Ok(value) = i64::try_from(value) {
+            Ok(value)
+        } else {
+            self.report_warning(format!("{arg}: Numerical result out of range"))?;
+            Ok(if value < 0 { i64::MIN } else { i64::MAX })
         }
+    }
+
+    fn next_unsigned_arg(&mut self) -> Result<u64, error::Error> {
+        let Some(arg) = self.next_arg() else {
+            return Ok(0);
+        };
 
-        result
+        let value = self.parse_integer_arg(arg)?;
+        if let Ok(magnitude) = u64::try_from(value.unsigned_abs()) {
+            // Negative values wrap around, as with strtoumax(3).
+            Ok(if value < 0 {
+                magnitude.wrapping_neg()
+            } else {
+                magnitude
+            })
+        } else {
+            self.report_warning(format!("{arg}: Numerical result out of range"))?;
+            Ok(u64::MAX)
+        }
     }
 
-    #[allow(clippy::unwrap_in_result)]
-    fn evaluate_via_external_command(
-        &self,
-        context: &commands::ExecutionContext<'_>,
-    ) -> Result<String, crate::error::Error> {
-        // TODO: Don't call external printf command.
-        let mut cmd = std::process::Command::new("printf");
+    /// Parses an integer argument the way strtoimax(3) would (i.e., accepting leading
+    /// whitespace, a sign, and a hexadecimal or octal prefix), except that an argument
+    /// starting with a quote yields the code of the character that follows it. Reports
+    /// an error if the argument isn't entirely numeric, and yields the value of whatever
+    /// prefix of it is.
+    fn parse_integer_arg(&mut self, arg: &str) -> Result<i128, error::Error> {
+        if let Some(value) = quoted_char_value(arg) {
+            return Ok(i128::from(value));
+        }
+
+        if arg.is_empty() {
+            return Ok(0);
+        }
+
+        let (value, consumed) = parse_integer_prefix(arg);
+        if consumed < arg.len() {
+            self.report_error(format!("{arg}: invalid number"))?;
+        }
+
+        Ok(value)
+    }
+
+    fn next_float_arg(&mut self) -> Result<f64, error::Error> {
+        let Some(arg) = self.next_arg() else {
+            return Ok(0.0);
+        };
+
+        if let Some(value) = quoted_char_value(arg) {
+            return Ok(f64::from(value));
+        }
+
+        if arg.is_empty() {
+            return Ok(0.0);
+        }
+
+        let (value, consumed) = parse_float_prefix(arg);
+        if consumed < arg.len() {
+            self.report_error(format!("{arg}: invalid number"))?;
+        }
+
+        Ok(value)
+    }
+
+    fn format_date_time(&mut self, date_format: &str) -> Result<String, error::Error> {
+        // A missing argument (or -1) means the current time; -2 means the time the shell
+        // was started.
+        let time = match self.next_arg() {
+            Some(arg) => match self.parse_integer_arg(arg)? {
+                -1 => chrono::Local::now(),
+                -2 => chrono::DateTime::<chrono::Local>::from(self.start_time),
+                seconds => i64::try_from(seconds)
+                    .ok()
+                    .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+                    .unwrap_or_default()
+                    .with_timezone(&chrono::Local),
+            },
+            None => chrono::Local::now(),
+        };
+
+        let date_format = if date_format.is_empty() {
+            "%X"
+        } else {
+            date_format
+        };
+
+        // N.B. An unsupported format yields no output.
+        let mut formatted = String::new();
+        let items = chrono::format::StrftimeItems::new(date_format);
+        if std::fmt::Write::write_fmt(
+            &mut formatted,
+            format_args!("{}", time.format_with_items(items)),
+        )
+        .is_err()
+        {
+            formatted.clear();
+        }
+
+        Ok(formatted)
+    }
 
-        // Clear all environment variables except the PATH we had on launch.
-        // This latter variable is preserved in case the default fallback
-        // PATH isn't sufficient to find printf.
-        cmd.env_clear();
-        if let Ok(orig_path) = std::env::var("PATH") {
-            cmd.env("PATH", orig_path);
+    /// Writes any output accumulated so far to stdout (unless it's destined for a variable).
+    fn flush(&mut self) -> Result<(), error::Error> {
+        if let Some(stdout) = &mut self.stdout {
+            stdout.write_all(&self.output)?;
+            stdout.flush()?;
+            self.output.clear();
         }
 
-        cmd.args(&self.format_and_args);
+        Ok(())
+    }
 
-        let output = cmd.output()?;
+    fn report_error(&mut self, message: impl std::fmt::Display) -> Result<(), error::Error> {
+        self.failed = true;
+        writeln!(self.stderr, "{}: {message}", self.command_name)?;
+        Ok(())
+    }
 
-        let stdout = String::from_utf8(output.stdout)?;
-        let stderr = String::from_utf8(output.stderr)?;
+    fn report_warning(&mut self, message: impl std::fmt::Display) -> Result<(), error::Error> {
+        writeln!(self.stderr, "{}: warning: {message}", self.command_name)?;
+        Ok(())
+    }
+}
+
+/// A formatted field, awaiting padding to the requested width.
+struct Field {
+    /// Sign or radix prefix, which precedes any zero padding.
+    prefix: &'static str,
+    body: Vec<u8>,
+    /// Whether the field may be padded with zeros instead of spaces.
+    zero_pad_allowed: bool,
+}
+
+impl Field {
+    fn numeric(prefix: &'static str, body: String, zero_pad_allowed: bool) -> Self {
+        Self {
+            prefix,
+            body: body.into_bytes(),
+            zero_pad_allowed,
+        }
+    }
+
+    fn text(body: Vec<u8>) -> Self {
+        Self {
+            prefix: "",
+            body,
+            zero_pad_allowed: false,
+        }
+    }
 
-        write!(context.stderr(), "{stderr}")?;
-        context.stderr().flush()?;
+    fn write(self, output: &mut Vec<u8>, width: usize, left_justify: bool, zero_pad: bool) {
+        let padding = width.saturating_sub(self.prefix.len() + self.body.len());
 
-        if output.status.success() {
-            Ok(stdout)
+        if left_justify {
+            output.extend(self.prefix.as_bytes());
+            output.extend(self.body);
+            output.resize(output.len() + padding, b' ');
+        } else if zero_pad && self.zero_pad_allowed {
+            output.extend(self.prefix.as_bytes());
+            output.resize(output.len() + padding, b'0');
+            output.extend(self.body);
         } else {
-            Err(crate::error::Error::PrintfFailure(
-                output.status.code().unwrap(),
-            ))
+            output.resize(output.len() + padding, b' ');
+            output.extend(self.prefix.as_bytes());
+            output.extend(self.body);
+        }
+    }
+}
+
+fn sign_prefix(negative: bool, flags: &DirectiveFlags) -> &'static str {
+    if negative {
+        "-"
+    } else if flags.plus_sign {
+        "+"
+    } else if flags.space_sign {
+        " "
+    } else {
+        ""
+    }
+}
+
+fn truncate(bytes: &[u8], precision: Option<usize>) -> &[u8] {
+    match precision {
+        Some(precision) if precision < bytes.len() => &bytes[..precision],
+        _ => bytes,
+    }
+}
+
+fn quote(s: &str) -> String {
+    if s.is_empty() {
+        "''".to_owned()
+    } else {
+        escape::quote_if_needed(s, escape::QuoteMode::BackslashEscape).into_owned()
+    }
+}
+
+/// Returns the code of the character following a leading quote in the given argument,
+/// if it has one.
+fn quoted_char_value(arg: &str) -> Option<u32> {
+    let mut chars = arg.chars();
+    match chars.next() {
+        Some('\'' | '"') => Some(chars.next().map_or(0, u32::from)),
+        _ => None,
+    }
+}
+
+/// Parses the longest prefix of the given string that's an integer, returning its value
+/// and the number of bytes it spans (0 if there's no such prefix).
+fn parse_integer_prefix(s: &str) -> (i128, usize) {
+    let bytes = s.as_bytes();
+    let mut i = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+
+    let negative = match bytes.get(i) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+
+    let radix = if bytes.get(i) == Some(&b'0')
+        && matches!(bytes.get(i + 1), Some(b'x' | b'X'))
+        && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit)
+    {
+        i += 2;
+        16
+    } else if bytes.get(i) == Some(&b'0') {
+        8
+    } else {
+        10
+    };
+
+    let mut value: i128 = 0;
+    let mut digit_count = 0;
+    while let Some(digit) = bytes.get(i).and_then(|b| char::from(*b).to_digit(radix)) {
+        value = value
+            .saturating_mul(i128::from(radix))
+            .saturating_add(i128::from(digit));
+        digit_count += 1;
+        i += 1;
+    }
+
+    if digit_count == 0 {
+        (0, 0)
+    } else if negative {
+        (-value, i)
+    } else {
+        (value, i)
+    }
+}
+
+/// Parses the longest prefix of the given string that's a floating-point number, returning
+/// its value and the number of bytes it spans (0 if there's no such prefix).
+fn parse_float_prefix(s: &str) -> (f64, usize) {
+    let bytes = s.as_bytes();
+    let start = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+
+    let mut i = start;
+    if matches!(bytes.get(i), Some(b'-' | b'+')) {
+        i += 1;
+    }
+
+    let rest = &s[i..];
+    for special in ["infinity", "inf", "nan"] {
+        if rest
+            .get(..special.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(special))
+        {
+            let end = i + special.len();
+            let value = if special == "nan" {
+                f64::NAN
+            } else {
+                f64::INFINITY
+            };
+            let value = if bytes[start] == b'-' { -value } else { value };
+            return (value, end);
+        }
+    }
+
+    // Hexadecimal values are accepted, albeit only as integers.
+    if rest.starts_with("0x") || rest.starts_with("0X") {
+        let (value, consumed) = parse_integer_prefix(&s[start..]);
+        #[allow(clippy::cast_precision_loss)]
+        return (value as f64, start + consumed);
+    }
+
+    let digits_before = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+    i += digits_before;
+
+    let mut digits_after = 0;
+    if bytes.get(i) == Some(&b'.') {
+        digits_after = bytes[i + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits_before > 0 || digits_after > 0 {
+            i += 1 + digits_after;
+        }
+    }
+
+    if digits_before == 0 && digits_after == 0 {
+        return (0.0, 0);
+    }
+
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(bytes.get(j), Some(b'-' | b'+')) {
+            j += 1;
         }
+        let exponent_digits = bytes[j..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if exponent_digits > 0 {
+            i = j + exponent_digits;
+        }
+    }
+
+    (s[start..i].parse().unwrap_or_default(), i)
+}
+
+fn format_integer_digits(
+    value: u64,
+    radix: u32,
+    uppercase: bool,
+    precision: Option<usize>,
+) -> String {
+    // An explicit precision of 0 yields no digits for a value of 0.
+    if value == 0 && precision == Some(0) {
+        return String::new();
+    }
+
+    let digits = match (radix, uppercase) {
+        (8, _) => format!("{value:o}"),
+        (16, false) => format!("{value:x}"),
+        (16, true) => format!("{value:X}"),
+        _ => value.to_string(),
+    };
+
+    let min_digits = precision.unwrap_or(1);
+    format!("{digits:0>min_digits$}")
+}
+
+/// Formats a non-negative floating-point value as printf(3) would for the given conversion.
+fn format_float(
+    value: f64,
+    conversion: char,
+    precision: Option<usize>,
+    flags: &DirectiveFlags,
+) -> String {
+    let uppercase = conversion.is_ascii_uppercase();
+
+    let formatted = if value.is_nan() {
+        "nan".to_owned()
+    } else if value.is_infinite() {
+        "inf".to_owned()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            'e' => format_exponential(value, precision.unwrap_or(6), flags.alternate_form),
+            'g' => format_general(value, precision.unwrap_or(6), flags.alternate_form),
+            'a' => format_hexadecimal(value, precision),
+            _ => {
+                let mut formatted = format!("{value:.*}", precision.unwrap_or(6));
+                if flags.alternate_form && precision == Some(0) {
+                    formatted.push('.');
+                }
+                formatted
+            }
+        }
+    };
+
+    if uppercase {
+        formatted.to_ascii_uppercase()
+    } else {
+        formatted
+    }
+}
+
+fn format_exponential(value: f64, precision: usize, alternate_form: bool) -> String {
+    // Rust formats the exponent without a sign or padding (e.g., "1.5e3"); printf(3) uses
+    // at least 2 digits with a sign (e.g., "1.5e+03").
+    let formatted = format!("{value:.precision$e}");
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let (exponent_sign, exponent_digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+
+    let point = if alternate_form && precision == 0 {
+        "."
+    } else {
+        ""
+    };
+
+    format!("{mantissa}{point}e{exponent_sign}{exponent_digits:0>2}")
+}
+
+fn format_general(value: f64, precision: usize, alternate_form: bool) -> String {
+    let precision = precision.max(1);
+
+    // The style depends on the exponent the value would have in exponential notation.
+    let exponential = format!("{value:.*e}", precision - 1);
+    let exponent: i64 = exponential
+        .split_once('e')
+        .and_then(|(_, exponent)| exponent.parse().ok())
+        .unwrap_or(0);
+
+    let mut formatted = if exponent < -4 || exponent >= i64::try_from(precision).unwrap_or(i64::MAX)
+    {
+        format_exponential(value, precision - 1, alternate_form)
+    } else {
+        let decimals =
+            usize::try_from(i64::try_from(precision).unwrap_or(0) - 1 - exponent).unwrap_or(0);
+        format!("{value:.decimals$}")
+    };
+
+    // Trailing zeros are removed, unless the alternate form was requested.
+    if !alternate_form {
+        let (mantissa, exponent) = match formatted.find('e') {
+            Some(index) => formatted.split_at(index),
+            None => (formatted.as_str(), ""),
+        };
+
+        if mantissa.contains('.') {
+            let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+            formatted = format!("{mantissa}{exponent}");
+        }
+    } else if !formatted.contains('.') {
+        let index = formatted.find('e').unwrap_or(formatted.len());
+        formatted.insert(index, '.');
+    }
+
+    formatted
+}
+
+/// Formats a value in hexadecimal floating-point notation, normalized (as printf(3) does
+/// for a `long double`) so that the leading hex digit holds 4 bits of the mantissa.
+fn format_hexadecimal(value: f64, precision: Option<usize>) -> String {
+    if value == 0.0 {
+        let fraction = "0".repeat(precision.unwrap_or(0));
+        let point = if fraction.is_empty() { "" } else { "." };
+        return format!("0x0{point}{fraction}p+0");
+    }
+
+    // Scale the mantissa up to 64 bits, so the leading hex digit is 8 to f.
+    let bits = value.to_bits();
+    let biased_exponent = i64::try_from((bits >> 52) & 0x7ff).unwrap_or(0);
+    let mut mantissa = bits & ((1 << 52) - 1);
+    let mut exponent = if biased_exponent == 0 {
+        // Subnormal values need normalizing.
+        let shift = mantissa.leading_zeros() - 11;
+        mantissa <<= shift;
+        -1022 - i64::from(shift)
+    } else {
+        mantissa |= 1 << 52;
+        biased_exponent - 1023
+    };
+    mantissa <<= 11;
+    exponent -= 3;
+
+    let mut fraction_digits = 15;
+    if let Some(precision) = precision.filter(|precision| *precision < 15) {
+        // Round half to even at the requested number of hex digits.
+        let dropped_bits = 4 * (15 - u32::try_from(precision).unwrap_or(0));
+        let dropped = mantissa & ((1 << dropped_bits) - 1);
+        let half = 1 << (dropped_bits - 1);
+        let kept = mantissa >> dropped_bits;
+        let round_up = dropped > half || (dropped == half && kept & 1 == 1);
+        let (rounded, overflowed) = (kept + u64::from(round_up)).overflowing_shl(dropped_bits);
+        mantissa = rounded;
+        if overflowed || (round_up && mantissa == 0) {
+            mantissa = 1 << 63;
+            exponent += 4;
+        }
+        fraction_digits = precision;
+    }
+
+    let hex = format!("{mantissa:016x}");
+    let (leading, fraction) = hex.split_at(1);
+    let mut fraction = fraction[..fraction_digits].to_owned();
+    if precision.is_none() {
+        fraction = fraction.trim_end_matches('0').to_owned();
+    } else {
+        fraction.push_str(&"0".repeat(precision.unwrap_or(0) - fraction_digits));
+    }
+
+    let point = if fraction.is_empty() { "" } else { "." };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+    let exponent = exponent.abs();
+
+    format!("0x{leading}{point}{fraction}p{exponent_sign}{exponent}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float(value: f64, conversion: char, precision: Option<usize>) -> String {
+        format_float(value, conversion, precision, &DirectiveFlags::default())
+    }
+
+    #[test]
+    fn test_format_float() {
+        assert_eq!(float(1234.5, 'e', None), "1.234500e+03");
+        assert_eq!(float(12_345_678.0, 'E', None), "1.234568E+07");
+        assert_eq!(float(0.000_123_4, 'g', None), "0.0001234");
+        assert_eq!(float(1_234_567.0, 'g', None), "1.23457e+06");
+        assert_eq!(float(100_000.0, 'g', None), "100000");
+        assert_eq!(float(1e-5, 'g', None), "1e-05");
+        assert_eq!(float(2.5, 'f', Some(0)), "2");
+        assert_eq!(float(f64::INFINITY, 'F', None), "INF");
+        assert_eq!(float(1.0, 'a', None), "0x8p-3");
+        assert_eq!(float(3.5, 'a', None), "0xep-2");
+        assert_eq!(float(255.0, 'A', None), "0XF.FP+4");
+    }
+
+    #[test]
+    fn test_parse_integer_prefix() {
+        assert_eq!(parse_integer_prefix("42"), (42, 2));
+        assert_eq!(parse_integer_prefix(" -0x1f"), (-31, 6));
+        assert_eq!(parse_integer_prefix("010"), (8, 3));
+        assert_eq!(parse_integer_prefix("12abc"), (12, 2));
+        assert_eq!(parse_integer_prefix("09"), (0, 1));
+        assert_eq!(parse_integer_prefix("abc"), (0, 0));
     }
 }
@@ -2,8 +2,10 @@ use clap::Parser;
 use itertools::Itertools;
 use std::collections::VecDeque;
 use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::{builtins, commands, env, error, openfiles, sys, variables};
+use crate::{builtins, commands, env, error, openfiles, sys, variables, LineEditor};
 
 /// Parse standard input.
 #[derive(Parser)]
@@ -46,10 +48,11 @@ pub(crate) struct ReadCommand {
     #[clap(short = 's')]
     silent: bool,
 
-    /// Specify timeout in seconds; fail if the timeout elapses before
-    /// input is completed.
-    #[clap(short = 't')]
-    timeout_in_seconds: Option<usize>,
+    /// Specify timeout in seconds (which may be fractional); fail if the
+    /// timeout elapses before input is completed. A timeout of 0 only checks
+    /// whether input is available.
+    #[clap(short = 't', allow_hyphen_values = true)]
+    timeout_in_seconds: Option<String>,
 
     /// File descriptor to read from instead of stdin.
     #[clap(short = 'u', name = "FD")]
@@ -62,20 +65,31 @@ pub(crate) struct ReadCommand {
 impl builtins::Command for ReadCommand {
     async fn execute(
         &self,
-        context: commands::ExecutionContext<'_>,
+        mut context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        if self.use_readline {
-            return error::unimp("read -e");
-        }
-        if self.initial_text.is_some() {
-            return error::unimp("read -i");
-        }
         if self.raw_mode {
             tracing::debug!("read -r is not implemented");
         }
-        if self.timeout_in_seconds.is_some() {
-            return error::unimp("read -t");
-        }
+
+        let timeout = if let Some(timeout_str) = &self.timeout_in_seconds {
+            let Some(timeout) = parse_timeout(timeout_str) else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {timeout_str}: invalid timeout specification",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            };
+            Some(timeout)
+        } else {
+            // TMOUT provides the default timeout.
+            context
+                .shell
+                .env
+                .get_str("TMOUT", context.shell)
+                .and_then(|value| parse_timeout(value.as_ref()))
+                .filter(|timeout| !timeout.is_zero())
+        };
 
         // Find the input stream to use.
         #[allow(clippy::cast_lossless)]
@@ -89,101 +103,180 @@ impl builtins::Command for ReadCommand {
             context.stdin()
         };
 
-        // Retrieve effective value of IFS for splitting.
-        let ifs = context.shell.get_ifs();
+        // A zero timeout means we only check whether there's input available to read.
+        if timeout.is_some_and(|timeout| timeout.is_zero()) {
+            if input_stream.input_available()? {
+                return Ok(builtins::ExitCode::Success);
+            } else {
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        }
 
-        let input_line = self.read_line(input_stream, context.stdout())?;
+        let (reason, line) =
+            if let Some(line_editor) = self.get_line_editor(&context, &input_stream) {
+                let prompt = self.prompt.as_deref().unwrap_or_default();
+                let initial_text = self.initial_text.as_deref().unwrap_or_default();
 
-        if let Some(input_line) = input_line {
-            // If -a was specified, then place the fields as elements into the array.
-            if let Some(array_variable) = &self.array_variable {
-                let fields: VecDeque<_> =
-                    split_line_by_ifs(ifs.as_ref(), input_line.as_str(), None /*max_fields*/);
-                let literal_fields = fields.into_iter().map(|f| (None, f)).collect();
-
-                context.shell.env.update_or_add(
-                    array_variable,
-                    variables::ShellValueLiteral::Array(variables::ArrayLiteral(literal_fields)),
-                    |_| Ok(()),
-                    env::EnvironmentLookup::Anywhere,
-                    env::EnvironmentScope::Global,
-                )?;
-            } else if !self.variable_names.is_empty() {
-                let mut fields: VecDeque<_> = split_line_by_ifs(
-                    ifs.as_ref(),
-                    input_line.as_str(),
-                    /*max_fields*/ Some(self.variable_names.len()),
-                );
-
-                for (i, name) in self.variable_names.iter().enumerate() {
-                    if fields.is_empty() {
-                        // Ensure the var is empty.
-                        context.shell.env.update_or_add(
-                            name,
-                            variables::ShellValueLiteral::Scalar(String::new()),
-                            |_| Ok(()),
-                            env::EnvironmentLookup::Anywhere,
-                            env::EnvironmentScope::Global,
-                        )?;
-                        continue;
-                    }
-
-                    let last = i == self.variable_names.len() - 1;
-                    if !last {
-                        let next_field = fields.pop_front().unwrap();
-                        context.shell.env.update_or_add(
-                            name,
-                            variables::ShellValueLiteral::Scalar(next_field),
-                            |_| Ok(()),
-                            env::EnvironmentLookup::Anywhere,
-                            env::EnvironmentScope::Global,
-                        )?;
-                    } else {
-                        let remaining_fields = fields.into_iter().join(" ");
-                        context.shell.env.update_or_add(
-                            name,
-                            variables::ShellValueLiteral::Scalar(remaining_fields),
-                            |_| Ok(()),
-                            env::EnvironmentLookup::Anywhere,
-                            env::EnvironmentScope::Global,
-                        )?;
-                        break;
-                    }
+                match line_editor.read_line(prompt, initial_text)? {
+                    Some(line) => (ReadTermination::Delimiter, line),
+                    None => (ReadTermination::EndOfInput, String::new()),
                 }
             } else {
-                // If no variable names were specified, then place everything into the
-                // REPLY variable.
-                context.shell.env.update_or_add(
-                    "REPLY",
-                    variables::ShellValueLiteral::Scalar(input_line),
-                    |_| Ok(()),
-                    env::EnvironmentLookup::Anywhere,
-                    env::EnvironmentScope::Global,
-                )?;
-            }
+                self.read_line(input_stream, context.stdout(), timeout)?
+            };
+
+        let input_line = match reason {
+            ReadTermination::EndOfInput if line.is_empty() => None,
+            // Discard the input.
+            ReadTermination::CtrlC => None,
+            _ => Some(line),
+        };
+
+        if let Some(input_line) = input_line {
+            self.assign_input(&mut context, input_line)?;
 
-            Ok(crate::builtins::ExitCode::Success)
+            // N.B. Any input read before timing out is still assigned.
+            if matches!(reason, ReadTermination::Timeout) {
+                Ok(crate::builtins::ExitCode::Custom(TIMEOUT_EXIT_CODE))
+            } else {
+                Ok(crate::builtins::ExitCode::Success)
+            }
         } else {
             Ok(crate::builtins::ExitCode::Custom(1))
         }
     }
 }
 
+/// Exit code returned when a read times out; it matches what would be returned if the
+/// read were interrupted by `SIGALRM`.
+const TIMEOUT_EXIT_CODE: u8 = 142;
+
 enum ReadTermination {
     Delimiter,
     EndOfInput,
     CtrlC,
     Limit,
+    Timeout,
 }
 
 impl ReadCommand {
+    /// Assigns the given line of input to the variables specified by the command.
+    fn assign_input(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        input_line: String,
+    ) -> Result<(), error::Error> {
+        // Retrieve effective value of IFS for splitting.
+        let ifs = context.shell.get_ifs();
+
+        // If -a was specified, then place the fields as elements into the array.
+        if let Some(array_variable) = &self.array_variable {
+            let fields: VecDeque<_> =
+                split_line_by_ifs(ifs.as_ref(), input_line.as_str(), None /*max_fields*/);
+            let literal_fields = fields.into_iter().map(|f| (None, f)).collect();
+
+            context.shell.env.update_or_add(
+                array_variable,
+                variables::ShellValueLiteral::Array(variables::ArrayLiteral(literal_fields)),
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+        } else if !self.variable_names.is_empty() {
+            let mut fields: VecDeque<_> = split_line_by_ifs(
+                ifs.as_ref(),
+                input_line.as_str(),
+                /*max_fields*/ Some(self.variable_names.len()),
+            );
+
+            for (i, name) in self.variable_names.iter().enumerate() {
+                if fields.is_empty() {
+                    // Ensure the var is empty.
+                    context.shell.env.update_or_add(
+                        name,
+                        variables::ShellValueLiteral::Scalar(String::new()),
+                        |_| Ok(()),
+                        env::EnvironmentLookup::Anywhere,
+                        env::EnvironmentScope::Global,
+                    )?;
+                    continue;
+                }
+
+                let last = i == self.variable_names.len() - 1;
+                if !last {
+                    let next_field = fields.pop_front().unwrap();
+                    context.shell.env.update_or_add(
+                        name,
+                        variables::ShellValueLiteral::Scalar(next_field),
+                        |_| Ok(()),
+                        env::EnvironmentLookup::Anywhere,
+                        env::EnvironmentScope::Global,
+                    )?;
+                } else {
+                    let remaining_fields = fields.into_iter().join(" ");
+                    context.shell.env.update_or_add(
+                        name,
+                        variables::ShellValueLiteral::Scalar(remaining_fields),
+                        |_| Ok(()),
+                        env::EnvironmentLookup::Anywhere,
+                        env::EnvironmentScope::Global,
+                    )?;
+                    break;
+                }
+            }
+        } else {
+            // If no variable names were specified, then place everything into the
+            // REPLY variable.
+            context.shell.env.update_or_add(
+                "REPLY",
+                variables::ShellValueLiteral::Scalar(input_line),
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the line editor to read input with, if one was requested and input is
+    /// coming from a terminal.
+    fn get_line_editor(
+        &self,
+        context: &commands::ExecutionContext<'_>,
+        input_file: &openfiles::OpenFile,
+    ) -> Option<Arc<dyn LineEditor>> {
+        if self.use_readline && input_file.is_term() {
+            context.shell.line_editor.clone()
+        } else {
+            None
+        }
+    }
+
     fn read_line(
         &self,
         mut input_file: openfiles::OpenFile,
         mut output_file: openfiles::OpenFile,
-    ) -> Result<Option<String>, error::Error> {
+        timeout: Option<Duration>,
+    ) -> Result<(ReadTermination, String), error::Error> {
         let orig_term_attr = self.setup_terminal_settings(&input_file)?;
 
+        let result = self.read_line_from_file(&mut input_file, &mut output_file, timeout);
+
+        // Make sure to restore the terminal settings, even if reading failed.
+        if let Some(orig_term_attr) = &orig_term_attr {
+            input_file.set_term_attr(orig_term_attr)?;
+        }
+
+        result
+    }
+
+    fn read_line_from_file(
+        &self,
+        input_file: &mut openfiles::OpenFile,
+        output_file: &mut openfiles::OpenFile,
+        timeout: Option<Duration>,
+    ) -> Result<(ReadTermination, String), error::Error> {
         let delimiter = if self.return_after_n_chars_no_delimiter.is_some() {
             None
         } else if let Some(delimiter_str) = &self.delimiter {
@@ -208,12 +301,22 @@ impl ReadCommand {
             output_file.flush()?;
         }
 
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
         let mut line = String::new();
         let mut buffer = [0; 1]; // 1-byte buffer
 
         let reason = loop {
-            // TODO: Figure out how to restore terminal settings on error?
-            let n = input_file.read(&mut buffer)?;
+            let n = if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match input_file.read_with_timeout(&mut buffer, remaining)? {
+                    Some(n) => n,
+                    None => break ReadTermination::Timeout,
+                }
+            } else {
+                input_file.read(&mut buffer)?
+            };
+
             if n == 0 {
                 break ReadTermination::EndOfInput; // EOF reached.
             }
@@ -250,24 +353,7 @@ impl ReadCommand {
             }
         };
 
-        if let Some(orig_term_attr) = &orig_term_attr {
-            input_file.set_term_attr(orig_term_attr)?;
-        }
-
-        match reason {
-            ReadTermination::EndOfInput => {
-                if line.is_empty() {
-                    Ok(None)
-                } else {
-                    Ok(Some(line))
-                }
-            }
-            ReadTermination::CtrlC => {
-                // Discard the input and return.
-                Ok(None)
-            }
-            ReadTermination::Delimiter | ReadTermination::Limit => Ok(Some(line)),
-        }
+        Ok((reason, line))
     }
 
     fn setup_terminal_settings(
@@ -290,6 +376,17 @@ impl ReadCommand {
     }
 }
 
+/// Parses a timeout given in (possibly fractional) seconds.
+fn parse_timeout(s: &str) -> Option<Duration> {
+    if !s.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    s.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
 fn split_line_by_ifs(ifs: &str, line: &str, max_fields: Option<usize>) -> VecDeque<String> {
     // Separate out the chars to split by.
     let ifs_chars = ifs.chars().collect::<Vec<_>>();
@@ -37,7 +37,7 @@ impl builtins::Command for TrapCommand {
             Ok(builtins::ExitCode::Success)
         } else if self.args.len() == 1 {
             let signal = self.args[0].as_str();
-            Self::remove_all_handlers(&mut context, signal.parse()?);
+            Self::remove_all_handlers(&mut context, signal.parse()?)?;
             Ok(builtins::ExitCode::Success)
         } else {
             let handler = &self.args[0];
@@ -47,7 +47,14 @@ impl builtins::Command for TrapCommand {
                 signal_types.push(signal.parse()?);
             }
 
-            Self::register_handler(&mut context, signal_types, handler.as_str());
+            if handler == "-" {
+                for signal in signal_types {
+                    Self::remove_all_handlers(&mut context, signal)?;
+                }
+            } else {
+                Self::register_handler(&mut context, signal_types, handler.as_str())?;
+            }
+
             Ok(builtins::ExitCode::Success)
         }
     }
@@ -75,20 +82,29 @@ impl TrapCommand {
     fn remove_all_handlers(
         context: &mut crate::commands::ExecutionContext<'_>,
         signal: TrapSignal,
-    ) {
+    ) -> Result<(), error::Error> {
         context.shell.traps.remove_handlers(signal);
+
+        let interactive = context.shell.options.interactive;
+        context.shell.traps.apply_disposition(signal, interactive)
     }
 
     fn register_handler(
         context: &mut crate::commands::ExecutionContext<'_>,
         signals: Vec<TrapSignal>,
         handler: &str,
-    ) {
+    ) -> Result<(), error::Error> {
+        let interactive = context.shell.options.interactive;
+
         for signal in signals {
             context
                 .shell
                 .traps
                 .register_handler(signal, handler.to_owned());
+
+            context.shell.traps.apply_disposition(signal, interactive)?;
         }
+
+        Ok(())
     }
 }
@@ -0,0 +1,550 @@
+use clap::Parser;
+use std::io::Write;
+
+use crate::sys::resource::{rlim_t, Resource, RLIM_INFINITY};
+use crate::{builtins, commands, error};
+
+/// Display or modify limits on the resources available to the shell and the processes
+/// it starts.
+///
+/// Each resource option takes an optional limit; the outer `Option` records whether the
+/// option was given at all.
+#[derive(Parser)]
+#[allow(clippy::option_option)]
+pub(crate) struct UlimitCommand {
+    /// Use the hard limit for the resource.
+    #[arg(short = 'H')]
+    hard: bool,
+
+    /// Use the soft limit for the resource.
+    #[arg(short = 'S')]
+    soft: bool,
+
+    /// Display all current limits.
+    #[arg(short = 'a')]
+    all: bool,
+
+    /// The maximum socket buffer size.
+    #[arg(short = 'b', num_args = 0..=1, value_name = "LIMIT")]
+    socket_buffer_size: Option<Option<String>>,
+
+    /// The maximum size of core files created.
+    #[arg(short = 'c', num_args = 0..=1, value_name = "LIMIT")]
+    core_file_size: Option<Option<String>>,
+
+    /// The maximum size of a process's data segment.
+    #[arg(short = 'd', num_args = 0..=1, value_name = "LIMIT")]
+    data_segment_size: Option<Option<String>>,
+
+    /// The maximum scheduling priority ("nice").
+    #[arg(short = 'e', num_args = 0..=1, value_name = "LIMIT")]
+    scheduling_priority: Option<Option<String>>,
+
+    /// The maximum size of files written by the shell and its children.
+    #[arg(short = 'f', num_args = 0..=1, value_name = "LIMIT")]
+    file_size: Option<Option<String>>,
+
+    /// The maximum number of pending signals.
+    #[arg(short = 'i', num_args = 0..=1, value_name = "LIMIT")]
+    pending_signals: Option<Option<String>>,
+
+    /// The maximum number of kqueues allocated.
+    #[arg(short = 'k', num_args = 0..=1, value_name = "LIMIT")]
+    kqueues: Option<Option<String>>,
+
+    /// The maximum size a process may lock into memory.
+    #[arg(short = 'l', num_args = 0..=1, value_name = "LIMIT")]
+    locked_memory: Option<Option<String>>,
+
+    /// The maximum resident set size.
+    #[arg(short = 'm', num_args = 0..=1, value_name = "LIMIT")]
+    memory_size: Option<Option<String>>,
+
+    /// The maximum number of open file descriptors.
+    #[arg(short = 'n', num_args = 0..=1, value_name = "LIMIT")]
+    open_files: Option<Option<String>>,
+
+    /// The pipe buffer size.
+    #[arg(short = 'p', num_args = 0..=1, value_name = "LIMIT")]
+    pipe_size: Option<Option<String>>,
+
+    /// The maximum number of bytes in POSIX message queues.
+    #[arg(short = 'q', num_args = 0..=1, value_name = "LIMIT")]
+    message_queues: Option<Option<String>>,
+
+    /// The maximum real-time scheduling priority.
+    #[arg(short = 'r', num_args = 0..=1, value_name = "LIMIT")]
+    real_time_priority: Option<Option<String>>,
+
+    /// The maximum stack size.
+    #[arg(short = 's', num_args = 0..=1, value_name = "LIMIT")]
+    stack_size: Option<Option<String>>,
+
+    /// The maximum amount of cpu time in seconds.
+    #[arg(short = 't', num_args = 0..=1, value_name = "LIMIT")]
+    cpu_time: Option<Option<String>>,
+
+    /// The maximum number of user processes.
+    #[arg(short = 'u', num_args = 0..=1, value_name = "LIMIT")]
+    user_processes: Option<Option<String>>,
+
+    /// The size of virtual memory.
+    #[arg(short = 'v', num_args = 0..=1, value_name = "LIMIT")]
+    virtual_memory: Option<Option<String>>,
+
+    /// The maximum number of file locks.
+    #[arg(short = 'x', num_args = 0..=1, value_name = "LIMIT")]
+    file_locks: Option<Option<String>>,
+
+    /// The maximum number of pseudoterminals.
+    #[arg(short = 'P', num_args = 0..=1, value_name = "LIMIT")]
+    pseudoterminals: Option<Option<String>>,
+
+    /// The maximum time a real-time process can run before blocking, in microseconds.
+    #[arg(short = 'R', num_args = 0..=1, value_name = "LIMIT")]
+    real_time_non_blocking_time: Option<Option<String>>,
+
+    /// The maximum number of threads.
+    #[arg(short = 'T', num_args = 0..=1, value_name = "LIMIT")]
+    threads: Option<Option<String>>,
+
+    /// New limit for the (last) selected resource.
+    limit: Option<String>,
+}
+
+/// Describes a limit that `ulimit` can display or modify.
+struct ResourceLimit {
+    /// The option letter that selects the limit.
+    option: char,
+    /// Description of the limit, as displayed.
+    description: &'static str,
+    /// Units that the limit is displayed and given in, if worth mentioning.
+    units: Option<&'static str>,
+    /// Number of bytes in the units that the limit is displayed and given in, outside of
+    /// POSIX mode.
+    block_size: rlim_t,
+    /// The resource the limit applies to; `None` for the (read-only) pipe buffer size.
+    resource: Option<Resource>,
+}
+
+impl ResourceLimit {
+    /// Returns the number of bytes in the units that the limit is displayed and given in;
+    /// in POSIX mode, blocks are 512 bytes rather than 1024.
+    fn block_size(&self, posix_mode: bool) -> rlim_t {
+        if posix_mode && matches!(self.option, 'c' | 'f') {
+            512
+        } else {
+            self.block_size
+        }
+    }
+}
+
+/// The limits supported on this platform, in the order they're displayed.
+const RESOURCE_LIMITS: &[ResourceLimit] = &[
+    #[cfg(target_os = "linux")]
+    ResourceLimit {
+        option: 'R',
+        description: "real-time non-blocking time",
+        units: Some("microseconds"),
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_RTTIME),
+    },
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+    ResourceLimit {
+        option: 'b',
+        description: "socket buffer size",
+        units: Some("bytes"),
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_SBSIZE),
+    },
+    ResourceLimit {
+        option: 'c',
+        description: "core file size",
+        units: Some("blocks"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_CORE),
+    },
+    ResourceLimit {
+        option: 'd',
+        description: "data seg size",
+        units: Some("kbytes"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_DATA),
+    },
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ResourceLimit {
+        option: 'e',
+        description: "scheduling priority",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_NICE),
+    },
+    ResourceLimit {
+        option: 'f',
+        description: "file size",
+        units: Some("blocks"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_FSIZE),
+    },
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ResourceLimit {
+        option: 'i',
+        description: "pending signals",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_SIGPENDING),
+    },
+    #[cfg(target_os = "freebsd")]
+    ResourceLimit {
+        option: 'k',
+        description: "max kqueues",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_KQUEUES),
+    },
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    ResourceLimit {
+        option: 'l',
+        description: "max locked memory",
+        units: Some("kbytes"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_MEMLOCK),
+    },
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    ResourceLimit {
+        option: 'm',
+        description: "max memory size",
+        units: Some("kbytes"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_RSS),
+    },
+    ResourceLimit {
+        option: 'n',
+        description: "open files",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_NOFILE),
+    },
+    ResourceLimit {
+        option: 'p',
+        description: "pipe size",
+        units: Some("512 bytes"),
+        block_size: 512,
+        resource: None,
+    },
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ResourceLimit {
+        option: 'q',
+        description: "POSIX message queues",
+        units: Some("bytes"),
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_MSGQUEUE),
+    },
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ResourceLimit {
+        option: 'r',
+        description: "real-time priority",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_RTPRIO),
+    },
+    ResourceLimit {
+        option: 's',
+        description: "stack size",
+        units: Some("kbytes"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_STACK),
+    },
+    ResourceLimit {
+        option: 't',
+        description: "cpu time",
+        units: Some("seconds"),
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_CPU),
+    },
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    ResourceLimit {
+        option: 'u',
+        description: "max user processes",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_NPROC),
+    },
+    #[cfg(not(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+    ResourceLimit {
+        option: 'v',
+        description: "virtual memory",
+        units: Some("kbytes"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_AS),
+    },
+    #[cfg(target_os = "freebsd")]
+    ResourceLimit {
+        option: 'v',
+        description: "virtual memory",
+        units: Some("kbytes"),
+        block_size: 1024,
+        resource: Some(Resource::RLIMIT_VMEM),
+    },
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ResourceLimit {
+        option: 'x',
+        description: "file locks",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_LOCKS),
+    },
+    #[cfg(target_os = "freebsd")]
+    ResourceLimit {
+        option: 'P',
+        description: "pseudoterminals",
+        units: None,
+        block_size: 1,
+        resource: Some(Resource::RLIMIT_NPTS),
+    },
+];
+
+impl builtins::Command for UlimitCommand {
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        // Collect the selected limits (along with any new values for them).
+        let mut selected = vec![];
+        for (option, value) in self.options() {
+            let Some(value) = value else {
+                continue;
+            };
+
+            let Some(limit) = RESOURCE_LIMITS.iter().find(|limit| limit.option == option) else {
+                writeln!(
+                    context.stderr(),
+                    "{}: -{option}: invalid option",
+                    context.command_name
+                )?;
+                writeln!(
+                    context.stderr(),
+                    "{}: usage: ulimit [-SHabcdefiklmnpqrstuvxPRT] [limit]",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::InvalidUsage);
+            };
+
+            selected.push((limit, value.clone()));
+        }
+
+        if self.all {
+            for limit in RESOURCE_LIMITS {
+                self.display_limit(&context, limit, true)?;
+            }
+            return Ok(builtins::ExitCode::Success);
+        }
+
+        // With no limit selected, the file size limit is implied.
+        if selected.is_empty() {
+            if let Some(limit) = RESOURCE_LIMITS.iter().find(|limit| limit.option == 'f') {
+                selected.push((limit, None));
+            }
+        }
+
+        // A trailing operand is the new value for the last limit selected, if it doesn't
+        // already have one.
+        if let Some((_, value @ None)) = selected.last_mut() {
+            value.clone_from(&self.limit);
+        }
+
+        let show_descriptions = selected.len() > 1;
+
+        let mut exit_code = builtins::ExitCode::Success;
+        for (limit, value) in selected {
+            let result = match value {
+                Some(value) => self.set_limit(&context, limit, value.as_str())?,
+                None => self.display_limit(&context, limit, show_descriptions)?,
+            };
+
+            if !matches!(result, builtins::ExitCode::Success) {
+                exit_code = result;
+            }
+        }
+
+        Ok(exit_code)
+    }
+}
+
+impl UlimitCommand {
+    #[allow(clippy::option_option)]
+    fn options(&self) -> [(char, &Option<Option<String>>); 21] {
+        [
+            ('R', &self.real_time_non_blocking_time),
+            ('b', &self.socket_buffer_size),
+            ('c', &self.core_file_size),
+            ('d', &self.data_segment_size),
+            ('e', &self.scheduling_priority),
+            ('f', &self.file_size),
+            ('i', &self.pending_signals),
+            ('k', &self.kqueues),
+            ('l', &self.locked_memory),
+            ('m', &self.memory_size),
+            ('n', &self.open_files),
+            ('p', &self.pipe_size),
+            ('q', &self.message_queues),
+            ('r', &self.real_time_priority),
+            ('s', &self.stack_size),
+            ('t', &self.cpu_time),
+            ('u', &self.user_processes),
+            ('v', &self.virtual_memory),
+            ('x', &self.file_locks),
+            ('P', &self.pseudoterminals),
+            ('T', &self.threads),
+        ]
+    }
+
+    fn display_limit(
+        &self,
+        context: &commands::ExecutionContext<'_>,
+        limit: &ResourceLimit,
+        show_description: bool,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let (soft, hard) = match get_limits(limit) {
+            Ok(limits) => limits,
+            Err(e) => {
+                writeln!(
+                    context.stderr(),
+                    "{}: {}: cannot get limit: {}",
+                    context.command_name,
+                    limit.description,
+                    e.desc()
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        };
+
+        // The soft limit is displayed unless only the hard limit was requested.
+        let value = if self.hard && !self.soft { hard } else { soft };
+
+        if show_description {
+            let units = match limit.units {
+                Some(units) => std::format!("({units}, -{}) ", limit.option),
+                None => std::format!("(-{}) ", limit.option),
+            };
+            write!(context.stdout(), "{:<20} {units:>20}", limit.description)?;
+        }
+
+        if value == RLIM_INFINITY {
+            writeln!(context.stdout(), "unlimited")?;
+        } else {
+            let block_size = limit.block_size(context.shell.options.posix_mode);
+            writeln!(context.stdout(), "{}", value / block_size)?;
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+
+    fn set_limit(
+        &self,
+        context: &commands::ExecutionContext<'_>,
+        limit: &ResourceLimit,
+        value: &str,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let (soft, hard) = match get_limits(limit) {
+            Ok(limits) => limits,
+            Err(e) => {
+                writeln!(
+                    context.stderr(),
+                    "{}: {}: cannot get limit: {}",
+                    context.command_name,
+                    limit.description,
+                    e.desc()
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        };
+
+        let new_limit = match value {
+            "unlimited" => RLIM_INFINITY,
+            "hard" => hard,
+            "soft" => soft,
+            _ if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) => {
+                let Some(new_limit) = value.parse::<rlim_t>().ok().and_then(|n| {
+                    n.checked_mul(limit.block_size(context.shell.options.posix_mode))
+                }) else {
+                    writeln!(
+                        context.stderr(),
+                        "{}: {value}: limit out of range",
+                        context.command_name
+                    )?;
+                    return Ok(builtins::ExitCode::Custom(1));
+                };
+                new_limit
+            }
+            _ => {
+                writeln!(
+                    context.stderr(),
+                    "{}: {value}: invalid number",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        };
+
+        // Unless only one of them was requested, both the soft and hard limits are set.
+        let new_soft = if self.hard && !self.soft {
+            soft
+        } else {
+            new_limit
+        };
+        let new_hard = if self.soft && !self.hard {
+            hard
+        } else {
+            new_limit
+        };
+
+        let result = match limit.resource {
+            Some(resource) => {
+                crate::sys::resource::set_resource_limits(resource, new_soft, new_hard)
+            }
+            None => Err(nix::errno::Errno::EINVAL),
+        };
+
+        if let Err(e) = result {
+            writeln!(
+                context.stderr(),
+                "{}: {}: cannot modify limit: {}",
+                context.command_name,
+                limit.description,
+                e.desc()
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+}
+
+/// Returns the soft and hard values of the given limit, in bytes (or other base units).
+fn get_limits(limit: &ResourceLimit) -> Result<(rlim_t, rlim_t), nix::Error> {
+    if let Some(resource) = limit.resource {
+        crate::sys::resource::get_resource_limits(resource)
+    } else {
+        let pipe_size = rlim_t::try_from(nix::libc::PIPE_BUF).unwrap_or_default();
+        Ok((pipe_size, pipe_size))
+    }
+}
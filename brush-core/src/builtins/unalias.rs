@@ -1,5 +1,6 @@
 use clap::Parser;
 use std::io::Write;
+use std::sync::Arc;
 
 use crate::{builtins, commands};
 
@@ -22,10 +23,13 @@ impl builtins::Command for UnaliasCommand {
         let mut exit_code = builtins::ExitCode::Success;
 
         if self.remove_all {
-            context.shell.aliases.clear();
+            Arc::make_mut(&mut context.shell.aliases).clear();
         } else {
             for alias in &self.aliases {
-                if context.shell.aliases.remove(alias).is_none() {
+                if Arc::make_mut(&mut context.shell.aliases)
+                    .remove(alias)
+                    .is_none()
+                {
                     writeln!(
                         context.stderr(),
                         "{}: {}: not found",
@@ -39,17 +39,16 @@ impl builtins::Command for UnsetCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        //
-        // TODO: implement nameref
-        //
-        if self.name_interpretation.name_references {
-            return crate::error::unimp("unset: name references are not yet implemented");
-        }
-
         let unspecified = self.name_interpretation.unspecified();
 
         #[allow(clippy::needless_continue)]
         for name in &self.names {
+            // Unset name references themselves, rather than the variables they refer to.
+            if self.name_interpretation.name_references {
+                context.shell.env.unset_nameref(name)?;
+                continue;
+            }
+
             if unspecified || self.name_interpretation.shell_variables {
                 let parameter =
                     brush_parser::word::parse_parameter(name, &context.shell.parser_options())?;
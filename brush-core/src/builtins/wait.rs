@@ -1,7 +1,7 @@
 use clap::Parser;
 use std::io::Write;
 
-use crate::{builtins, commands, error};
+use crate::{builtins, commands, env, error, sys, traps, variables, ExecutionResult};
 
 /// Wait for jobs to terminate.
 #[derive(Parser)]
@@ -23,25 +23,80 @@ pub(crate) struct WaitCommand {
     job_specs: Vec<String>,
 }
 
+/// A job identified by an argument to `wait`.
+enum WaitTarget {
+    /// A job that is still managed by the shell, identified by its job ID.
+    Job(usize),
+    /// A job that already completed with the given exit status, identified by the process
+    /// ID of the job.
+    Completed(sys::process::ProcessId, u8),
+}
+
 impl builtins::Command for WaitCommand {
     async fn execute(
         &self,
-        context: commands::ExecutionContext<'_>,
+        mut context: commands::ExecutionContext<'_>,
     ) -> Result<builtins::ExitCode, crate::error::Error> {
-        if self.wait_for_terminate {
-            return error::unimp("wait -f");
+        // The variable only ends up set if a job's status is reported.
+        if let Some(variable_name) = &self.variable_to_receive_id {
+            context.shell.env.unset(variable_name)?;
         }
+
+        // Waiting is interrupted by the receipt of any signal with a registered trap
+        // handler; the handler will run as soon as we return.
+        let caught_signals = context.shell.traps.caught_signals();
+
         if self.wait_for_first_or_next {
-            return error::unimp("wait -n");
-        }
-        if self.variable_to_receive_id.is_some() {
-            return error::unimp("wait -p");
+            return self.wait_for_next(&mut context, &caught_signals).await;
         }
+
         if !self.job_specs.is_empty() {
-            return error::unimp("wait with job specs");
+            let mut exit_code = builtins::ExitCode::Success;
+            for job_spec in &self.job_specs {
+                let target = match self.resolve_target(&mut context, job_spec)? {
+                    Ok(target) => target,
+                    Err(error_exit_code) => {
+                        exit_code = error_exit_code;
+                        continue;
+                    }
+                };
+
+                let (id, result) = match target {
+                    WaitTarget::Job(id) => {
+                        let description = describe_job(&context, id);
+                        let wait_result = interruptible(
+                            &caught_signals,
+                            context.shell.jobs.wait_for_job(id, self.wait_for_terminate),
+                        )
+                        .await?;
+
+                        let result = match wait_result {
+                            Ok(result) => result.unwrap_or_else(|| ExecutionResult::new(127)),
+                            Err(exit_code) => return Ok(exit_code),
+                        };
+
+                        (description, result)
+                    }
+                    WaitTarget::Completed(pid, exit_code) => {
+                        (pid.to_string(), ExecutionResult::new(exit_code))
+                    }
+                };
+
+                self.release_completed_jobs(&mut context)?;
+                self.report_id(&mut context, id)?;
+
+                exit_code = builtins::ExitCode::Custom(result.exit_code);
+            }
+
+            return Ok(exit_code);
         }
 
-        let jobs = context.shell.jobs.wait_all().await?;
+        let jobs = match interruptible(&caught_signals, context.shell.jobs.wait_all()).await? {
+            Ok(jobs) => jobs,
+            Err(exit_code) => return Ok(exit_code),
+        };
+
+        context.shell.release_completed_coprocesses(jobs.iter())?;
 
         if context.shell.options.enable_job_control {
             for job in jobs {
@@ -52,3 +107,190 @@ impl builtins::Command for WaitCommand {
         Ok(builtins::ExitCode::Success)
     }
 }
+
+impl WaitCommand {
+    /// Waits for the first of the specified jobs (or, if none were specified, of all jobs)
+    /// to complete.
+    async fn wait_for_next(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        caught_signals: &[traps::TrapSignal],
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let mut ids = vec![];
+        if self.job_specs.is_empty() {
+            ids.extend(
+                context
+                    .shell
+                    .jobs
+                    .jobs
+                    .iter()
+                    .filter(|job| !matches!(job.state, crate::jobs::JobState::Stopped))
+                    .map(|job| job.id),
+            );
+        } else {
+            for job_spec in &self.job_specs {
+                match self.resolve_target(context, job_spec)? {
+                    Ok(WaitTarget::Job(id)) => ids.push(id),
+                    Ok(WaitTarget::Completed(pid, exit_code)) => {
+                        // A job that already completed is the first to have done so.
+                        self.report_id(context, pid.to_string())?;
+                        return Ok(builtins::ExitCode::Custom(exit_code));
+                    }
+                    Err(_) => (),
+                }
+            }
+        }
+
+        // N.B. A job's process ID is no longer known once it has completed.
+        let descriptions = ids
+            .iter()
+            .map(|id| describe_job(context, *id))
+            .collect::<Vec<_>>();
+
+        let wait_result = interruptible(
+            caught_signals,
+            context
+                .shell
+                .jobs
+                .wait_for_any_job(&ids, self.wait_for_terminate),
+        )
+        .await?;
+
+        let (id, result) = match wait_result {
+            Ok(Some((id, result))) => (id, result),
+            Ok(None) => return Ok(builtins::ExitCode::Custom(127)),
+            Err(exit_code) => return Ok(exit_code),
+        };
+
+        let description = ids
+            .iter()
+            .position(|candidate| *candidate == id)
+            .map_or_else(String::new, |i| descriptions[i].clone());
+
+        self.release_completed_jobs(context)?;
+        self.report_id(context, description)?;
+
+        Ok(builtins::ExitCode::Custom(result.exit_code))
+    }
+
+    /// Resolves a process ID or job spec given as an argument, reporting an error and
+    /// returning the exit code to yield if it doesn't identify a job.
+    fn resolve_target(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        job_spec: &str,
+    ) -> Result<Result<WaitTarget, builtins::ExitCode>, error::Error> {
+        if job_spec.starts_with('%') {
+            if let Some(job) = context.shell.jobs.resolve_job_spec(job_spec) {
+                return Ok(Ok(WaitTarget::Job(job.id)));
+            }
+
+            writeln!(
+                context.stderr(),
+                "{}: {job_spec}: no such job",
+                context.command_name
+            )?;
+            return Ok(Err(builtins::ExitCode::Custom(127)));
+        }
+
+        let pid = job_spec
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| job_spec.parse::<sys::process::ProcessId>().ok())
+            .flatten();
+
+        let Some(pid) = pid else {
+            writeln!(
+                context.stderr(),
+                "{}: `{job_spec}': not a pid or valid job spec",
+                context.command_name
+            )?;
+            return Ok(Err(builtins::ExitCode::Custom(1)));
+        };
+
+        if let Some(job) = context.shell.jobs.resolve_pid(pid) {
+            return Ok(Ok(WaitTarget::Job(job.id)));
+        }
+
+        if let Some(exit_code) = context.shell.jobs.get_completed_status(pid) {
+            return Ok(Ok(WaitTarget::Completed(pid, exit_code)));
+        }
+
+        if self.wait_for_first_or_next {
+            writeln!(
+                context.stderr(),
+                "{}: {pid}: no such job",
+                context.command_name
+            )?;
+        } else {
+            writeln!(
+                context.stderr(),
+                "{}: pid {pid} is not a child of this shell",
+                context.command_name
+            )?;
+        }
+
+        Ok(Err(builtins::ExitCode::Custom(127)))
+    }
+
+    #[allow(clippy::unused_self)]
+    fn release_completed_jobs(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+    ) -> Result<(), error::Error> {
+        let completed_jobs = context.shell.jobs.sweep_completed_jobs();
+        context
+            .shell
+            .release_completed_coprocesses(completed_jobs.iter())
+    }
+
+    /// Stores the identifier of the job whose status is being reported in the variable
+    /// requested with `-p`, if any.
+    fn report_id(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        id: String,
+    ) -> Result<(), error::Error> {
+        if let Some(variable_name) = &self.variable_to_receive_id {
+            context.shell.env.update_or_add(
+                variable_name,
+                variables::ShellValueLiteral::Scalar(id),
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Awaits the given future, unless first interrupted by the receipt of one of the given
+/// signals; in that case, returns the exit code that `wait` yields.
+async fn interruptible<T>(
+    caught_signals: &[traps::TrapSignal],
+    future: impl std::future::Future<Output = Result<T, error::Error>>,
+) -> Result<Result<T, builtins::ExitCode>, error::Error> {
+    tokio::select! {
+        biased;
+
+        signal = sys::signal::await_pending_signal(caught_signals) => {
+            let signal_number = i32::try_from(signal).unwrap_or_default();
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Ok(Err(builtins::ExitCode::Custom((128 + signal_number) as u8)))
+        }
+        result = future => result.map(Ok),
+    }
+}
+
+/// Returns the identifier to report for the job with the given ID: the process ID of the
+/// job if it has one, and otherwise its job spec.
+fn describe_job(context: &commands::ExecutionContext<'_>, id: usize) -> String {
+    context
+        .shell
+        .jobs
+        .jobs
+        .iter()
+        .find(|job| job.id == id)
+        .and_then(|job| job.get_representative_pid())
+        .map_or_else(|| std::format!("%{id}"), |pid| pid.to_string())
+}
@@ -275,6 +275,12 @@ pub(crate) async fn execute(
     use_functions: bool,
     path_dirs: Option<Vec<String>>,
 ) -> Result<CommandSpawnResult, error::Error> {
+    // An attempt to exit only skips warning about jobs if it immediately follows `jobs` or
+    // another attempt to exit.
+    if !matches!(cmd_context.command_name.as_str(), "exit" | "logout") {
+        cmd_context.shell.jobs_acknowledged = cmd_context.command_name == "jobs";
+    }
+
     if !cmd_context.command_name.contains(std::path::MAIN_SEPARATOR) {
         let builtin = cmd_context
             .shell
@@ -410,6 +416,17 @@ pub(crate) fn execute_external_command(
         }
     }
 
+    // Make sure the child keeps ignoring any signals the shell was asked to ignore.
+    #[cfg(unix)]
+    if sys::signal::any_signals_ignored() {
+        unsafe {
+            cmd.pre_exec(|| {
+                sys::signal::reapply_ignored_signals();
+                Ok(())
+            });
+        }
+    }
+
     // When tracing is enabled, report.
     tracing::debug!(
         target: trace_categories::COMMANDS,
@@ -525,27 +542,47 @@ pub(crate) async fn invoke_shell_function(
         }
     }
 
-    // Temporarily replace positional parameters.
-    let prior_positional_params = std::mem::take(&mut context.shell.positional_parameters);
-    context.shell.positional_parameters = args.iter().map(|a| a.to_string()).collect();
+    let function_args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
 
     // Pass through open files.
     let params = context.params.clone();
 
+    // The function's commands report their own lines; the caller's line is restored after.
+    let prior_line_number = context.shell.get_current_input_line_number();
+
     // Note that we're going deeper. Once we do this, we need to make sure we don't bail early
     // before "exiting" the function.
-    context
-        .shell
-        .enter_function(context.command_name.as_str(), &function_definition)?;
+    context.shell.enter_function(
+        context.command_name.as_str(),
+        &function_definition,
+        &function_args,
+    )?;
 
-    // Invoke the function.
-    let result = body.execute(context.shell, &params).await;
+    // Temporarily replace positional parameters.
+    let prior_positional_params =
+        std::mem::replace(&mut context.shell.positional_parameters, function_args);
+
+    // If the function is being traced (i.e., it inherited the DEBUG trap), then the
+    // trap also gets a chance to run as the function is entered.
+    let debug_result = context.shell.run_debug_trap(&params).await;
+
+    // Invoke the function, and then give any RETURN trap visible within it a chance to run.
+    let result = match debug_result {
+        Ok(()) => match body.execute(context.shell, &params).await {
+            Ok(result) => context.shell.run_return_trap(result, &params).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
 
     // Clean up parameters so any owned files are closed.
     drop(params);
 
     // We've come back out, reflect it.
     context.shell.leave_function()?;
+    context
+        .shell
+        .set_current_input_line_number(prior_line_number);
 
     // Restore positional parameters.
     context.shell.positional_parameters = prior_positional_params;
@@ -569,6 +606,11 @@ pub(crate) async fn invoke_command_in_subshell_and_get_output(
     // Instantiate a subshell to run the command in.
     let mut subshell = shell.clone();
 
+    // Command substitutions don't inherit errexit unless asked to.
+    if !shell.options.command_subst_inherits_errexit {
+        subshell.options.exit_on_nonzero_command_exit = false;
+    }
+
     // Get our own set of parameters we can customize and use.
     let mut params = params.clone();
     params.process_group_policy = ProcessGroupPolicy::SameProcessGroup;
@@ -580,17 +622,19 @@ pub(crate) async fn invoke_command_in_subshell_and_get_output(
         .files
         .insert(1, openfiles::OpenFile::PipeWriter(writer));
 
-    // Run the command.
-    let result = subshell.run_string(s, &params).await?;
+    // Run the command, followed by any EXIT trap it registered.
+    let result = subshell.run_embedded_string(s, &params).await?;
+    subshell.last_exit_status = result.exit_code;
+    subshell.run_exit_trap(&params).await?;
+
+    // Store the status.
+    shell.last_exit_status = subshell.last_exit_status;
 
     // Make sure the subshell and params are closed; among other things, this
     // ensures they're not holding onto the write end of the pipe.
     drop(subshell);
     drop(params);
 
-    // Store the status.
-    shell.last_exit_status = result.exit_code;
-
     // Extract output.
     let output_str = std::io::read_to_string(reader)?;
 
@@ -396,7 +396,7 @@ impl Spec {
                 CompleteAction::Alias => {
                     for name in shell.aliases.keys() {
                         if name.starts_with(token) {
-                            candidates.insert(name.to_string());
+                            candidates.insert(name.clone());
                         }
                     }
                 }
@@ -1066,9 +1066,16 @@ async fn get_file_completions(
 
     let path_filter = |path: &Path| !must_be_dir || shell.get_absolute_path(path).is_dir();
 
+    // N.B. readline's completion-ignore-case variable also makes filename completion
+    // case-insensitive.
+    let case_insensitive = shell.options.case_insensitive_pathname_expansion
+        || shell
+            .readline_config
+            .is_variable_on("completion-ignore-case");
+
     let pattern = patterns::Pattern::from(glob)
         .set_extended_globbing(shell.options.extended_globbing)
-        .set_case_insensitive(shell.options.case_insensitive_pathname_expansion);
+        .set_case_insensitive(case_insensitive);
 
     pattern
         .expand(
@@ -0,0 +1,24 @@
+use crate::error;
+
+/// Represents an interactive line editor that builtins (e.g., `read -e`) may use to read
+/// input from the user.
+pub trait LineEditor: Send + Sync {
+    /// Reads a line of input from the user, returning `None` if no line was entered
+    /// (e.g., because end of input was reached or the read was interrupted).
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt to display to the user.
+    /// * `initial_text` - Text to pre-populate the input buffer with, for the user to edit.
+    fn read_line(&self, prompt: &str, initial_text: &str) -> Result<Option<String>, error::Error>;
+}
+
+/// The contents of a line editor's input buffer, as exposed to shell commands bound to
+/// key sequences.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EditBuffer {
+    /// The text being edited.
+    pub text: String,
+    /// The byte offset of the cursor within the text.
+    pub cursor: usize,
+}
@@ -165,12 +165,17 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve an immutable reference to the variable with the given name
-    /// in the environment.
+    /// in the environment, following any name references.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to retrieve.
     pub fn get<S: AsRef<str>>(&self, name: S) -> Option<(EnvironmentScope, &ShellVariable)> {
+        let name = self.resolve_nameref(name.as_ref()).ok()?;
+        if split_subscript(name.as_ref()).1.is_some() {
+            return None;
+        }
+
         // Look through scopes, from the top of the stack on down.
         for (scope_type, map) in self.scopes.iter().rev() {
             if let Some(var) = map.get(name.as_ref()) {
@@ -182,7 +187,7 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve a mutable reference to the variable with the given name
-    /// in the environment.
+    /// in the environment, following any name references.
     ///
     /// # Arguments
     ///
@@ -191,6 +196,11 @@ impl ShellEnvironment {
         &mut self,
         name: S,
     ) -> Option<(EnvironmentScope, &mut ShellVariable)> {
+        let name = self.resolve_nameref(name.as_ref()).ok()?;
+        if split_subscript(name.as_ref()).1.is_some() {
+            return None;
+        }
+
         // Look through scopes, from the top of the stack on down.
         for (scope_type, map) in self.scopes.iter_mut().rev() {
             if let Some(var) = map.get_mut(name.as_ref()) {
@@ -201,6 +211,60 @@ impl ShellEnvironment {
         None
     }
 
+    /// Resolves the given name through any chain of name references (i.e., variables
+    /// declared with `declare -n`), returning the name of the variable ultimately referred
+    /// to. The resolved name may include an array subscript (e.g., `arr[2]`). Names that
+    /// don't refer to a name reference are returned as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to resolve.
+    pub fn resolve_nameref<'a>(&self, name: &'a str) -> Result<Cow<'a, str>, error::Error> {
+        self.resolve_nameref_using_policy(name, EnvironmentLookup::Anywhere)
+    }
+
+    /// Resolves the given name through any chain of name references, using the given
+    /// lookup policy to find the first variable in the chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to resolve.
+    /// * `lookup_policy` - The policy to use when looking up the first variable.
+    pub fn resolve_nameref_using_policy<'a>(
+        &self,
+        name: &'a str,
+        lookup_policy: EnvironmentLookup,
+    ) -> Result<Cow<'a, str>, error::Error> {
+        let mut resolved = Cow::Borrowed(name);
+        let mut lookup_policy = lookup_policy;
+        let mut seen = vec![];
+
+        while let Some(var) = self.get_using_policy(resolved.as_ref(), lookup_policy) {
+            // A name reference without a value refers to nothing.
+            let target = match var.value() {
+                ShellValue::String(target) if var.is_treated_as_nameref() && !target.is_empty() => {
+                    target
+                }
+                _ => break,
+            };
+
+            seen.push(resolved.into_owned());
+            if seen.iter().any(|n| n == target) {
+                return Err(error::Error::CircularNameReference(name.to_owned()));
+            }
+
+            resolved = Cow::Owned(target.clone());
+            lookup_policy = EnvironmentLookup::Anywhere;
+
+            // An array element can't itself be a name reference.
+            if split_subscript(target).1.is_some() {
+                break;
+            }
+        }
+
+        Ok(resolved)
+    }
+
     /// Tries to retrieve the string value of the variable with the given name in the
     /// environment.
     ///
@@ -230,13 +294,30 @@ impl ShellEnvironment {
     // Setters
     //
 
-    /// Tries to unset the variable with the given name in the environment, returning
-    /// whether or not such a variable existed.
+    /// Tries to unset the variable with the given name in the environment, following any
+    /// name references, and returning whether or not such a variable existed.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to unset.
     pub fn unset(&mut self, name: &str) -> Result<Option<ShellVariable>, error::Error> {
+        let name = self.resolve_nameref(name)?;
+        if let (array_name, Some(index)) = split_subscript(name.as_ref()) {
+            self.unset_index(array_name, index)?;
+            return Ok(None);
+        }
+
+        self.unset_nameref(name.as_ref())
+    }
+
+    /// Tries to unset the variable with the given name in the environment, returning
+    /// whether or not such a variable existed. Unlike `unset`, a name reference is itself
+    /// unset rather than the variable it refers to.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to unset.
+    pub fn unset_nameref(&mut self, name: &str) -> Result<Option<ShellVariable>, error::Error> {
         let mut local_count = 0;
         for (scope_type, map) in self.scopes.iter_mut().rev() {
             if matches!(scope_type, EnvironmentScope::Local) {
@@ -273,6 +354,9 @@ impl ShellEnvironment {
     /// * `name` - The name of the array variable to unset an element from.
     /// * `index` - The index of the element to unset.
     pub fn unset_index(&mut self, name: &str, index: &str) -> Result<bool, error::Error> {
+        let name = self.resolve_nameref(name)?;
+        let (name, _) = split_subscript(name.as_ref());
+
         if let Some((_, var)) = self.get_mut(name) {
             var.unset_index(index)
         } else {
@@ -410,7 +494,24 @@ impl ShellEnvironment {
         lookup_policy: EnvironmentLookup,
         scope_if_creating: EnvironmentScope,
     ) -> Result<(), error::Error> {
-        let name = name.into();
+        let (name, lookup_policy, scope_if_creating) =
+            self.resolve_nameref_for_update(name.into(), lookup_policy, scope_if_creating)?;
+
+        // Assigning to a name reference to an array element updates that element.
+        if let (array_name, Some(index)) = split_subscript(name.as_str()) {
+            let variables::ShellValueLiteral::Scalar(value) = value else {
+                return Err(error::Error::AssigningListToArrayMember);
+            };
+
+            return self.update_or_add_array_element(
+                array_name,
+                index.to_owned(),
+                value,
+                updater,
+                lookup_policy,
+                scope_if_creating,
+            );
+        }
 
         let auto_export = self.export_variables_on_modification;
         if let Some(var) = self.get_mut_using_policy(&name, lookup_policy) {
@@ -450,7 +551,9 @@ impl ShellEnvironment {
         lookup_policy: EnvironmentLookup,
         scope_if_creating: EnvironmentScope,
     ) -> Result<(), error::Error> {
-        let name = name.into();
+        let (name, lookup_policy, scope_if_creating) =
+            self.resolve_nameref_for_update(name.into(), lookup_policy, scope_if_creating)?;
+        let name = split_subscript(name.as_str()).0.to_owned();
 
         if let Some(var) = self.get_mut_using_policy(&name, lookup_policy) {
             var.assign_at_index(index, value, false)?;
@@ -470,6 +573,36 @@ impl ShellEnvironment {
         }
     }
 
+    /// Resolves the name of a variable about to be updated through any name references.
+    /// If the name is resolved to a different variable, then that variable is looked up
+    /// anywhere and created (if needed) in the global scope, as the referenced variable
+    /// needn't be local. As in bash, a circular chain of references is warned about and
+    /// the global variable of the given name is updated instead.
+    fn resolve_nameref_for_update(
+        &self,
+        name: String,
+        lookup_policy: EnvironmentLookup,
+        scope_if_creating: EnvironmentScope,
+    ) -> Result<(String, EnvironmentLookup, EnvironmentScope), error::Error> {
+        match self.resolve_nameref_using_policy(name.as_str(), lookup_policy) {
+            Ok(Cow::Borrowed(_)) => Ok((name, lookup_policy, scope_if_creating)),
+            Ok(Cow::Owned(resolved)) => Ok((
+                resolved,
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )),
+            Err(e @ error::Error::CircularNameReference(_)) => {
+                tracing::warn!("{e}");
+                Ok((
+                    name,
+                    EnvironmentLookup::OnlyInGlobal,
+                    EnvironmentScope::Global,
+                ))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Adds a variable to the environment.
     ///
     /// # Arguments
@@ -516,6 +649,22 @@ impl ShellEnvironment {
     }
 }
 
+/// Splits a variable name that may include an array subscript (e.g., `arr[2]`) into the
+/// name of the array and the (unevaluated) subscript.
+///
+/// # Arguments
+///
+/// * `name` - The name to split.
+pub(crate) fn split_subscript(name: &str) -> (&str, Option<&str>) {
+    if let Some(without_bracket) = name.strip_suffix(']') {
+        if let Some((array_name, index)) = without_bracket.split_once('[') {
+            return (array_name, Some(index));
+        }
+    }
+
+    (name, None)
+}
+
 /// Represents a map from names to shell variables.
 #[derive(Clone, Debug)]
 pub struct ShellVariableMap {
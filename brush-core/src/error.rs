@@ -99,6 +99,10 @@ pub enum Error {
     #[error("failed to decode utf-8")]
     Utf8Error(#[from] std::str::Utf8Error),
 
+    /// A name reference refers back to itself, directly or indirectly.
+    #[error("{0}: circular name reference")]
+    CircularNameReference(String),
+
     /// An attempt was made to modify a readonly variable.
     #[error("cannot mutate readonly variable")]
     ReadonlyVariable,
@@ -147,6 +151,10 @@ pub enum Error {
     #[error("threading error")]
     ThreadingError(#[from] tokio::task::JoinError),
 
+    /// A lock guarding shared state was poisoned by a panicking thread.
+    #[error("poisoned lock")]
+    PoisonedLock,
+
     /// An invalid signal was referenced.
     #[error("{0}: invalid signal specification")]
     InvalidSignal(String),
@@ -177,10 +185,6 @@ pub enum Error {
     #[error("bad file descriptor: {0}")]
     BadFileDescriptor(u32),
 
-    /// Printf failure
-    #[error("printf failure: {0}")]
-    PrintfFailure(i32),
-
     /// Interrupted
     #[error("interrupted")]
     Interrupted,
@@ -192,6 +196,10 @@ pub enum Error {
     /// System time error.
     #[error("system time error: {0}")]
     TimeError(#[from] std::time::SystemTimeError),
+
+    /// History expansion failed.
+    #[error("{0}")]
+    HistoryExpansionFailed(String),
 }
 
 /// Convenience function for returning an error for unimplemented functionality.
@@ -8,6 +8,10 @@ use crate::error;
 pub(crate) enum EscapeExpansionMode {
     EchoBuiltin,
     AnsiCQuotes,
+    /// Escapes in the format string given to the `printf` builtin.
+    PrintfFormat,
+    /// Escapes in an argument formatted by the `printf` builtin's `%b` directive.
+    PrintfArgument,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -29,10 +33,14 @@ pub(crate) fn expand_backslash_escapes(
             Some('b') => result.push(b'\x08'),
             Some('c') => {
                 match mode {
-                    EscapeExpansionMode::EchoBuiltin => {
+                    EscapeExpansionMode::EchoBuiltin | EscapeExpansionMode::PrintfArgument => {
                         // Stop all additional output!
                         return Ok((result, false));
                     }
+                    EscapeExpansionMode::PrintfFormat => {
+                        result.push(b'\\');
+                        result.push(b'c');
+                    }
                     EscapeExpansionMode::AnsiCQuotes => {
                         if let Some(_next_next) = it.next() {
                             return error::unimp("control character in ANSI C quotes");
@@ -50,29 +58,46 @@ pub(crate) fn expand_backslash_escapes(
             Some('t') => result.push(b'\t'),
             Some('v') => result.push(b'\x0b'),
             Some('\\') => result.push(b'\\'),
-            Some('\'') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'\''),
-            Some('\"') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'\"'),
-            Some('?') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'?'),
-            Some('0') => {
-                // Consume 0-3 valid octal chars
-                let mut taken_so_far = 0;
-                let mut octal_chars: String = it
-                    .take_while_ref(|c| {
-                        if taken_so_far < 3 && matches!(*c, '0'..='7') {
-                            taken_so_far += 1;
-                            true
-                        } else {
-                            false
-                        }
-                    })
-                    .collect();
+            Some(quote @ ('\'' | '"' | '?'))
+                if matches!(
+                    mode,
+                    EscapeExpansionMode::AnsiCQuotes | EscapeExpansionMode::PrintfFormat
+                ) =>
+            {
+                result.append(quote.to_string().into_bytes().as_mut());
+            }
+            Some(first @ '0'..='7') => {
+                // In a printf format string, an octal escape has up to 3 digits, including any
+                // leading 0. Elsewhere, it must start with a 0, which may be followed by up to 3
+                // more digits--though printf's %b also accepts the format string's style.
+                let (mut octal_chars, max_len) = match (mode, first) {
+                    (EscapeExpansionMode::PrintfFormat, _) => (first.to_string(), 3),
+                    (EscapeExpansionMode::PrintfArgument, '1'..='7') => (first.to_string(), 3),
+                    (_, '0') => (String::new(), 3),
+                    (_, _) => {
+                        result.push(b'\\');
+                        result.append(first.to_string().into_bytes().as_mut());
+                        continue;
+                    }
+                };
+
+                let mut taken_so_far = octal_chars.len();
+                octal_chars.extend(it.take_while_ref(|c| {
+                    if taken_so_far < max_len && matches!(*c, '0'..='7') {
+                        taken_so_far += 1;
+                        true
+                    } else {
+                        false
+                    }
+                }));
 
                 if octal_chars.is_empty() {
                     octal_chars.push('0');
                 }
 
-                let value = u8::from_str_radix(octal_chars.as_str(), 8)?;
-                result.push(value);
+                // N.B. Values too large for a byte are truncated.
+                let value = u32::from_str_radix(octal_chars.as_str(), 8)?;
+                result.push((value & 0xff) as u8);
             }
             Some('x') => {
                 // Consume 1-2 valid hex chars
@@ -199,8 +224,11 @@ pub(crate) fn quote<'a>(s: &'a str, options: &QuoteOptions) -> Cow<'a, str> {
         return ansi_c_quote(s).into();
     }
 
-    let use_default_quotes =
-        !use_ansi_c_quotes && (options.always_quote || s.is_empty() || s.contains(needs_escaping));
+    let use_default_quotes = !use_ansi_c_quotes
+        && (options.always_quote
+            || s.is_empty()
+            || s.contains(needs_escaping)
+            || s.starts_with(needs_escaping_at_start));
 
     if !use_default_quotes {
         return s.into();
@@ -237,9 +265,9 @@ fn backslash_escape(s: &str) -> String {
     let mut output = String::new();
 
     // TODO: Handle other interesting sequences.
-    for c in s.chars() {
+    for (i, c) in s.chars().enumerate() {
         match c {
-            c if needs_escaping(c) => {
+            c if needs_escaping(c) || (i == 0 && needs_escaping_at_start(c)) => {
                 output.push('\\');
                 output.push(c);
             }
@@ -356,6 +384,12 @@ fn needs_escaping(c: char) -> bool {
     )
 }
 
+// Returns whether or not the given character needs to be escaped (or quoted) if it starts
+// a word outside quotes.
+fn needs_escaping_at_start(c: char) -> bool {
+    matches!(c, '~' | '#')
+}
+
 fn needs_ansi_c_quoting(c: char) -> bool {
     c.is_ascii_control()
 }
@@ -369,6 +403,10 @@ mod tests {
         assert_eq!(quote_if_needed("a", QuoteMode::BackslashEscape), "a");
         assert_eq!(quote_if_needed("a b", QuoteMode::BackslashEscape), r"a\ b");
         assert_eq!(quote_if_needed("", QuoteMode::BackslashEscape), "");
+        assert_eq!(
+            quote_if_needed("~a#b", QuoteMode::BackslashEscape),
+            r"\~a#b"
+        );
     }
 
     #[test]
@@ -419,4 +457,23 @@ mod tests {
         assert_echo_expands_to(r"\u2620", "☠");
         assert_echo_expands_to(r"\U0001f602", "😂");
     }
+
+    fn assert_printf_expands_to(mode: EscapeExpansionMode, unexpanded: &str, expected: &[u8]) {
+        assert_eq!(
+            expand_backslash_escapes(unexpanded, mode).unwrap().0,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_printf_expansion() {
+        let format = EscapeExpansionMode::PrintfFormat;
+        assert_printf_expands_to(format, r"\101\0101", b"A\x081");
+        assert_printf_expands_to(format, r#"\'\"\?\c"#, br#"'"?\c"#);
+        assert_printf_expands_to(format, r"\777", b"\xff");
+
+        let argument = EscapeExpansionMode::PrintfArgument;
+        assert_printf_expands_to(argument, r"\101\0101\9", b"AA\\9");
+        assert_printf_expands_to(argument, r#"\'\"\?"#, br#"\'\"\?"#);
+    }
 }
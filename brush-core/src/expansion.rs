@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::cmp::min;
+use std::io::Write;
 
 use brush_parser::ast;
 use brush_parser::word::ParameterTransformOp;
@@ -1150,6 +1151,7 @@ impl<'a> WordExpander<'a> {
                 variable_name,
                 concatenate,
             } => {
+                let variable_name = self.shell.env.resolve_nameref(variable_name.as_str())?;
                 let keys = if let Some((_, var)) = self.shell.env.get(variable_name) {
                     var.value().get_element_keys(self.shell)
                 } else {
@@ -1174,7 +1176,8 @@ impl<'a> WordExpander<'a> {
         parameter: &brush_parser::word::Parameter,
         value: String,
     ) -> Result<(), error::Error> {
-        let (variable_name, index) = match parameter {
+        let parameter = self.resolve_nameref_parameter(parameter)?;
+        let (variable_name, index) = match parameter.as_ref() {
             brush_parser::word::Parameter::Named(name) => (name, None),
             brush_parser::word::Parameter::NamedWithIndex { name, index } => {
                 let is_set_assoc_array = if let Some((_, var)) = self.shell.env.get(name) {
@@ -1242,7 +1245,11 @@ impl<'a> WordExpander<'a> {
         &self,
         parameter: &brush_parser::word::Parameter,
     ) -> (Option<String>, Option<String>, Option<ShellVariable>) {
-        let (name, index) = match parameter {
+        let parameter = self
+            .resolve_nameref_parameter(parameter)
+            .unwrap_or(Cow::Borrowed(parameter));
+
+        let (name, index) = match parameter.as_ref() {
             brush_parser::word::Parameter::Positional(_)
             | brush_parser::word::Parameter::Special(_) => (None, None),
             brush_parser::word::Parameter::Named(name) => (Some(name.to_owned()), Some("0".into())),
@@ -1267,6 +1274,21 @@ impl<'a> WordExpander<'a> {
         parameter: &brush_parser::word::Parameter,
         indirect: bool,
     ) -> Result<Expansion, error::Error> {
+        // Indirection through a name reference yields the name of the variable it refers to.
+        if indirect {
+            if let brush_parser::word::Parameter::Named(name) = parameter {
+                if self
+                    .shell
+                    .env
+                    .get_using_policy(name, env::EnvironmentLookup::Anywhere)
+                    .is_some_and(|var| var.is_treated_as_nameref())
+                {
+                    let resolved = self.shell.env.resolve_nameref(name)?;
+                    return Ok(Expansion::from(resolved.into_owned()));
+                }
+            }
+        }
+
         let expansion = self.expand_parameter_without_indirect(parameter).await?;
         if !indirect {
             Ok(expansion)
@@ -1284,7 +1306,16 @@ impl<'a> WordExpander<'a> {
         &mut self,
         parameter: &brush_parser::word::Parameter,
     ) -> Result<Expansion, error::Error> {
-        match parameter {
+        let parameter = match self.resolve_nameref_parameter(parameter) {
+            Ok(parameter) => parameter,
+            Err(e @ error::Error::CircularNameReference(_)) => {
+                writeln!(self.params.stderr(), "warning: {e}")?;
+                return Ok(Expansion::undefined());
+            }
+            Err(e) => return Err(e),
+        };
+
+        match parameter.as_ref() {
             brush_parser::word::Parameter::Positional(p) => {
                 if *p == 0 {
                     Ok(self
@@ -1369,6 +1400,58 @@ impl<'a> WordExpander<'a> {
         }
     }
 
+    /// Resolves any name reference in the given parameter to the parameter it refers to.
+    fn resolve_nameref_parameter<'p>(
+        &self,
+        parameter: &'p brush_parser::word::Parameter,
+    ) -> Result<Cow<'p, brush_parser::word::Parameter>, error::Error> {
+        let name = match parameter {
+            brush_parser::word::Parameter::Named(name)
+            | brush_parser::word::Parameter::NamedWithIndex { name, index: _ }
+            | brush_parser::word::Parameter::NamedWithAllIndices {
+                name,
+                concatenate: _,
+            } => name,
+            brush_parser::word::Parameter::Positional(_)
+            | brush_parser::word::Parameter::Special(_) => return Ok(Cow::Borrowed(parameter)),
+        };
+
+        let Cow::Owned(resolved) = self.shell.env.resolve_nameref(name)? else {
+            return Ok(Cow::Borrowed(parameter));
+        };
+
+        let (resolved_name, resolved_index) = env::split_subscript(resolved.as_str());
+        let name = resolved_name.to_owned();
+
+        let resolved_parameter = match (parameter, resolved_index) {
+            (brush_parser::word::Parameter::Named(_), Some(index)) => {
+                brush_parser::word::Parameter::NamedWithIndex {
+                    name,
+                    index: index.to_owned(),
+                }
+            }
+            (brush_parser::word::Parameter::NamedWithIndex { name: _, index }, _) => {
+                brush_parser::word::Parameter::NamedWithIndex {
+                    name,
+                    index: index.clone(),
+                }
+            }
+            (
+                brush_parser::word::Parameter::NamedWithAllIndices {
+                    name: _,
+                    concatenate,
+                },
+                _,
+            ) => brush_parser::word::Parameter::NamedWithAllIndices {
+                name,
+                concatenate: *concatenate,
+            },
+            _ => brush_parser::word::Parameter::Named(name),
+        };
+
+        Ok(Cow::Owned(resolved_parameter))
+    }
+
     async fn expand_array_index(
         &mut self,
         index: &str,
@@ -25,15 +25,34 @@ impl FunctionEnv {
         self.functions.remove(name)
     }
 
-    /// Updates a function registration in this environment.
+    /// Tries to retrieve a mutable reference to the registration for a function by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the function to retrieve.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut FunctionRegistration> {
+        self.functions.get_mut(name)
+    }
+
+    /// Updates a function registration in this environment. Any attributes of an
+    /// existing registration are preserved.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the function to update.
     /// * `definition` - The new definition for the function.
     pub fn update(&mut self, name: String, definition: Arc<brush_parser::ast::FunctionDefinition>) {
-        self.functions
-            .insert(name, FunctionRegistration { definition });
+        if let Some(registration) = self.functions.get_mut(&name) {
+            registration.definition = definition;
+        } else {
+            self.functions.insert(
+                name,
+                FunctionRegistration {
+                    definition,
+                    trace: false,
+                },
+            );
+        }
     }
 
     /// Returns an iterator over the functions registered in this environment.
@@ -47,4 +66,24 @@ impl FunctionEnv {
 pub struct FunctionRegistration {
     /// The definition of the function.
     pub definition: Arc<brush_parser::ast::FunctionDefinition>,
+    /// Whether or not the function has the trace attribute, causing it to inherit the
+    /// `DEBUG` and `RETURN` traps.
+    trace: bool,
+}
+
+impl FunctionRegistration {
+    /// Returns whether or not the function has the trace attribute.
+    pub const fn is_trace_enabled(&self) -> bool {
+        self.trace
+    }
+
+    /// Gives the function the trace attribute.
+    pub fn enable_trace(&mut self) {
+        self.trace = true;
+    }
+
+    /// Removes the trace attribute from the function.
+    pub fn disable_trace(&mut self) {
+        self.trace = false;
+    }
 }
@@ -0,0 +1,998 @@
+//! Bang-style history expansion (e.g., `!!`, `!$`, `^old^new`), as applied to lines of
+//! input before they're parsed.
+
+use crate::history::History;
+
+/// Result of performing history expansion on a line of input.
+#[derive(Clone, Debug)]
+pub struct HistoryExpansion {
+    /// The line, after expansion.
+    pub line: String,
+    /// Whether any history references were expanded in the line.
+    pub expanded: bool,
+    /// Whether the `:p` modifier was used, in which case the line should be displayed (and
+    /// added to history) but not executed.
+    pub print_only: bool,
+}
+
+/// The special characters used by history expansion, as configured by `histchars`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HistoryChars {
+    /// Character that introduces a history reference; `!` by default.
+    pub expansion: char,
+    /// Character that introduces a quick substitution at the start of a line; `^` by default.
+    pub quick_substitution: char,
+    /// Character that, at the start of a word, disables expansion for the rest of the line;
+    /// `#` by default.
+    pub comment: char,
+}
+
+impl Default for HistoryChars {
+    fn default() -> Self {
+        Self {
+            expansion: '!',
+            quick_substitution: '^',
+            comment: '#',
+        }
+    }
+}
+
+impl HistoryChars {
+    /// Parses the value of the `histchars` variable, with any missing characters taking
+    /// their default values. Returns `None` if the value is empty, which disables history
+    /// expansion.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value of `histchars`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut chars = value.chars();
+        let defaults = Self::default();
+
+        Some(Self {
+            expansion: chars.next()?,
+            quick_substitution: chars.next().unwrap_or(defaults.quick_substitution),
+            comment: chars.next().unwrap_or(defaults.comment),
+        })
+    }
+}
+
+/// State carried over from one history expansion to the next.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ExpansionState {
+    /// The string most recently searched for with `!?string?`.
+    search_string: Option<String>,
+    /// The word of the history entry matched by the most recent `!?string?` search.
+    search_match: Option<String>,
+    /// The text replaced and its replacement in the most recent `:s` substitution.
+    substitution: Option<(String, String)>,
+}
+
+/// Performs history expansion on a line of input.
+///
+/// # Arguments
+///
+/// * `line` - The line to expand.
+/// * `history` - The history list that references are resolved against.
+/// * `chars` - The special characters used by history expansion.
+/// * `extended_globbing` - Whether extended globbing is enabled, in which case `!(...)`
+///   is left alone.
+/// * `state` - State carried over between expansions.
+pub(crate) fn expand(
+    line: &str,
+    history: &History,
+    chars: HistoryChars,
+    extended_globbing: bool,
+    state: &mut ExpansionState,
+) -> Result<HistoryExpansion, String> {
+    let mut expander = Expander {
+        line: line.chars().collect(),
+        history,
+        chars,
+        extended_globbing,
+        state,
+        result: String::new(),
+        expanded: false,
+        print_only: false,
+    };
+
+    expander.expand()?;
+
+    Ok(HistoryExpansion {
+        line: expander.result,
+        expanded: expander.expanded,
+        print_only: expander.print_only,
+    })
+}
+
+/// Characters that, following the expansion character, keep it from being expanded.
+const NO_EXPAND_CHARS: &[char] = &[' ', '\t', '\n', '\r', '='];
+
+/// Characters that terminate the search string in a `!string` reference.
+const SEARCH_DELIMITER_CHARS: &[char] = &[';', '&', '(', ')', '|', '<', '>'];
+
+/// Characters that separate words in a history entry.
+const WORD_DELIMITER_CHARS: &[char] = &[' ', '\t', '\n', ';', '&', '(', ')', '|', '<', '>'];
+
+/// Characters that may directly follow the expansion character to apply a word
+/// designator to the previous command.
+const IMPLIED_PREVIOUS_CHARS: &[char] = &[':', '$', '*', '%', '^'];
+
+struct Expander<'a> {
+    line: Vec<char>,
+    history: &'a History,
+    chars: HistoryChars,
+    extended_globbing: bool,
+    state: &'a mut ExpansionState,
+    result: String,
+    expanded: bool,
+    print_only: bool,
+}
+
+/// Bound of a word range, as given in a word designator.
+#[derive(Clone, Copy)]
+enum WordBound {
+    /// The word with the given 0-based index.
+    Index(usize),
+    /// The last word.
+    Last,
+    /// The word before the last word.
+    NextToLast,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self) -> Result<(), String> {
+        let mut i = 0;
+
+        if self.line.first() == Some(&self.chars.quick_substitution) {
+            i = self.expand_quick_substitution()?;
+        }
+
+        let mut in_double_quotes = false;
+        while i < self.line.len() {
+            let c = self.line[i];
+
+            if c == '\\' && i + 1 < self.line.len() {
+                self.result.push(c);
+                self.result.push(self.line[i + 1]);
+                i += 2;
+            } else if c == '\'' && !in_double_quotes {
+                // Single quotes inhibit expansion up through the closing quote.
+                let end = self.line[i + 1..]
+                    .iter()
+                    .position(|c| *c == '\'')
+                    .map_or(self.line.len(), |offset| i + offset + 2);
+                self.result.extend(&self.line[i..end]);
+                i = end;
+            } else if c == '"' {
+                in_double_quotes = !in_double_quotes;
+                self.result.push(c);
+                i += 1;
+            } else if c == self.chars.comment
+                && !in_double_quotes
+                && (i == 0 || WORD_DELIMITER_CHARS.contains(&self.line[i - 1]))
+            {
+                // The rest of the line is a comment.
+                self.result.extend(&self.line[i..]);
+                i = self.line.len();
+            } else if c == self.chars.expansion && !self.is_inhibited(i, in_double_quotes) {
+                i = self.expand_reference(i, in_double_quotes)?;
+                self.expanded = true;
+            } else {
+                self.result.push(c);
+                i += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the expansion character at the given index should be left alone.
+    fn is_inhibited(&self, i: usize, in_double_quotes: bool) -> bool {
+        let Some(next) = self.line.get(i + 1) else {
+            return true;
+        };
+
+        let previous = i.checked_sub(1).map(|p| self.line[p]);
+        let before_previous = i.checked_sub(2).map(|p| self.line[p]);
+        let rest = &self.line[i + 1..];
+
+        NO_EXPAND_CHARS.contains(next)
+            || (in_double_quotes && *next == '"')
+            // Negated bracket expressions: `[!...]`
+            || (previous == Some('[') && rest.contains(&']'))
+            // Indirect parameter expansion: `${!...}`
+            || (previous == Some('{') && before_previous == Some('$') && rest.contains(&'}'))
+            // The `$!` special parameter.
+            || previous == Some('$')
+            // Negated extended glob patterns: `!(...)`
+            || (self.extended_globbing && *next == '(' && rest.contains(&')'))
+    }
+
+    /// Expands a quick substitution (`^old^new^`) at the start of the line, returning the
+    /// index of the first character following it.
+    fn expand_quick_substitution(&mut self) -> Result<usize, String> {
+        let delimiter = self.chars.quick_substitution;
+        let (old, i) = self.parse_substitution_part(1, delimiter, false);
+        let (new, i) = self.parse_substitution_part(i, delimiter, true);
+
+        let error_text: String = std::iter::once(':')
+            .chain(std::iter::once('s'))
+            .chain(self.line[..i].iter().copied())
+            .collect();
+
+        let mut text = self.previous_command(error_text.as_str())?;
+        text = self.substitute(text.as_str(), old, new, false, error_text.as_str())?;
+
+        self.result.push_str(text.as_str());
+        self.expanded = true;
+
+        Ok(i)
+    }
+
+    /// Expands the history reference starting at the given index, returning the index of
+    /// the first character following it.
+    fn expand_reference(&mut self, start: usize, in_double_quotes: bool) -> Result<usize, String> {
+        let (event, mut i) = self.resolve_event(start, in_double_quotes)?;
+
+        let mut text = match self.resolve_words(event.as_str(), i)? {
+            Some((words, next)) => {
+                i = next;
+                words
+            }
+            None => event,
+        };
+
+        let modifiers_start = i;
+        while self.line.get(i) == Some(&':') {
+            let (modified, next) = self.apply_modifier(text, i, modifiers_start)?;
+            text = modified;
+            i = next;
+        }
+
+        self.result.push_str(text.as_str());
+
+        Ok(i)
+    }
+
+    /// Resolves the event designator of the history reference starting at the given index,
+    /// returning the selected command line and the index of the first character following
+    /// the designator.
+    fn resolve_event(
+        &mut self,
+        start: usize,
+        in_double_quotes: bool,
+    ) -> Result<(String, usize), String> {
+        let mut i = start + 1;
+        let next = self.line[i];
+
+        if next == self.chars.expansion {
+            let command = self.previous_command(self.text(start, i + 1).as_str())?;
+            return Ok((command, i + 1));
+        }
+
+        if IMPLIED_PREVIOUS_CHARS.contains(&next) {
+            let command = self.previous_command(self.text(start, i).as_str())?;
+            return Ok((command, i));
+        }
+
+        if next == '#' {
+            return Ok((self.result.clone(), i + 1));
+        }
+
+        // `!n` and `!-n` refer to commands by number.
+        let negative = next == '-';
+        let digits_start = if negative { i + 1 } else { i };
+        let digits_end = self.line[digits_start..]
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .map_or(self.line.len(), |offset| digits_start + offset);
+
+        if digits_end > digits_start {
+            let digits: String = self.line[digits_start..digits_end].iter().collect();
+            let number = digits.parse::<usize>().unwrap_or(usize::MAX);
+
+            let index = if negative {
+                self.history.len().checked_sub(number)
+            } else {
+                number.checked_sub(self.history.base())
+            };
+
+            let command = index
+                .and_then(|index| self.history.get(index))
+                .map(|item| item.command_line.clone())
+                .ok_or_else(|| event_not_found(self.text(start, digits_end).as_str()))?;
+
+            return Ok((command, digits_end));
+        }
+
+        // `!?string?` refers to the most recent command containing the string.
+        if next == '?' {
+            i += 1;
+            let search_start = i;
+            while i < self.line.len() && self.line[i] != '?' && self.line[i] != '\n' {
+                i += 1;
+            }
+            let search: String = self.line[search_start..i].iter().collect();
+            if self.line.get(i) == Some(&'?') {
+                i += 1;
+            }
+
+            let search = if search.is_empty() {
+                self.state.search_string.clone().unwrap_or_default()
+            } else {
+                search
+            };
+
+            let found = if search.is_empty() {
+                None
+            } else {
+                self.history.iter().rev().find_map(|item| {
+                    item.command_line
+                        .find(search.as_str())
+                        .map(|offset| (item.command_line.clone(), offset))
+                })
+            };
+
+            let Some((command, offset)) = found else {
+                return Err(event_not_found(self.text(start, i).as_str()));
+            };
+
+            self.state.search_match = word_at_offset(command.as_str(), offset);
+            self.state.search_string = Some(search);
+
+            return Ok((command, i));
+        }
+
+        // `!string` refers to the most recent command starting with the string.
+        while i < self.line.len() {
+            let c = self.line[i];
+            if c.is_whitespace()
+                || c == ':'
+                || SEARCH_DELIMITER_CHARS.contains(&c)
+                || (in_double_quotes && c == '"')
+            {
+                break;
+            }
+            i += 1;
+        }
+
+        let prefix: String = self.line[start + 1..i].iter().collect();
+        let command = if prefix.is_empty() {
+            None
+        } else {
+            self.history
+                .iter()
+                .rev()
+                .find(|item| item.command_line.starts_with(prefix.as_str()))
+                .map(|item| item.command_line.clone())
+        };
+
+        command
+            .map(|command| (command, i))
+            .ok_or_else(|| event_not_found(self.text(start, i).as_str()))
+    }
+
+    /// Resolves the word designator (if any) at the given index, returning the selected
+    /// words of the event and the index of the first character following the designator.
+    fn resolve_words(&self, event: &str, start: usize) -> Result<Option<(String, usize)>, String> {
+        let mut i = start;
+        let mut expecting_word_spec = false;
+
+        if self.line.get(i) == Some(&':') {
+            i += 1;
+            expecting_word_spec = true;
+        }
+
+        let words = split_words(event);
+        let bad_word_spec = |end: usize| format!("{}: bad word specifier", self.text(start, end));
+
+        let first = match self.line.get(i) {
+            Some('%') => {
+                let word = self.state.search_match.clone().unwrap_or_default();
+                return Ok(Some((word, i + 1)));
+            }
+            Some('*') => {
+                let selected = select_words(&words, WordBound::Index(1), WordBound::Last);
+                return Ok(Some((selected.unwrap_or_default(), i + 1)));
+            }
+            Some('$') => {
+                let selected = select_words(&words, WordBound::Last, WordBound::Last);
+                return selected
+                    .map(|words| Some((words, i + 1)))
+                    .ok_or_else(|| bad_word_spec(i + 1));
+            }
+            Some('-') => 0,
+            Some('^') => {
+                i += 1;
+                1
+            }
+            Some(c) if c.is_ascii_digit() && expecting_word_spec => {
+                let (number, next) = self.parse_number(i);
+                i = next;
+                number
+            }
+            _ => return Ok(None),
+        };
+
+        let last = match self.line.get(i) {
+            Some('^') => {
+                i += 1;
+                WordBound::Index(1)
+            }
+            Some('*') => {
+                i += 1;
+                WordBound::Last
+            }
+            Some('-') => {
+                i += 1;
+                match self.line.get(i) {
+                    Some(c) if c.is_ascii_digit() => {
+                        let (number, next) = self.parse_number(i);
+                        i = next;
+                        WordBound::Index(number)
+                    }
+                    Some('$') => {
+                        i += 1;
+                        WordBound::Last
+                    }
+                    Some('^') => {
+                        i += 1;
+                        WordBound::Index(1)
+                    }
+                    _ => WordBound::NextToLast,
+                }
+            }
+            _ => WordBound::Index(first),
+        };
+
+        select_words(&words, WordBound::Index(first), last)
+            .map(|words| Some((words, i)))
+            .ok_or_else(|| bad_word_spec(i))
+    }
+
+    /// Applies the modifier starting (with a `:`) at the given index to the text, returning
+    /// the modified text and the index of the first character following the modifier.
+    /// Errors refer to the modifiers applied so far, which start at `modifiers_start`.
+    fn apply_modifier(
+        &mut self,
+        text: String,
+        start: usize,
+        modifiers_start: usize,
+    ) -> Result<(String, usize), String> {
+        let mut i = start + 1;
+
+        let mut global = false;
+        let mut by_words = false;
+        match self.line.get(i) {
+            Some('g' | 'a') => {
+                global = true;
+                i += 1;
+            }
+            Some('G') => {
+                by_words = true;
+                i += 1;
+            }
+            _ => (),
+        }
+
+        let Some(modifier) = self.line.get(i).copied() else {
+            return Err(format!(
+                "{}: unrecognized history modifier",
+                self.text(i, i)
+            ));
+        };
+        i += 1;
+
+        let modified = match modifier {
+            'h' => match text.rfind('/') {
+                Some(index) => text[..index].to_owned(),
+                None => text,
+            },
+            't' => match text.rfind('/') {
+                Some(index) => text[index + 1..].to_owned(),
+                None => text,
+            },
+            'r' => match text.rfind('.') {
+                Some(index) => text[..index].to_owned(),
+                None => text,
+            },
+            'e' => match text.rfind('.') {
+                Some(index) => text[index..].to_owned(),
+                None => text,
+            },
+            'p' => {
+                self.print_only = true;
+                text
+            }
+            'q' => single_quote(text.as_str()),
+            'x' => text
+                .split([' ', '\t', '\n'])
+                .filter(|word| !word.is_empty())
+                .map(single_quote)
+                .collect::<Vec<_>>()
+                .join(" "),
+            's' | '&' => {
+                let (old, new) = if modifier == 's' {
+                    let delimiter = self.line.get(i).copied().unwrap_or('/');
+                    let (old, next) = self.parse_substitution_part(i + 1, delimiter, false);
+                    let (new, next) = self.parse_substitution_part(next, delimiter, true);
+                    i = next;
+                    (old, new)
+                } else {
+                    let (old, new) = self.state.substitution.clone().ok_or_else(|| {
+                        format!(
+                            "{}: no previous substitution",
+                            self.text(modifiers_start, i)
+                        )
+                    })?;
+                    (Some(old), Some(new))
+                };
+
+                let error_text = self.text(modifiers_start, i);
+                if by_words {
+                    let mut words = vec![];
+                    for word in text.split(' ') {
+                        words.push(
+                            self.substitute(word, old.clone(), new.clone(), false, &error_text)
+                                .unwrap_or_else(|_| word.to_owned()),
+                        );
+                    }
+                    words.join(" ")
+                } else {
+                    self.substitute(text.as_str(), old, new, global, &error_text)?
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "{}: unrecognized history modifier",
+                    self.text(i - 1, i)
+                ))
+            }
+        };
+
+        Ok((modified, i))
+    }
+
+    /// Parses the old or new text of a substitution, up through the given delimiter (or the
+    /// end of the line), returning it along with the index of the first character following
+    /// it. A backslash quotes the delimiter. Returns `None` if the text is empty, in which
+    /// case it's inherited from the previous substitution.
+    fn parse_substitution_part(
+        &self,
+        start: usize,
+        delimiter: char,
+        is_replacement: bool,
+    ) -> (Option<String>, usize) {
+        let mut part = String::new();
+        let mut i = start;
+
+        while i < self.line.len() {
+            let c = self.line[i];
+            if c == delimiter {
+                i += 1;
+                break;
+            } else if c == '\\' && self.line.get(i + 1) == Some(&delimiter) {
+                part.push(delimiter);
+                i += 2;
+            } else if c == '\n' {
+                break;
+            } else {
+                part.push(c);
+                i += 1;
+            }
+        }
+
+        // N.B. An empty replacement is meaningful; it's only the text being replaced that's
+        // inherited when empty.
+        if part.is_empty() && !is_replacement {
+            (None, i)
+        } else {
+            (Some(part), i)
+        }
+    }
+
+    /// Replaces the first (or, if `global` is set, every) occurrence of `old` in the text
+    /// with `new`, in which an `&` stands for `old`.
+    fn substitute(
+        &mut self,
+        text: &str,
+        old: Option<String>,
+        new: Option<String>,
+        global: bool,
+        error_text: &str,
+    ) -> Result<String, String> {
+        let previous = self.state.substitution.clone();
+
+        let old = old
+            .or_else(|| self.state.search_string.clone())
+            .or_else(|| previous.as_ref().map(|(old, _)| old.clone()))
+            .ok_or_else(|| format!("{error_text}: no previous substitution"))?;
+        let new = new
+            .or_else(|| previous.map(|(_, new)| new))
+            .unwrap_or_default();
+
+        self.state.substitution = Some((old.clone(), new.clone()));
+
+        let replacement = expand_replacement(new.as_str(), old.as_str());
+
+        if !text.contains(old.as_str()) {
+            return Err(format!("{error_text}: substitution failed"));
+        }
+
+        if global {
+            Ok(text.replace(old.as_str(), replacement.as_str()))
+        } else {
+            Ok(text.replacen(old.as_str(), replacement.as_str(), 1))
+        }
+    }
+
+    /// Returns the most recent command in the history list.
+    fn previous_command(&self, reference: &str) -> Result<String, String> {
+        self.history
+            .last()
+            .map(|item| item.command_line.clone())
+            .ok_or_else(|| event_not_found(reference))
+    }
+
+    fn parse_number(&self, start: usize) -> (usize, usize) {
+        let end = self.line[start..]
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .map_or(self.line.len(), |offset| start + offset);
+
+        let digits: String = self.line[start..end].iter().collect();
+        (digits.parse().unwrap_or(usize::MAX), end)
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.line.len());
+        if start >= end {
+            self.line
+                .get(start)
+                .map(char::to_string)
+                .unwrap_or_default()
+        } else {
+            self.line[start..end].iter().collect()
+        }
+    }
+}
+
+fn event_not_found(reference: &str) -> String {
+    format!("{reference}: event not found")
+}
+
+/// Selects the given range of words, joining them with spaces. Returns `None` if the
+/// range doesn't refer to words that exist.
+fn select_words(words: &[String], first: WordBound, last: WordBound) -> Option<String> {
+    let resolve = |bound: WordBound| match bound {
+        WordBound::Index(index) => Some(index),
+        WordBound::Last => words.len().checked_sub(1),
+        WordBound::NextToLast => words.len().checked_sub(2),
+    };
+
+    let first = resolve(first)?;
+    let last = resolve(last)?;
+
+    if first >= words.len() || last >= words.len() || first > last {
+        return None;
+    }
+
+    Some(words[first..=last].join(" "))
+}
+
+/// Expands the replacement text of a substitution, in which an unquoted `&` stands for the
+/// text being replaced.
+fn expand_replacement(new: &str, old: &str) -> String {
+    let mut expanded = String::new();
+
+    let mut chars = new.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'&') => {
+                expanded.push('&');
+                chars.next();
+            }
+            '&' => expanded.push_str(old),
+            c => expanded.push(c),
+        }
+    }
+
+    expanded
+}
+
+fn single_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Returns the word of the command line containing the character at the given byte offset.
+fn word_at_offset(command_line: &str, offset: usize) -> Option<String> {
+    split_words_with_offsets(command_line)
+        .into_iter()
+        .find(|(start, word)| *start <= offset && offset < start + word.len())
+        .map(|(_, word)| word)
+}
+
+/// Splits a command line into words, the way word designators see them: shell
+/// metacharacters form words of their own, and quoted text is kept together.
+fn split_words(command_line: &str) -> Vec<String> {
+    split_words_with_offsets(command_line)
+        .into_iter()
+        .map(|(_, word)| word)
+        .collect()
+}
+
+fn split_words_with_offsets(command_line: &str) -> Vec<(usize, String)> {
+    let chars: Vec<(usize, char)> = command_line.char_indices().collect();
+    let mut words = vec![];
+
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].1 == ' ' || chars[i].1 == '\t' {
+            i += 1;
+            continue;
+        }
+
+        let end = word_end(&chars, i);
+        let start_offset = chars[i].0;
+        let word: String = chars[i..end].iter().map(|(_, c)| *c).collect();
+        words.push((start_offset, word));
+        i = end;
+    }
+
+    words
+}
+
+/// Returns the index just past the end of the word starting at the given index.
+fn word_end(chars: &[(usize, char)], start: usize) -> usize {
+    let c = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let mut i = start;
+
+    if matches!(c(i), Some('(' | ')' | '\n')) {
+        return i + 1;
+    }
+
+    let mut open_delimiter = None;
+    let mut delimiter = None;
+    let mut nesting = 0;
+
+    // A sequence of digits directly followed by a redirection operator is part of the
+    // operator.
+    if c(i).is_some_and(|c| c.is_ascii_digit()) {
+        let mut j = i;
+        while c(j).is_some_and(|c| c.is_ascii_digit()) {
+            j += 1;
+        }
+        i = j;
+        if !matches!(c(i), Some('<' | '>')) {
+            return word_body_end(chars, i, open_delimiter, delimiter, nesting);
+        }
+    }
+
+    if let Some(current) = c(i) {
+        if matches!(current, '<' | '>' | ';' | '&' | '|' | '$') {
+            let peek = c(i + 1);
+            if peek == Some(current) && current != '$' {
+                if current == '<' && matches!(c(i + 2), Some('-' | '<')) {
+                    i += 1;
+                }
+                return i + 2;
+            } else if peek == Some('&') && matches!(current, '<' | '>') {
+                let mut j = i + 2;
+                while c(j).is_some_and(|c| c.is_ascii_digit()) {
+                    j += 1;
+                }
+                if c(j) == Some('-') {
+                    j += 1;
+                }
+                return j;
+            } else if (peek == Some('>') && current == '&') || (peek == Some('|') && current == '>')
+            {
+                return i + 2;
+            } else if peek == Some('(') && matches!(current, '<' | '>' | '$') {
+                i += 2;
+                open_delimiter = Some('(');
+                delimiter = Some(')');
+                nesting = 1;
+            } else if current != '$' {
+                return i + 1;
+            }
+        }
+    }
+
+    word_body_end(chars, i, open_delimiter, delimiter, nesting)
+}
+
+fn word_body_end(
+    chars: &[(usize, char)],
+    start: usize,
+    mut open_delimiter: Option<char>,
+    mut delimiter: Option<char>,
+    mut nesting: usize,
+) -> usize {
+    let c = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let mut i = start;
+
+    if delimiter.is_none() && matches!(c(i), Some('\'' | '"' | '`')) {
+        delimiter = c(i);
+        i += 1;
+    }
+
+    while let Some(current) = c(i) {
+        if current == '\\' && delimiter != Some('\'') {
+            i += 2;
+            continue;
+        }
+
+        if nesting > 0 && Some(current) == open_delimiter {
+            nesting += 1;
+        } else if nesting > 0 && Some(current) == delimiter {
+            nesting -= 1;
+            if nesting == 0 {
+                delimiter = None;
+            }
+        } else if nesting == 0 && delimiter == Some(current) {
+            delimiter = None;
+        } else if nesting == 0
+            && delimiter.is_none()
+            && matches!(current, '<' | '>' | '$' | '!' | '@' | '?' | '+' | '*')
+            && c(i + 1) == Some('(')
+        {
+            i += 2;
+            open_delimiter = Some('(');
+            delimiter = Some(')');
+            nesting = 1;
+            continue;
+        } else if delimiter.is_none() && WORD_DELIMITER_CHARS.contains(&current) {
+            break;
+        } else if delimiter.is_none() && matches!(current, '\'' | '"' | '`') {
+            delimiter = Some(current);
+        }
+
+        i += 1;
+    }
+
+    i.min(chars.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryItem;
+
+    fn history_of(commands: &[&str]) -> History {
+        let mut history = History::default();
+        for command in commands {
+            history.add(HistoryItem::new((*command).to_owned()));
+        }
+        history
+    }
+
+    fn expand_str(line: &str, history: &History) -> Result<String, String> {
+        let mut state = ExpansionState::default();
+        expand(line, history, HistoryChars::default(), false, &mut state).map(|e| e.line)
+    }
+
+    #[test]
+    fn event_designators() {
+        let history = history_of(&["echo a b c", "ls -l /tmp", "echo x"]);
+
+        assert_eq!(expand_str("!!", &history).unwrap(), "echo x");
+        assert_eq!(expand_str("sudo !!", &history).unwrap(), "sudo echo x");
+        assert_eq!(expand_str("!1", &history).unwrap(), "echo a b c");
+        assert_eq!(expand_str("!-2", &history).unwrap(), "ls -l /tmp");
+        assert_eq!(expand_str("!ls", &history).unwrap(), "ls -l /tmp");
+        assert_eq!(expand_str("!ec", &history).unwrap(), "echo x");
+        assert_eq!(expand_str("!?b?", &history).unwrap(), "echo a b c");
+        assert_eq!(expand_str("echo !#", &history).unwrap(), "echo echo ");
+        assert_eq!(
+            expand_str("!nope", &history).unwrap_err(),
+            "!nope: event not found"
+        );
+        assert_eq!(
+            expand_str("!9", &history).unwrap_err(),
+            "!9: event not found"
+        );
+    }
+
+    #[test]
+    fn word_designators() {
+        let history = history_of(&["echo a b c"]);
+
+        assert_eq!(expand_str("!$", &history).unwrap(), "c");
+        assert_eq!(expand_str("!^", &history).unwrap(), "a");
+        assert_eq!(expand_str("!*", &history).unwrap(), "a b c");
+        assert_eq!(expand_str("!:0", &history).unwrap(), "echo");
+        assert_eq!(expand_str("!!:1-2", &history).unwrap(), "a b");
+        assert_eq!(expand_str("!!:2*", &history).unwrap(), "b c");
+        assert_eq!(expand_str("!!:2-", &history).unwrap(), "b");
+        assert_eq!(expand_str("!!:-2", &history).unwrap(), "echo a b");
+        assert_eq!(
+            expand_str("!!:9", &history).unwrap_err(),
+            ":9: bad word specifier"
+        );
+    }
+
+    #[test]
+    fn modifiers() {
+        let history = history_of(&["ls /a/b/c.txt", "echo a a"]);
+
+        assert_eq!(expand_str("!ls:$:h", &history).unwrap(), "/a/b");
+        assert_eq!(expand_str("!ls:$:t", &history).unwrap(), "c.txt");
+        assert_eq!(expand_str("!ls:$:r", &history).unwrap(), "/a/b/c");
+        assert_eq!(expand_str("!ls:$:e", &history).unwrap(), ".txt");
+        assert_eq!(expand_str("!!:s/a/b/", &history).unwrap(), "echo b a");
+        assert_eq!(expand_str("!!:gs/a/b/", &history).unwrap(), "echo b b");
+        assert_eq!(expand_str("!!:s/a/[&]/", &history).unwrap(), "echo [a] a");
+        assert_eq!(expand_str("!!:s/a/b/:&", &history).unwrap(), "echo b b");
+        assert_eq!(expand_str("!!:q", &history).unwrap(), "'echo a a'");
+        assert_eq!(
+            expand_str("!!:s/z/b/", &history).unwrap_err(),
+            ":s/z/b/: substitution failed"
+        );
+        assert_eq!(
+            expand_str("!!:z", &history).unwrap_err(),
+            "z: unrecognized history modifier"
+        );
+
+        let mut state = ExpansionState::default();
+        let expansion = expand("!!:p", &history, HistoryChars::default(), false, &mut state);
+        assert!(expansion.unwrap().print_only);
+    }
+
+    #[test]
+    fn quick_substitution() {
+        let history = history_of(&["echo abc"]);
+
+        assert_eq!(expand_str("^b^x", &history).unwrap(), "echo axc");
+        assert_eq!(expand_str("^b^x^ d", &history).unwrap(), "echo axc d");
+        assert!(expand_str("^z^y", &history).is_err());
+    }
+
+    #[test]
+    fn quoting() {
+        let history = history_of(&["echo x"]);
+
+        assert_eq!(expand_str("echo '!!'", &history).unwrap(), "echo '!!'");
+        assert_eq!(
+            expand_str("echo \"!!\"", &history).unwrap(),
+            "echo \"echo x\""
+        );
+        assert_eq!(expand_str(r"echo \!!", &history).unwrap(), r"echo \!!");
+        assert_eq!(
+            expand_str("echo a! b!= $! [!a]", &history).unwrap(),
+            "echo a! b!= $! [!a]"
+        );
+        assert_eq!(expand_str("echo ${!x}", &history).unwrap(), "echo ${!x}");
+        assert_eq!(expand_str("echo # !!", &history).unwrap(), "echo # !!");
+    }
+
+    #[test]
+    fn custom_history_chars() {
+        let history = history_of(&["echo x"]);
+        let chars = HistoryChars::parse("%").unwrap();
+        let mut state = ExpansionState::default();
+
+        let expansion = expand("%% !!", &history, chars, false, &mut state).unwrap();
+        assert_eq!(expansion.line, "echo x !!");
+        assert!(HistoryChars::parse("").is_none());
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            split_words("echo x|cat>/dev/null 2>&1 \"a b\" $(c d)"),
+            vec![
+                "echo",
+                "x",
+                "|",
+                "cat",
+                ">",
+                "/dev/null",
+                "2>&1",
+                "\"a b\"",
+                "$(c d)"
+            ]
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! Command history for the shell.
+
+use std::io::Write;
+use std::path::Path;
+
+/// An entry in the shell's command history.
+#[derive(Clone, Debug)]
+pub struct HistoryItem {
+    /// The command line that was entered.
+    pub command_line: String,
+    /// When the command was entered, in seconds since the Unix epoch, if known.
+    pub timestamp: Option<i64>,
+}
+
+impl HistoryItem {
+    /// Returns a new history item for a command line entered just now.
+    ///
+    /// # Arguments
+    ///
+    /// * `command_line` - The command line that was entered.
+    pub fn new(command_line: String) -> Self {
+        Self {
+            command_line,
+            timestamp: Some(chrono::Utc::now().timestamp()),
+        }
+    }
+}
+
+/// The shell's command history list.
+#[derive(Clone, Debug)]
+pub struct History {
+    /// The items in the list, oldest first.
+    items: Vec<HistoryItem>,
+    /// The history number of the first item in the list.
+    base: usize,
+    /// Index of the first item not yet appended to a history file.
+    first_unsaved: usize,
+    /// Number of lines of the history file that have been read or written so far.
+    lines_in_file: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            items: vec![],
+            base: 1,
+            first_unsaved: 0,
+            lines_in_file: 0,
+        }
+    }
+}
+
+impl History {
+    /// Returns an iterator over the items in the list, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryItem> {
+        self.items.iter()
+    }
+
+    /// Returns the number of items in the list.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the item at the given index in the list, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The 0-based index of the item.
+    pub fn get(&self, index: usize) -> Option<&HistoryItem> {
+        self.items.get(index)
+    }
+
+    /// Returns the most recently added item, if there is one.
+    pub fn last(&self) -> Option<&HistoryItem> {
+        self.items.last()
+    }
+
+    /// Returns the history number of the first item in the list.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Returns the history number that the next item added to the list will have.
+    pub fn next_number(&self) -> usize {
+        self.base + self.items.len()
+    }
+
+    /// Adds an item to the end of the list.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The item to add.
+    pub(crate) fn add(&mut self, item: HistoryItem) {
+        self.items.push(item);
+    }
+
+    /// Removes the item at the given index in the list, returning it.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The 0-based index of the item.
+    pub(crate) fn remove(&mut self, index: usize) -> Option<HistoryItem> {
+        if index >= self.items.len() {
+            return None;
+        }
+
+        if index < self.first_unsaved {
+            self.first_unsaved -= 1;
+        }
+
+        Some(self.items.remove(index))
+    }
+
+    /// Removes the most recently added item, returning it.
+    pub(crate) fn remove_last(&mut self) -> Option<HistoryItem> {
+        if self.items.is_empty() {
+            None
+        } else {
+            self.remove(self.items.len() - 1)
+        }
+    }
+
+    /// Removes all items with the given command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `command_line` - The command line to remove.
+    pub(crate) fn remove_matching(&mut self, command_line: &str) {
+        for index in (0..self.items.len()).rev() {
+            if self.items[index].command_line == command_line {
+                self.remove(index);
+            }
+        }
+    }
+
+    /// Removes all items from the list.
+    pub(crate) fn clear(&mut self) {
+        self.items.clear();
+        self.base = 1;
+        self.first_unsaved = 0;
+    }
+
+    /// Removes the oldest items from the list until it holds no more than the given
+    /// number of items.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_items` - The maximum number of items to retain.
+    pub(crate) fn truncate_to(&mut self, max_items: usize) {
+        if self.items.len() > max_items {
+            let removed = self.items.len() - max_items;
+            self.items.drain(..removed);
+            self.base += removed;
+            self.first_unsaved = self.first_unsaved.saturating_sub(removed);
+        }
+    }
+
+    /// Reads items from a history file, appending them to the list. Lines of the form
+    /// `#<seconds>` provide the timestamp of the item that follows them.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the history file.
+    /// * `only_new_lines` - Whether to skip the lines of the file that have already been
+    ///   read or written.
+    /// * `multiline_items` - Whether lines following a timestamp belong to a single item,
+    ///   up until the next timestamp.
+    pub(crate) fn read_file(
+        &mut self,
+        path: &Path,
+        only_new_lines: bool,
+        multiline_items: bool,
+    ) -> Result<(), std::io::Error> {
+        let contents = std::fs::read(path)?;
+        let contents = String::from_utf8_lossy(&contents);
+        let lines: Vec<_> = contents.lines().collect();
+
+        let lines_to_skip = if only_new_lines {
+            self.lines_in_file.min(lines.len())
+        } else {
+            0
+        };
+
+        let mut timestamp = None;
+        let mut item_open = false;
+        for line in &lines[lines_to_skip..] {
+            if let Some(seconds) = parse_timestamp_line(line) {
+                timestamp = Some(seconds);
+                item_open = false;
+            } else if item_open {
+                if let Some(item) = self.items.last_mut() {
+                    item.command_line.push('\n');
+                    item.command_line.push_str(line);
+                }
+            } else {
+                item_open = multiline_items && timestamp.is_some();
+                self.items.push(HistoryItem {
+                    command_line: (*line).to_owned(),
+                    timestamp: timestamp.take(),
+                });
+            }
+        }
+
+        // Items read from the file needn't be appended to it again.
+        self.first_unsaved = self.items.len();
+        self.lines_in_file = lines.len();
+
+        Ok(())
+    }
+
+    /// Writes items to a history file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the history file.
+    /// * `append` - Whether to append only the items not yet saved to the file, instead of
+    ///   replacing the file's contents with the whole list.
+    /// * `write_timestamps` - Whether to precede each item with a `#<seconds>` timestamp line.
+    pub(crate) fn write_file(
+        &mut self,
+        path: &Path,
+        append: bool,
+        write_timestamps: bool,
+    ) -> Result<(), std::io::Error> {
+        let items = if append {
+            &self.items[self.first_unsaved..]
+        } else {
+            &self.items[..]
+        };
+
+        let mut contents = vec![];
+        for item in items {
+            if write_timestamps {
+                if let Some(seconds) = item.timestamp {
+                    writeln!(contents, "#{seconds}")?;
+                }
+            }
+            writeln!(contents, "{}", item.command_line)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+        file.write_all(&contents)?;
+
+        // N.B. Like bash, we only consider the items saved (and the lines present in the
+        // file) when appending to the file.
+        if append {
+            self.lines_in_file += String::from_utf8_lossy(&contents).lines().count();
+            self.first_unsaved = self.items.len();
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the oldest lines from a history file until it holds no more than the given
+/// number of lines.
+///
+/// # Arguments
+///
+/// * `path` - The path to the history file.
+/// * `max_lines` - The maximum number of lines to retain.
+pub(crate) fn truncate_file(path: &Path, max_lines: usize) -> Result<(), std::io::Error> {
+    let contents = std::fs::read(path)?;
+    let contents = String::from_utf8_lossy(&contents);
+    let lines: Vec<_> = contents.lines().collect();
+
+    if lines.len() > max_lines {
+        let mut retained = lines[lines.len() - max_lines..].join("\n");
+        if !retained.is_empty() {
+            retained.push('\n');
+        }
+        std::fs::write(path, retained)?;
+    }
+
+    Ok(())
+}
+
+/// Joins the lines of a multi-line command into a single line, the way it's stored in
+/// history when `cmdhist` is enabled but `lithist` is not. Lines are separated by a
+/// semicolon, unless one would be a syntax error or the line break is quoted.
+///
+/// # Arguments
+///
+/// * `command_line` - The command line to join.
+pub(crate) fn join_command_lines(command_line: &str) -> String {
+    let mut joined = String::new();
+
+    for line in command_line.lines() {
+        if joined.is_empty() {
+            joined.push_str(line);
+            continue;
+        }
+
+        let in_quote_or_here_doc = matches!(
+            brush_parser::tokenize_str(joined.as_str()),
+            Err(e) if e.is_incomplete()
+        );
+
+        if in_quote_or_here_doc {
+            joined.push('\n');
+        } else if line.trim().is_empty() {
+            continue;
+        } else if needs_no_separator(joined.as_str()) {
+            joined.push(' ');
+        } else {
+            joined.push_str("; ");
+        }
+
+        joined.push_str(line);
+    }
+
+    joined
+}
+
+fn needs_no_separator(preceding: &str) -> bool {
+    let preceding = preceding.trim_end();
+
+    if preceding.ends_with(['|', '&', ';', '(', '{']) {
+        return true;
+    }
+
+    matches!(
+        preceding.split_ascii_whitespace().last(),
+        Some("do" | "then" | "else" | "elif" | "in" | "if" | "while" | "until" | "!")
+    )
+}
+
+fn parse_timestamp_line(line: &str) -> Option<i64> {
+    line.strip_prefix('#')
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|s| s.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_lines() {
+        assert_eq!(
+            join_command_lines("for i in 1 2\ndo\necho $i\ndone\n"),
+            "for i in 1 2; do echo $i; done"
+        );
+        assert_eq!(join_command_lines("f() {\n echo y\n}"), "f() {  echo y; }");
+        assert_eq!(join_command_lines("echo x |\n  cat"), "echo x |   cat");
+        assert_eq!(join_command_lines("echo \"a\nb\""), "echo \"a\nb\"");
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        assert_eq!(parse_timestamp_line("#1700000000"), Some(1_700_000_000));
+        assert_eq!(parse_timestamp_line("#"), None);
+        assert_eq!(parse_timestamp_line("# comment"), None);
+        assert_eq!(parse_timestamp_line("echo"), None);
+    }
+}
@@ -1,7 +1,8 @@
-use brush_parser::ast::{self, CommandPrefixOrSuffixItem};
+use brush_parser::ast::{self, CommandPrefixOrSuffixItem, SourceLocation};
 use itertools::Itertools;
+use std::borrow::Cow;
 use std::collections::VecDeque;
-use std::io::Write;
+use std::io::{Read, Write};
 #[cfg(target_os = "linux")]
 use std::os::fd::{AsFd, AsRawFd};
 #[cfg(unix)]
@@ -11,13 +12,13 @@ use std::sync::Arc;
 
 use crate::arithmetic::{self, ExpandAndEvaluate};
 use crate::commands::{self, CommandArg, CommandSpawnResult};
-use crate::env::{EnvironmentLookup, EnvironmentScope};
+use crate::env::{self, EnvironmentLookup, EnvironmentScope};
 use crate::openfiles::{OpenFile, OpenFiles};
 use crate::shell::Shell;
 use crate::variables::{
     ArrayLiteral, ShellValue, ShellValueLiteral, ShellValueUnsetType, ShellVariable,
 };
-use crate::{error, expansion, extendedtests, jobs, openfiles, processes, sys, timing, traps};
+use crate::{error, expansion, extendedtests, jobs, openfiles, processes, sys, timing};
 
 /// Encapsulates the result of executing a command.
 #[derive(Debug, Default)]
@@ -112,6 +113,8 @@ pub struct ExecutionParameters {
     pub open_files: openfiles::OpenFiles,
     /// Policy for how to manage spawned external processes.
     pub process_group_policy: ProcessGroupPolicy,
+    /// Version of the shell's file descriptor table that `open_files` reflects.
+    pub(crate) open_files_version: u64,
 }
 
 impl ExecutionParameters {
@@ -244,9 +247,15 @@ fn spawn_ao_list_in_task<'a>(
     cloned_shell.options.interactive = false;
 
     let join_handle = tokio::spawn(async move {
-        cloned_ao_list
+        let mut result = cloned_ao_list
             .execute(&mut cloned_shell, &cloned_params)
-            .await
+            .await?;
+
+        cloned_shell.last_exit_status = result.exit_code;
+        cloned_shell.run_exit_trap(&cloned_params).await?;
+        result.exit_code = cloned_shell.last_exit_status;
+
+        Ok(result)
     });
 
     let job = shell.jobs.add_as_current(jobs::Job::new(
@@ -265,9 +274,13 @@ impl Execute for ast::AndOrList {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let mut result = self.first.execute(shell, params).await?;
+        let mut result = if self.additional.is_empty() {
+            self.first.execute(shell, params).await?
+        } else {
+            execute_ignoring_failures(&self.first, shell, params).await?
+        };
 
-        for next_ao in &self.additional {
+        for (i, next_ao) in self.additional.iter().enumerate() {
             // Check for exit/return
             if result.exit_shell || result.return_from_function_or_script {
                 break;
@@ -296,13 +309,32 @@ impl Execute for ast::AndOrList {
                 continue;
             }
 
-            result = pipeline.execute(shell, params).await?;
+            // Only the failure of the last pipeline in the list gets checked.
+            result = if i + 1 == self.additional.len() {
+                pipeline.execute(shell, params).await?
+            } else {
+                execute_ignoring_failures(pipeline, shell, params).await?
+            };
         }
 
         Ok(result)
     }
 }
 
+/// Executes the given command in a context where failures are not checked, i.e., they
+/// neither trigger the `ERR` trap nor cause the shell to exit under `errexit`.
+async fn execute_ignoring_failures<E: Execute + Sync>(
+    command: &E,
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+) -> Result<ExecutionResult, error::Error> {
+    shell.enter_ignore_failures_context();
+    let result = command.execute(shell, params).await;
+    shell.leave_ignore_failures_context();
+
+    result
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::Pipeline {
     async fn execute(
@@ -317,13 +349,26 @@ impl Execute for ast::Pipeline {
             .then(timing::start_timing)
             .transpose()?;
 
+        // Failures of commands within a negated pipeline are never checked.
+        if self.bang {
+            shell.enter_ignore_failures_context();
+        }
+
         // Spawn all the processes required for the pipeline, connecting outputs/inputs with pipes
-        // as needed.
-        let spawn_results = spawn_pipeline_processes(self, shell, params).await?;
+        // as needed; then wait for them. Waiting also has a side effect of updating pipeline
+        // status.
+        let result = match spawn_pipeline_processes(self, shell, params).await {
+            Ok(spawn_results) => {
+                wait_for_pipeline_processes_and_update_status(self, spawn_results, shell).await
+            }
+            Err(e) => Err(e),
+        };
+
+        if self.bang {
+            shell.leave_ignore_failures_context();
+        }
 
-        // Wait for the processes. This also has a side effect of updating pipeline status.
-        let mut result =
-            wait_for_pipeline_processes_and_update_status(self, spawn_results, shell).await?;
+        let mut result = result?;
 
         // Invert the exit code if requested.
         if self.bang {
@@ -333,6 +378,18 @@ impl Execute for ast::Pipeline {
         // Update statuses.
         shell.last_exit_status = result.exit_code;
 
+        // Check for failure, running the ERR trap and honoring errexit as appropriate.
+        if !result.is_success() && pipeline_failure_is_checked(self, shell) {
+            handle_checked_failure(shell, params, &mut result).await?;
+        }
+
+        // Run handlers for any trapped signals received while the pipeline was running.
+        let trap_result = shell.run_pending_traps(params).await?;
+        if trap_result.exit_shell && !result.exit_shell {
+            result.exit_code = trap_result.exit_code;
+            result.exit_shell = true;
+        }
+
         // If requested, report timing.
         if let Some(timed) = &self.timed {
             if let Some(stderr) = params.open_files.stderr() {
@@ -365,6 +422,55 @@ impl Execute for ast::Pipeline {
     }
 }
 
+/// Returns whether the failure of the given pipeline should trigger the `ERR` trap and
+/// `errexit`. Compound commands other than subshells and arithmetic commands are not
+/// checked as a whole, since the commands they contain are checked individually.
+fn pipeline_failure_is_checked(pipeline: &ast::Pipeline, shell: &Shell) -> bool {
+    if pipeline.bang || !shell.failures_are_checked() {
+        return false;
+    }
+
+    !is_control_structure(pipeline)
+}
+
+/// Returns whether the given pipeline consists solely of a compound command that only
+/// groups or controls the execution of other commands (i.e., anything other than a
+/// subshell or arithmetic command). The status of such a pipeline is determined by the
+/// commands it contains.
+fn is_control_structure(pipeline: &ast::Pipeline) -> bool {
+    match pipeline.seq.as_slice() {
+        [ast::Command::Compound(compound, _)] => !matches!(
+            compound,
+            ast::CompoundCommand::Subshell(_) | ast::CompoundCommand::Arithmetic(_)
+        ),
+        _ => false,
+    }
+}
+
+/// Handles the checked failure of a command: runs the `ERR` trap, and then requests that
+/// the shell exit if `errexit` is enabled.
+async fn handle_checked_failure(
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+    result: &mut ExecutionResult,
+) -> Result<(), error::Error> {
+    // Failures that are already on their way out of the shell (or the current function or
+    // script) don't need further handling.
+    if result.exit_shell || result.return_from_function_or_script {
+        return Ok(());
+    }
+
+    let handler_result = shell.run_err_trap(params).await?;
+    if handler_result.exit_shell {
+        result.exit_code = handler_result.exit_code;
+        result.exit_shell = true;
+    } else if shell.options.exit_on_nonzero_command_exit {
+        result.exit_shell = true;
+    }
+
+    Ok(())
+}
+
 async fn spawn_pipeline_processes(
     pipeline: &ast::Pipeline,
     shell: &mut Shell,
@@ -406,11 +512,18 @@ async fn spawn_pipeline_processes(
                 cmd_params.process_group_policy = ProcessGroupPolicy::SameProcessGroup;
             }
 
-            spawn_results.push_back(
-                command
-                    .execute_in_pipeline(&mut pipeline_context, cmd_params)
-                    .await?,
-            );
+            // A request to exit from the subshell doesn't carry over to this shell.
+            let spawn_result = match command
+                .execute_in_pipeline(&mut pipeline_context, cmd_params)
+                .await?
+            {
+                CommandSpawnResult::ExitShell(exit_code) => {
+                    CommandSpawnResult::ImmediateExit(exit_code)
+                }
+                spawn_result => spawn_result,
+            };
+
+            spawn_results.push_back(spawn_result);
             process_group_id = pipeline_context.process_group_id;
         } else {
             let mut pipeline_context = PipelineExecutionContext {
@@ -440,27 +553,38 @@ async fn wait_for_pipeline_processes_and_update_status(
 ) -> Result<ExecutionResult, error::Error> {
     let mut result = ExecutionResult::success();
     let mut stopped_children = vec![];
-
-    // Clear our the pipeline status so we can start filling it out.
-    shell.last_pipeline_statuses.clear();
+    let mut statuses = vec![];
 
     while let Some(child) = process_spawn_results.pop_front() {
         match child.wait(!stopped_children.is_empty()).await? {
             commands::CommandWaitResult::CommandCompleted(current_result) => {
                 result = current_result;
                 shell.last_exit_status = result.exit_code;
-                shell.last_pipeline_statuses.push(result.exit_code);
+                statuses.push(result.exit_code);
             }
             commands::CommandWaitResult::CommandStopped(current_result, child) => {
                 result = current_result;
                 shell.last_exit_status = result.exit_code;
-                shell.last_pipeline_statuses.push(result.exit_code);
+                statuses.push(result.exit_code);
 
                 stopped_children.push(jobs::JobTask::External(child));
             }
         }
     }
 
+    // With pipefail, the pipeline's status is that of the last (rightmost) command to fail.
+    if shell.options.return_first_failure_from_pipeline {
+        if let Some(failure) = statuses.iter().rev().find(|status| **status != 0) {
+            result.exit_code = *failure;
+        }
+    }
+
+    // A control structure (e.g., a loop) leaves behind the statuses of the last pipeline
+    // it executed.
+    if !is_control_structure(pipeline) {
+        shell.last_pipeline_statuses = statuses;
+    }
+
     if shell.options.interactive {
         sys::terminal::move_self_to_foreground()?;
     }
@@ -494,6 +618,17 @@ impl ExecuteInPipeline for ast::Command {
             return Ok(CommandSpawnResult::ImmediateExit(0));
         }
 
+        // Keep track of the line being executed (e.g., for `LINENO`).
+        if let Some(loc) = self.location() {
+            if let Ok(line) = u32::try_from(loc.start.line) {
+                pipeline_context.shell.set_current_input_line_number(line);
+            }
+        }
+
+        // Pick up any files the shell has opened (e.g., for a coprocess) since these
+        // parameters were captured.
+        pipeline_context.shell.add_missing_open_files(&mut params)?;
+
         match self {
             ast::Command::Simple(simple) => {
                 simple.execute_in_pipeline(pipeline_context, params).await
@@ -529,20 +664,176 @@ impl ExecuteInPipeline for ast::Command {
                 Ok(CommandSpawnResult::ImmediateExit(result.exit_code))
             }
             ast::Command::ExtendedTest(e) => {
-                let result =
-                    if extendedtests::eval_extended_test_expr(e, pipeline_context.shell, &params)
-                        .await?
-                    {
-                        0
-                    } else {
-                        1
-                    };
+                let result = if extendedtests::eval_extended_test_expr(
+                    &e.expr,
+                    pipeline_context.shell,
+                    &params,
+                )
+                .await?
+                {
+                    0
+                } else {
+                    1
+                };
                 Ok(CommandSpawnResult::ImmediateExit(result))
             }
+            ast::Command::Coprocess(coproc) => {
+                let result = coproc.execute(pipeline_context.shell, &params).await?;
+                Ok(CommandSpawnResult::ImmediateExit(result.exit_code))
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Execute for ast::CoprocessCommand {
+    async fn execute(
+        &self,
+        shell: &mut Shell,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let name = self.name.as_deref().unwrap_or("COPROC");
+
+        // Set up pipes connecting the shell to the coprocess's standard input and output.
+        let (coproc_stdin, shell_writer) = sys::pipes::pipe()?;
+        let (shell_reader, coproc_stdout) = sys::pipes::pipe()?;
+
+        // Mark the coprocess's shell as not interactive; we don't want it messing with the
+        // terminal.
+        let mut coproc_shell = shell.clone();
+        coproc_shell.options.interactive = false;
+
+        let mut coproc_params = params.clone();
+        coproc_params
+            .open_files
+            .files
+            .insert(0, OpenFile::PipeReader(coproc_stdin));
+        coproc_params
+            .open_files
+            .files
+            .insert(1, OpenFile::PipeWriter(coproc_stdout));
+
+        let task = if coprocess_runs_external_program(shell, &self.body) {
+            // Spawn the program directly, so the job has a real process behind it.
+            let mut output_pipes = vec![];
+            let mut pipeline_context = PipelineExecutionContext {
+                shell: &mut coproc_shell,
+                current_pipeline_index: 0,
+                pipeline_len: 1,
+                output_pipes: &mut output_pipes,
+                process_group_id: None,
+            };
+
+            match self
+                .body
+                .execute_in_pipeline(&mut pipeline_context, coproc_params)
+                .await?
+            {
+                CommandSpawnResult::SpawnedProcess(child) => jobs::JobTask::External(child),
+                spawn_result => {
+                    // The command already ran to completion (e.g., it wasn't found).
+                    let result = match spawn_result.wait(false).await? {
+                        commands::CommandWaitResult::CommandCompleted(result)
+                        | commands::CommandWaitResult::CommandStopped(result, _) => result,
+                    };
+                    jobs::JobTask::Internal(tokio::spawn(async move { Ok(result) }))
+                }
+            }
+        } else {
+            let pipeline = ast::Pipeline {
+                timed: None,
+                bang: false,
+                seq: vec![self.body.as_ref().clone()],
+            };
+
+            // N.B. A coprocess typically spends its life blocked reading from the shell, so
+            // we give it a thread of its own rather than tie up one of the runtime's workers.
+            let runtime = tokio::runtime::Handle::current();
+            jobs::JobTask::Internal(tokio::task::spawn_blocking(move || {
+                runtime.block_on(async move {
+                    let mut result = pipeline.execute(&mut coproc_shell, &coproc_params).await?;
+
+                    coproc_shell.last_exit_status = result.exit_code;
+                    coproc_shell.run_exit_trap(&coproc_params).await?;
+                    result.exit_code = coproc_shell.last_exit_status;
+
+                    Ok(result)
+                })
+            }))
+        };
+
+        let mut job = jobs::Job::new(
+            [task],
+            std::format!("coproc {name} {}", self.body),
+            jobs::JobState::Running,
+        );
+        job.coprocess_name = Some(name.to_owned());
+
+        let pid = shell.jobs.add_as_current(job).get_representative_pid();
+
+        // Hand the shell's ends of the pipes over to the shell, and tell the user where to
+        // find them.
+        let read_fd = shell.add_open_file(OpenFile::PipeReader(shell_reader), params)?;
+        let write_fd = shell.add_open_file(OpenFile::PipeWriter(shell_writer), params)?;
+
+        shell.env.update_or_add(
+            name,
+            ShellValueLiteral::Array(ArrayLiteral(vec![
+                (None, read_fd.to_string()),
+                (None, write_fd.to_string()),
+            ])),
+            |_| Ok(()),
+            EnvironmentLookup::Anywhere,
+            EnvironmentScope::Global,
+        )?;
+
+        // N.B. Commands run in asynchronous tasks within the shell have no process ID of
+        // their own to report.
+        if let Some(pid) = pid {
+            shell.env.update_or_add(
+                std::format!("{name}_PID"),
+                ShellValueLiteral::Scalar(pid.to_string()),
+                |_| Ok(()),
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )?;
         }
+
+        let result = ExecutionResult::success();
+        shell.last_exit_status = result.exit_code;
+        Ok(result)
     }
 }
 
+/// Checks whether the given coprocess command is a simple command that will run an
+/// external program, which can then be spawned directly as the coprocess. Anything
+/// else needs to run in an asynchronous task within the shell.
+fn coprocess_runs_external_program(shell: &Shell, command: &ast::Command) -> bool {
+    let ast::Command::Simple(ast::SimpleCommand {
+        word_or_name: Some(command_name),
+        ..
+    }) = command
+    else {
+        return false;
+    };
+
+    // Only consider names that won't change on expansion.
+    let name = command_name.value.as_str();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '+'))
+    {
+        return false;
+    }
+
+    shell.funcs.get(name).is_none()
+        && !shell
+            .builtins
+            .get(name)
+            .is_some_and(|builtin| !builtin.disabled)
+}
+
 enum WhileOrUntil {
     While,
     Until,
@@ -556,19 +847,24 @@ impl Execute for ast::CompoundCommand {
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
         match self {
-            ast::CompoundCommand::BraceGroup(ast::BraceGroupCommand(g)) => {
-                g.execute(shell, params).await
+            ast::CompoundCommand::BraceGroup(ast::BraceGroupCommand { list, .. }) => {
+                list.execute(shell, params).await
             }
-            ast::CompoundCommand::Subshell(ast::SubshellCommand(s)) => {
+            ast::CompoundCommand::Subshell(ast::SubshellCommand { list: s, .. }) => {
                 // Clone off a new subshell, and run the body of the subshell there.
                 let mut subshell = shell.clone();
                 let subshell_result = s.execute(&mut subshell, params).await?;
 
+                // Give the subshell a chance to run its EXIT trap, if it registered one.
+                subshell.last_exit_status = subshell_result.exit_code;
+                subshell.run_exit_trap(params).await?;
+
                 // Preserve the subshell's exit code, but don't honor any of its requests to exit
                 // the shell, break out of loops, etc.
-                Ok(ExecutionResult::new(subshell_result.exit_code))
+                Ok(ExecutionResult::new(subshell.last_exit_status))
             }
             ast::CompoundCommand::ForClause(f) => f.execute(shell, params).await,
+            ast::CompoundCommand::SelectClause(s) => s.execute(shell, params).await,
             ast::CompoundCommand::CaseClause(c) => c.execute(shell, params).await,
             ast::CompoundCommand::IfClause(i) => i.execute(shell, params).await,
             ast::CompoundCommand::WhileClause(w) => {
@@ -623,16 +919,155 @@ impl Execute for ast::ForClauseCommand {
                 }
             }
 
-            // Update the variable.
+            // Update the variable. If it's a name reference, then it's re-pointed at each
+            // value in turn instead of assigning through it.
+            if let Some(nameref) = shell
+                .env
+                .get_mut_using_policy(&self.variable_name, EnvironmentLookup::Anywhere)
+                .filter(|var| var.is_treated_as_nameref())
+            {
+                nameref.assign(ShellValueLiteral::Scalar(value), false)?;
+            } else {
+                shell.env.update_or_add(
+                    &self.variable_name,
+                    ShellValueLiteral::Scalar(value),
+                    |_| Ok(()),
+                    EnvironmentLookup::Anywhere,
+                    EnvironmentScope::Global,
+                )?;
+            }
+
+            result = self.body.list.execute(shell, params).await?;
+            if result.exit_shell || result.return_from_function_or_script {
+                break;
+            }
+
+            if let Some(continue_count) = &result.continue_loop {
+                if *continue_count == 0 {
+                    result.continue_loop = None;
+                } else {
+                    result.continue_loop = Some(*continue_count - 1);
+                    break;
+                }
+            }
+            if let Some(break_count) = &result.break_loop {
+                if *break_count == 0 {
+                    result.break_loop = None;
+                } else {
+                    result.break_loop = Some(*break_count - 1);
+                }
+                break;
+            }
+        }
+
+        shell.last_exit_status = result.exit_code;
+        Ok(result)
+    }
+}
+
+#[async_trait::async_trait]
+impl Execute for ast::SelectClauseCommand {
+    async fn execute(
+        &self,
+        shell: &mut Shell,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let mut result = ExecutionResult::success();
+
+        // As with for loops, expand the explicitly given words (with splitting enabled) or
+        // fall back to the current positional parameters.
+        let mut expanded_values = vec![];
+        if let Some(unexpanded_values) = &self.values {
+            for value in unexpanded_values {
+                let mut expanded =
+                    expansion::full_expand_and_split_word(shell, params, value).await?;
+                expanded_values.append(&mut expanded);
+            }
+        } else {
+            expanded_values.extend_from_slice(&shell.positional_parameters);
+        }
+
+        // With nothing to choose from, there's nothing to do.
+        if expanded_values.is_empty() {
+            shell.last_exit_status = result.exit_code;
+            return Ok(result);
+        }
+
+        if shell.options.print_commands_and_arguments {
+            if let Some(unexpanded_values) = &self.values {
+                shell
+                    .trace_command(std::format!(
+                        "select {} in {}",
+                        self.variable_name,
+                        unexpanded_values.iter().join(" ")
+                    ))
+                    .await?;
+            } else {
+                shell
+                    .trace_command(std::format!("select {}", self.variable_name))
+                    .await?;
+            }
+        }
+
+        let mut show_menu = true;
+        loop {
+            if show_menu {
+                let columns = shell
+                    .get_env_str("COLUMNS")
+                    .and_then(|c| c.parse::<usize>().ok())
+                    .filter(|c| *c > 0)
+                    .unwrap_or(80);
+
+                let mut stderr = params.stderr();
+                write!(stderr, "{}", format_select_menu(&expanded_values, columns))?;
+            }
+
+            let prompt = shell.get_env_str("PS3").unwrap_or("#? ".into()).to_string();
+            let mut stderr = params.stderr();
+            write!(stderr, "{prompt}")?;
+            stderr.flush()?;
+
+            // On end of input, terminate the menu's line and fail out of the loop.
+            let Some(reply) = read_select_reply(params.stdin())? else {
+                writeln!(params.stdout())?;
+                result = ExecutionResult::new(1);
+                break;
+            };
+
+            shell.env.update_or_add(
+                "REPLY",
+                ShellValueLiteral::Scalar(reply.clone()),
+                |_| Ok(()),
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )?;
+
+            // An empty reply just redisplays the menu.
+            if reply.is_empty() {
+                show_menu = true;
+                continue;
+            }
+            show_menu = false;
+
+            // Anything other than the number of a menu item selects nothing.
+            let selection = reply
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| expanded_values.get(i))
+                .cloned()
+                .unwrap_or_default();
+
             shell.env.update_or_add(
                 &self.variable_name,
-                ShellValueLiteral::Scalar(value),
+                ShellValueLiteral::Scalar(selection),
                 |_| Ok(()),
                 EnvironmentLookup::Anywhere,
                 EnvironmentScope::Global,
             )?;
 
-            result = self.body.0.execute(shell, params).await?;
+            result = self.body.list.execute(shell, params).await?;
             if result.exit_shell || result.return_from_function_or_script {
                 break;
             }
@@ -660,6 +1095,99 @@ impl Execute for ast::ForClauseCommand {
     }
 }
 
+/// Formats the numbered menu displayed by a `select` loop, laying the items out in
+/// column-major order across as many columns as fit in the given terminal width.
+fn format_select_menu(values: &[String], columns: usize) -> String {
+    const TAB_WIDTH: usize = 8;
+
+    let number_width = |n: usize| n.to_string().len();
+    let indent = |output: &mut String, mut from: usize, to: usize| {
+        while from < to {
+            if to / TAB_WIDTH > from / TAB_WIDTH {
+                output.push('\t');
+                from += TAB_WIDTH - from % TAB_WIDTH;
+            } else {
+                output.push(' ');
+                from += 1;
+            }
+        }
+    };
+
+    // Each entry is "N) value"; leave a couple of spaces of separation between columns.
+    let index_width = number_width(values.len());
+    let max_entry_len =
+        values.iter().map(|v| v.chars().count()).max().unwrap_or(0) + index_width + 4;
+
+    let column_count = std::cmp::max(columns / max_entry_len, 1);
+    let mut row_count = values.len().div_ceil(column_count);
+
+    // A menu that would fit on a single line is instead displayed one item per line.
+    if row_count == 1 {
+        row_count = values.len();
+    }
+
+    let first_index_width = number_width(row_count);
+
+    let mut output = String::new();
+    for row in 0..row_count {
+        let mut index = row;
+        let mut pos = 0;
+        loop {
+            let width = if pos == 0 {
+                first_index_width
+            } else {
+                index_width
+            };
+
+            let value = &values[index];
+            output.push_str(std::format!("{:>width$}) {value}", index + 1).as_str());
+
+            let entry_len = value.chars().count() + width + 2;
+            index += row_count;
+            if index >= values.len() {
+                break;
+            }
+
+            indent(&mut output, pos + entry_len, pos + max_entry_len);
+            pos += max_entry_len;
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Reads a reply to a `select` prompt from the given file. Backslashes escape the
+/// character that follows them, allowing a line to be continued. Returns `None` at
+/// end of input.
+fn read_select_reply(mut input_file: OpenFile) -> Result<Option<String>, error::Error> {
+    let mut reply = vec![];
+    let mut escaped = false;
+    let mut buffer = [0; 1];
+
+    loop {
+        let n = input_file.read(&mut buffer)?;
+        if n == 0 {
+            if reply.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+
+        match buffer[0] {
+            b'\n' if escaped => escaped = false,
+            b'\n' => break,
+            b'\\' if !escaped => escaped = true,
+            byte => {
+                escaped = false;
+                reply.push(byte);
+            }
+        }
+    }
+
+    Ok(Some(String::from_utf8_lossy(&reply).into_owned()))
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::CaseClauseCommand {
     async fn execute(
@@ -707,6 +1235,10 @@ impl Execute for ast::CaseClauseCommand {
                 ExecutionResult::success()
             };
 
+            if result.exit_shell || result.return_from_function_or_script {
+                break;
+            }
+
             match case.post_action {
                 ast::CaseItemPostAction::ExitCase => break,
                 ast::CaseItemPostAction::UnconditionallyExecuteNextCaseItem => {
@@ -729,7 +1261,7 @@ impl Execute for ast::IfClauseCommand {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let condition = self.condition.execute(shell, params).await?;
+        let condition = execute_ignoring_failures(&self.condition, shell, params).await?;
 
         if condition.is_success() {
             return self.then.execute(shell, params).await;
@@ -739,7 +1271,8 @@ impl Execute for ast::IfClauseCommand {
             for else_clause in elses {
                 match &else_clause.condition {
                     Some(else_condition) => {
-                        let else_condition_result = else_condition.execute(shell, params).await?;
+                        let else_condition_result =
+                            execute_ignoring_failures(else_condition, shell, params).await?;
                         if else_condition_result.is_success() {
                             return else_clause.body.execute(shell, params).await;
                         }
@@ -769,13 +1302,13 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
             WhileOrUntil::While => true,
             WhileOrUntil::Until => false,
         };
-        let test_condition = &self.1 .0;
-        let body = &self.1 .1;
+        let test_condition = &self.1.condition;
+        let body = &self.1.body;
 
         let mut result = ExecutionResult::success();
 
         loop {
-            let condition_result = test_condition.execute(shell, params).await?;
+            let condition_result = execute_ignoring_failures(test_condition, shell, params).await?;
 
             if condition_result.exit_shell || condition_result.return_from_function_or_script {
                 result.exit_code = condition_result.exit_code;
@@ -789,7 +1322,7 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
                 break;
             }
 
-            result = body.0.execute(shell, params).await?;
+            result = body.list.execute(shell, params).await?;
             if result.exit_shell || result.return_from_function_or_script {
                 break;
             }
@@ -855,7 +1388,7 @@ impl Execute for ast::ArithmeticForClauseCommand {
                 }
             }
 
-            result = self.body.0.execute(shell, params).await?;
+            result = self.body.list.execute(shell, params).await?;
             if result.exit_shell || result.return_from_function_or_script {
                 break;
             }
@@ -928,6 +1461,9 @@ impl ExecuteInPipeline for ast::SimpleCommand {
         let mut args: Vec<CommandArg> = vec![];
         let mut invoking_declaration_builtin = false;
 
+        // Reflect the command in BASH_COMMAND before expanding any of its words.
+        context.shell.set_current_command(self);
+
         // Set up pipelining.
         setup_pipeline_redirection(&mut params.open_files, context)?;
 
@@ -996,35 +1532,18 @@ impl ExecuteInPipeline for ast::SimpleCommand {
                     }
                 }
                 CommandPrefixOrSuffixItem::Word(arg) => {
-                    let mut next_args =
+                    let next_args =
                         expansion::full_expand_and_split_word(context.shell, &params, arg).await?;
 
                     if args.is_empty() {
                         if let Some(cmd_name) = next_args.first() {
-                            if let Some(alias_value) = context.shell.aliases.get(cmd_name.as_str())
-                            {
-                                //
-                                // TODO: This is a total hack; aliases are supposed to be handled
-                                // much earlier in the process.
-                                //
-                                let mut alias_pieces: Vec<_> = alias_value
-                                    .split_ascii_whitespace()
-                                    .map(|i| i.to_owned())
-                                    .collect();
-
-                                next_args.remove(0);
-                                alias_pieces.append(&mut next_args);
-
-                                next_args = alias_pieces;
-                            }
-
                             // Check if we're going to be invoking a special declaration builtin.
                             // That will change how we parse and process
                             // args.
                             if context
                                 .shell
                                 .builtins
-                                .get(next_args[0].as_str())
+                                .get(cmd_name.as_str())
                                 .is_some_and(|r| r.declaration_builtin)
                             {
                                 invoking_declaration_builtin = true;
@@ -1064,45 +1583,8 @@ impl ExecuteInPipeline for ast::SimpleCommand {
                     .await?;
             }
 
-            // TODO: This is adding more complexity here; should be factored out into an appropriate
-            // helper.
-            if context.shell.traps.handler_depth == 0 {
-                let debug_trap_handler = context
-                    .shell
-                    .traps
-                    .handlers
-                    .get(&traps::TrapSignal::Debug)
-                    .cloned();
-                if let Some(debug_trap_handler) = debug_trap_handler {
-                    // TODO: Confirm whether trap handlers should be executed in the same process
-                    // group.
-                    let handler_params = ExecutionParameters {
-                        open_files: params.open_files.clone(),
-                        process_group_policy: ProcessGroupPolicy::SameProcessGroup,
-                    };
-
-                    let full_cmd = args.iter().map(|arg| arg.to_string()).join(" ");
-
-                    // TODO: This shouldn't *just* be set in a trap situation.
-                    context.shell.env.update_or_add(
-                        "BASH_COMMAND",
-                        ShellValueLiteral::Scalar(full_cmd),
-                        |_| Ok(()),
-                        EnvironmentLookup::Anywhere,
-                        EnvironmentScope::Global,
-                    )?;
-
-                    context.shell.traps.handler_depth += 1;
-
-                    // TODO: Discard result?
-                    let _ = context
-                        .shell
-                        .run_string(debug_trap_handler, &handler_params)
-                        .await?;
-
-                    context.shell.traps.handler_depth -= 1;
-                }
-            }
+            // Give the DEBUG trap a chance to run before the command does.
+            context.shell.run_debug_trap(&params).await?;
 
             let cmd_context = commands::ExecutionContext {
                 shell: context.shell,
@@ -1126,6 +1608,9 @@ impl ExecuteInPipeline for ast::SimpleCommand {
 
             execution_result
         } else {
+            // Assignment-only commands are still commands as far as the DEBUG trap is concerned.
+            context.shell.run_debug_trap(&params).await?;
+
             // Reset last status.
             context.shell.last_exit_status = 0;
 
@@ -1195,6 +1680,7 @@ async fn expand_assignment_value(
             let expanded_word = expansion::basic_expand_word(shell, params, s).await?;
             ast::AssignmentValue::Scalar(ast::Word {
                 value: expanded_word,
+                loc: s.loc.clone(),
             })
         }
         ast::AssignmentValue::Array(arr) => {
@@ -1246,6 +1732,35 @@ async fn apply_assignment(
         }
     };
 
+    // Follow any name reference to the variable (or array element) it refers to; the
+    // referenced variable needn't be in the scope the assignment would otherwise target.
+    let mut required_scope = required_scope;
+    let mut creation_scope = creation_scope;
+    let mut circular_nameref = false;
+    let variable_name = match shell.env.resolve_nameref(variable_name) {
+        Ok(Cow::Borrowed(name)) => name.to_owned(),
+        // As in bash, assigning through a circular chain of name references warns and then
+        // updates the global variable of the same name instead.
+        Err(e @ error::Error::CircularNameReference(_)) => {
+            writeln!(params.stderr(), "warning: {e}")?;
+            circular_nameref = true;
+            creation_scope = EnvironmentScope::Global;
+            variable_name.to_owned()
+        }
+        Err(e) => return Err(e),
+        Ok(Cow::Owned(resolved)) => {
+            required_scope = None;
+            creation_scope = EnvironmentScope::Global;
+
+            let (resolved_name, resolved_index) = env::split_subscript(resolved.as_str());
+            if let Some(resolved_index) = resolved_index {
+                array_index.get_or_insert_with(|| resolved_index.to_owned());
+            }
+
+            resolved_name.to_owned()
+        }
+    };
+
     // Expand the values.
     let new_value = match &assignment.value {
         ast::AssignmentValue::Scalar(unexpanded_value) => {
@@ -1288,7 +1803,7 @@ async fn apply_assignment(
 
     // See if we need to eval an array index.
     if let Some(idx) = &array_index {
-        let will_be_indexed_array = if let Some((_, existing_value)) = shell.env.get(variable_name)
+        let will_be_indexed_array = if let Some((_, existing_value)) = shell.env.get(&variable_name)
         {
             matches!(
                 existing_value.value(),
@@ -1308,8 +1823,15 @@ async fn apply_assignment(
     }
 
     // See if we can find an existing value associated with the variable.
-    if let Some((existing_value_scope, existing_value)) = shell.env.get_mut(variable_name.as_str())
-    {
+    let existing = if circular_nameref {
+        shell
+            .env
+            .get_mut_using_policy(variable_name.as_str(), EnvironmentLookup::OnlyInGlobal)
+            .map(|var| (EnvironmentScope::Global, var))
+    } else {
+        shell.env.get_mut(variable_name.as_str())
+    };
+    if let Some((existing_value_scope, existing_value)) = existing {
         if required_scope.is_none() || Some(existing_value_scope) == required_scope {
             if let Some(array_index) = array_index {
                 match new_value {
@@ -1449,6 +1971,28 @@ pub(crate) async fn setup_redirect(
                         return Err(error::Error::InvalidRedirection);
                     }
 
+                    // The target of a duplication may only turn out to be a file descriptor
+                    // once expanded (e.g., `>&${fd}`).
+                    if matches!(
+                        kind,
+                        ast::IoFileRedirectKind::DuplicateInput
+                            | ast::IoFileRedirectKind::DuplicateOutput
+                    ) {
+                        if let Ok(target_fd) = expanded_fields[0].parse::<u32>() {
+                            let fd_num = specified_fd_num
+                                .unwrap_or_else(|| get_default_fd_for_redirect_kind(kind));
+
+                            let Some(f) = params.open_files.files.get(&target_fd) else {
+                                tracing::error!("{}: Bad file descriptor", target_fd);
+                                return Ok(None);
+                            };
+
+                            let target_file = f.try_dup()?;
+                            params.open_files.files.insert(fd_num, target_file);
+                            return Ok(Some(fd_num));
+                        }
+                    }
+
                     let expanded_file_path: PathBuf =
                         shell.get_absolute_path(Path::new(expanded_fields.remove(0).as_str()));
 
@@ -1641,7 +2185,14 @@ fn setup_process_substitution(
     let subshell_cmd = subshell_cmd.to_owned();
     tokio::spawn(async move {
         // Intentionally ignore the result of the subshell command.
-        let _ = subshell_cmd.0.execute(&mut subshell, &child_params).await;
+        if let Ok(result) = subshell_cmd
+            .list
+            .execute(&mut subshell, &child_params)
+            .await
+        {
+            subshell.last_exit_status = result.exit_code;
+            let _ = subshell.run_exit_trap(&child_params).await;
+        }
     });
 
     // Starting at 63 (a.k.a. 64-1)--and decrementing--look for an
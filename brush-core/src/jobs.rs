@@ -18,8 +18,17 @@ pub(crate) type JobResult = (Job, Result<ExecutionResult, error::Error>);
 pub struct JobManager {
     /// The jobs that are currently managed by the shell.
     pub jobs: Vec<Job>,
+
+    /// Exit statuses of completed jobs, paired with the process ID of the job and oldest
+    /// first; these remain available to be waited for after the jobs themselves are no
+    /// longer managed, until there are too many of them or `wait` is run without arguments.
+    completed_statuses: VecDeque<(sys::process::ProcessId, u8)>,
 }
 
+/// The maximum number of completed job statuses remembered, analogous to bash's
+/// `CHILD_MAX`-based limit.
+const MAX_COMPLETED_STATUSES: usize = 1024;
+
 /// Represents a task that is part of a job.
 pub enum JobTask {
     /// An external process.
@@ -81,10 +90,10 @@ impl JobManager {
     /// * `job` - The job to add.
     pub fn add_as_current(&mut self, mut job: Job) -> &Job {
         for j in &mut self.jobs {
-            if matches!(j.annotation, JobAnnotation::Current) {
-                j.annotation = JobAnnotation::Previous;
-                break;
-            }
+            j.annotation = match j.annotation {
+                JobAnnotation::Current => JobAnnotation::Previous,
+                JobAnnotation::Previous | JobAnnotation::None => JobAnnotation::None,
+            };
         }
 
         let id = self.jobs.len() + 1;
@@ -146,22 +155,197 @@ impl JobManager {
         }
     }
 
+    /// Tries to find the job whose representative process has the given process ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `pid` - The process ID to look for.
+    pub fn resolve_pid(&mut self, pid: sys::process::ProcessId) -> Option<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|j| j.get_representative_pid() == Some(pid))
+    }
+
+    /// Returns the exit status of the completed job whose representative process had the
+    /// given process ID, if the job is no longer managed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pid` - The process ID to look for.
+    pub fn get_completed_status(&self, pid: sys::process::ProcessId) -> Option<u8> {
+        self.completed_statuses
+            .iter()
+            .find(|(completed_pid, _)| *completed_pid == pid)
+            .map(|(_, status)| *status)
+    }
+
+    /// Waits for the job whose representative process has the given process ID to
+    /// complete; returns `None` if there is no such job. Completed jobs remain managed
+    /// until swept.
+    ///
+    /// # Arguments
+    ///
+    /// * `pid` - The process ID of the job to wait for.
+    pub async fn wait_for_pid(
+        &mut self,
+        pid: sys::process::ProcessId,
+    ) -> Result<Option<ExecutionResult>, error::Error> {
+        let Some(job) = self.resolve_pid(pid) else {
+            return Ok(self.get_completed_status(pid).map(ExecutionResult::new));
+        };
+
+        let result = job.wait().await?;
+        self.record_completion(Some(pid), &result);
+
+        Ok(Some(result))
+    }
+
+    /// Waits for the job with the given ID to complete; returns `None` if there is no such
+    /// job. Completed jobs remain managed until swept.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the job to wait for.
+    /// * `until_terminated` - Whether to keep waiting if the job is stopped.
+    pub async fn wait_for_job(
+        &mut self,
+        id: usize,
+        until_terminated: bool,
+    ) -> Result<Option<ExecutionResult>, error::Error> {
+        let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) else {
+            return Ok(None);
+        };
+
+        let pid = job.get_representative_pid();
+        let mut result = job.wait().await?;
+        while until_terminated && matches!(job.state, JobState::Stopped) {
+            result = job.wait().await?;
+        }
+
+        self.record_completion(pid, &result);
+
+        Ok(Some(result))
+    }
+
+    /// Waits for the first of the jobs with the given IDs to complete, returning that job's
+    /// ID and result; returns `None` if there are no such jobs. Completed jobs remain
+    /// managed until swept.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The IDs of the jobs to wait for.
+    /// * `until_terminated` - Whether to keep waiting for a job if it's stopped.
+    pub async fn wait_for_any_job(
+        &mut self,
+        ids: &[usize],
+        until_terminated: bool,
+    ) -> Result<Option<(usize, ExecutionResult)>, error::Error> {
+        let waits = self
+            .jobs
+            .iter_mut()
+            .filter(|job| ids.contains(&job.id))
+            .map(|job| {
+                Box::pin(async move {
+                    let pid = job.get_representative_pid();
+                    let mut result = job.wait().await;
+                    while until_terminated && matches!(job.state, JobState::Stopped) {
+                        result = job.wait().await;
+                    }
+                    (job.id, pid, result)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if waits.is_empty() {
+            return Ok(None);
+        }
+
+        let ((id, pid, result), _, _) = futures::future::select_all(waits).await;
+        let result = result?;
+
+        self.record_completion(pid, &result);
+
+        Ok(Some((id, result)))
+    }
+
     /// Waits for all managed jobs to complete.
     pub async fn wait_all(&mut self) -> Result<Vec<Job>, error::Error> {
         for job in &mut self.jobs {
             job.wait().await?;
         }
 
+        // As in bash, waiting for all jobs forgets the statuses of those already completed.
+        self.completed_statuses.clear();
+
         Ok(self.sweep_completed_jobs())
     }
 
+    /// Stops managing the job with the given ID, returning it; if it was the current or
+    /// previous job, the most recently started of the remaining jobs take its place.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the job to remove.
+    pub fn remove_job(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|j| j.id == id)?;
+        let job = self.jobs.remove(index);
+
+        if self.current_job().is_none() {
+            if let Some(prev_job) = self.prev_job_mut() {
+                prev_job.annotation = JobAnnotation::Current;
+            } else if let Some(last_job) = self.jobs.last_mut() {
+                last_job.annotation = JobAnnotation::Current;
+            }
+        }
+
+        if self.prev_job().is_none() {
+            if let Some(job) = self.jobs.iter_mut().rev().find(|j| !j.is_current()) {
+                job.annotation = JobAnnotation::Previous;
+            }
+        }
+
+        Some(job)
+    }
+
+    /// Sends `SIGHUP` to all managed jobs, other than those exempted from it (e.g., via
+    /// `disown -h`). Expected to be invoked as the shell exits after losing its terminal.
+    pub fn hangup_all(&mut self) {
+        for job in &mut self.jobs {
+            if !job.nohup {
+                // N.B. Jobs run within the shell's own process have no process to signal;
+                // they end along with the shell.
+                let _ = job.hangup();
+            }
+        }
+    }
+
+    fn record_completion(
+        &mut self,
+        pid: Option<sys::process::ProcessId>,
+        result: &ExecutionResult,
+    ) {
+        if let Some(pid) = pid {
+            self.completed_statuses
+                .retain(|(completed_pid, _)| *completed_pid != pid);
+            if self.completed_statuses.len() >= MAX_COMPLETED_STATUSES {
+                self.completed_statuses.pop_front();
+            }
+            self.completed_statuses.push_back((pid, result.exit_code));
+        }
+    }
+
     /// Polls all managed jobs for completion.
     pub fn poll(&mut self) -> Result<Vec<JobResult>, error::Error> {
         let mut results = vec![];
 
         let mut i = 0;
         while i != self.jobs.len() {
+            let pid = self.jobs[i].get_representative_pid();
             if let Some(result) = self.jobs[i].poll_done()? {
+                if let Ok(result) = &result {
+                    self.record_completion(pid, result);
+                }
+
                 let job = self.jobs.remove(i);
                 results.push((job, result));
             } else if matches!(self.jobs[i].state, JobState::Done) {
@@ -176,7 +360,8 @@ impl JobManager {
         Ok(results)
     }
 
-    fn sweep_completed_jobs(&mut self) -> Vec<Job> {
+    /// Removes all jobs that have been waited on to completion, returning them.
+    pub(crate) fn sweep_completed_jobs(&mut self) -> Vec<Job> {
         let mut completed_jobs = vec![];
 
         let mut i = 0;
@@ -256,6 +441,12 @@ pub struct Job {
 
     /// The current operational state of the job.
     pub state: JobState,
+
+    /// If the job is a coprocess, its name.
+    pub(crate) coprocess_name: Option<String>,
+
+    /// Whether the job is exempt from the `SIGHUP` sent to jobs when the shell exits.
+    pub(crate) nohup: bool,
 }
 
 impl Display for Job {
@@ -290,6 +481,8 @@ impl Job {
             annotation: JobAnnotation::None,
             command_line,
             state,
+            coprocess_name: None,
+            nohup: false,
         }
     }
 
@@ -380,7 +573,7 @@ impl Job {
     pub fn move_to_background(&mut self) -> Result<(), error::Error> {
         if matches!(self.state, JobState::Stopped) {
             if let Some(pgid) = self.get_process_group_id() {
-                sys::signal::continue_process(pgid)?;
+                sys::signal::continue_process_group(pgid)?;
                 self.state = JobState::Running;
                 Ok(())
             } else {
@@ -395,7 +588,7 @@ impl Job {
     pub fn move_to_foreground(&mut self) -> Result<(), error::Error> {
         if matches!(self.state, JobState::Stopped) {
             if let Some(pgid) = self.get_process_group_id() {
-                sys::signal::continue_process(pgid)?;
+                sys::signal::continue_process_group(pgid)?;
                 self.state = JobState::Running;
             } else {
                 return Err(error::Error::FailedToSendSignal);
@@ -418,6 +611,23 @@ impl Job {
         }
     }
 
+    /// Sends `SIGHUP` to the job, continuing it if it's stopped so that it can act on it.
+    pub fn hangup(&mut self) -> Result<(), error::Error> {
+        let Some(pgid) = self.get_process_group_id() else {
+            return Err(error::Error::FailedToSendSignal);
+        };
+
+        // Signal the whole process group, so every process in a pipeline hears it.
+        sys::signal::hangup_process_group(pgid)?;
+
+        if matches!(self.state, JobState::Stopped) {
+            sys::signal::continue_process_group(pgid)?;
+            self.state = JobState::Running;
+        }
+
+        Ok(())
+    }
+
     /// Tries to retrieve a "representative" pid for the job.
     pub fn get_representative_pid(&self) -> Option<sys::process::ProcessId> {
         for task in &self.tasks {
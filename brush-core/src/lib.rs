@@ -8,12 +8,15 @@ pub mod completion;
 mod arithmetic;
 pub mod builtins;
 mod commands;
+mod editor;
 mod env;
 mod error;
 mod escape;
 mod expansion;
 mod extendedtests;
 mod functions;
+mod histexpansion;
+mod history;
 mod interp;
 mod jobs;
 mod keywords;
@@ -24,6 +27,7 @@ mod pathcache;
 mod patterns;
 mod processes;
 mod prompt;
+pub mod readline;
 mod regex;
 mod shell;
 mod sys;
@@ -35,7 +39,10 @@ mod traps;
 mod variables;
 
 pub use commands::ExecutionContext;
+pub use editor::{EditBuffer, LineEditor};
 pub use error::Error;
+pub use histexpansion::HistoryExpansion;
+pub use history::{History, HistoryItem};
 pub use interp::{ExecutionParameters, ExecutionResult};
 pub use shell::{CreateOptions, Shell};
 pub use terminal::TerminalControl;
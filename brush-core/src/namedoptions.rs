@@ -188,7 +188,13 @@ lazy_static! {
             "emacs",
             OptionDefinition::new(
                 |options| options.emacs_mode,
-                |options, value| options.emacs_mode = value
+                |options, value| {
+                    // N.B. Only one editing mode may be selected.
+                    options.emacs_mode = value;
+                    if value {
+                        options.vi_mode = false;
+                    }
+                }
             )
         ),
         (
@@ -345,7 +351,13 @@ lazy_static! {
             "vi",
             OptionDefinition::new(
                 |options| options.vi_mode,
-                |options, value| options.vi_mode = value
+                |options, value| {
+                    // N.B. Only one editing mode may be selected.
+                    options.vi_mode = value;
+                    if value {
+                        options.emacs_mode = false;
+                    }
+                }
             )
         ),
         (
@@ -134,6 +134,75 @@ impl OpenFile {
         }
         Ok(())
     }
+
+    /// Reads from the open file into the given buffer, waiting no longer than the given
+    /// duration for input to become available. Returns `None` if the wait timed out.
+    pub(crate) fn read_with_timeout(
+        &mut self,
+        buf: &mut [u8],
+        timeout: std::time::Duration,
+    ) -> Result<Option<usize>, error::Error> {
+        // First try reading without blocking; this also picks up any input that was
+        // already buffered in-process, which polling the file wouldn't see.
+        let was_nonblocking = self.set_nonblocking(true)?;
+        let result = std::io::Read::read(self, buf);
+        self.set_nonblocking(was_nonblocking)?;
+
+        match result {
+            Ok(n) => return Ok(Some(n)),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => (),
+            Err(e) => return Err(e.into()),
+        }
+
+        if self.poll_for_input(timeout)? {
+            Ok(Some(std::io::Read::read(self, buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Checks whether input can be read from the open file without blocking; reaching the
+    /// end of input counts as input being available.
+    pub(crate) fn input_available(&self) -> Result<bool, error::Error> {
+        match self {
+            // N.B. Input may already be buffered in-process, where polling wouldn't see it.
+            OpenFile::Stdin => {
+                let was_nonblocking = sys::terminal::set_nonblocking(std::io::stdin(), true)?;
+                let result = std::io::BufRead::fill_buf(&mut std::io::stdin().lock()).map(|_| ());
+                sys::terminal::set_nonblocking(std::io::stdin(), was_nonblocking)?;
+
+                match result {
+                    Ok(()) => Ok(true),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(false),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            _ => self.poll_for_input(std::time::Duration::ZERO),
+        }
+    }
+
+    fn set_nonblocking(&self, value: bool) -> Result<bool, error::Error> {
+        match self {
+            OpenFile::Stdin => sys::terminal::set_nonblocking(std::io::stdin(), value),
+            OpenFile::File(f) => sys::terminal::set_nonblocking(f, value),
+            OpenFile::PipeReader(r) => sys::terminal::set_nonblocking(r, value),
+            OpenFile::Stdout | OpenFile::Stderr | OpenFile::Null | OpenFile::PipeWriter(_) => {
+                Ok(false)
+            }
+        }
+    }
+
+    fn poll_for_input(&self, timeout: std::time::Duration) -> Result<bool, error::Error> {
+        match self {
+            OpenFile::Stdin => sys::terminal::poll_for_input(std::io::stdin(), timeout),
+            OpenFile::File(f) => sys::terminal::poll_for_input(f, timeout),
+            OpenFile::PipeReader(r) => sys::terminal::poll_for_input(r, timeout),
+            // N.B. Reading from any of these won't block.
+            OpenFile::Stdout | OpenFile::Stderr | OpenFile::Null | OpenFile::PipeWriter(_) => {
+                Ok(true)
+            }
+        }
+    }
 }
 
 impl From<std::fs::File> for OpenFile {
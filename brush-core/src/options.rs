@@ -196,6 +196,7 @@ impl RuntimeOptions {
             disallow_overwriting_regular_files_via_output_redirection: create_options
                 .disallow_overwriting_regular_files_via_output_redirection,
             do_not_execute_commands: create_options.do_not_execute_commands,
+            login_shell: create_options.login,
             enable_command_history: create_options.interactive,
             enable_job_control: create_options.interactive,
             exit_after_one_command: create_options.exit_after_one_command,
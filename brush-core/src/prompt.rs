@@ -45,7 +45,7 @@ fn format_prompt_piece(
             return error::unimp("prompt: current command number")
         }
         brush_parser::prompt::PromptPiece::CurrentHistoryNumber => {
-            return error::unimp("prompt: current history number")
+            shell.history().next_number().to_string()
         }
         brush_parser::prompt::PromptPiece::CurrentUser => users::get_current_username()?,
         brush_parser::prompt::PromptPiece::CurrentWorkingDirectory {
@@ -0,0 +1,1769 @@
+//! Configuration of interactive line editing, modeled after the GNU readline library:
+//! key bindings, editing variables, and the init files (a.k.a. inputrc files) that
+//! configure them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Version of readline whose behavior is emulated; used to evaluate `$if version`
+/// conditionals in init files.
+const READLINE_VERSION: (u32, u32) = (8, 2);
+
+/// Application names matched by `$if` conditionals in init files.
+const APPLICATION_NAMES: &[&str] = &["bash", "brush"];
+
+/// Maximum depth of nested `$include` directives.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Names of all known readline functions.
+pub const FUNCTION_NAMES: &[&str] = &[
+    "abort",
+    "accept-line",
+    "alias-expand-line",
+    "arrow-key-prefix",
+    "backward-byte",
+    "backward-char",
+    "backward-delete-char",
+    "backward-kill-line",
+    "backward-kill-word",
+    "backward-word",
+    "beginning-of-history",
+    "beginning-of-line",
+    "bracketed-paste-begin",
+    "call-last-kbd-macro",
+    "capitalize-word",
+    "character-search",
+    "character-search-backward",
+    "clear-display",
+    "clear-screen",
+    "complete",
+    "complete-command",
+    "complete-filename",
+    "complete-hostname",
+    "complete-into-braces",
+    "complete-username",
+    "complete-variable",
+    "copy-backward-word",
+    "copy-forward-word",
+    "copy-region-as-kill",
+    "dabbrev-expand",
+    "delete-char",
+    "delete-char-or-list",
+    "delete-horizontal-space",
+    "digit-argument",
+    "display-shell-version",
+    "do-lowercase-version",
+    "downcase-word",
+    "dump-functions",
+    "dump-macros",
+    "dump-variables",
+    "dynamic-complete-history",
+    "edit-and-execute-command",
+    "emacs-editing-mode",
+    "end-kbd-macro",
+    "end-of-history",
+    "end-of-line",
+    "exchange-point-and-mark",
+    "fetch-history",
+    "forward-backward-delete-char",
+    "forward-byte",
+    "forward-char",
+    "forward-search-history",
+    "forward-word",
+    "glob-complete-word",
+    "glob-expand-word",
+    "glob-list-expansions",
+    "history-and-alias-expand-line",
+    "history-expand-line",
+    "history-search-backward",
+    "history-search-forward",
+    "history-substring-search-backward",
+    "history-substring-search-forward",
+    "insert-comment",
+    "insert-completions",
+    "insert-last-argument",
+    "kill-line",
+    "kill-region",
+    "kill-whole-line",
+    "kill-word",
+    "magic-space",
+    "menu-complete",
+    "menu-complete-backward",
+    "next-history",
+    "next-screen-line",
+    "non-incremental-forward-search-history",
+    "non-incremental-forward-search-history-again",
+    "non-incremental-reverse-search-history",
+    "non-incremental-reverse-search-history-again",
+    "old-menu-complete",
+    "operate-and-get-next",
+    "overwrite-mode",
+    "possible-command-completions",
+    "possible-completions",
+    "possible-filename-completions",
+    "possible-hostname-completions",
+    "possible-username-completions",
+    "possible-variable-completions",
+    "previous-history",
+    "previous-screen-line",
+    "print-last-kbd-macro",
+    "quoted-insert",
+    "re-read-init-file",
+    "redraw-current-line",
+    "reverse-search-history",
+    "revert-line",
+    "self-insert",
+    "set-mark",
+    "shell-backward-kill-word",
+    "shell-backward-word",
+    "shell-expand-line",
+    "shell-forward-word",
+    "shell-kill-word",
+    "shell-transpose-words",
+    "skip-csi-sequence",
+    "spell-correct-word",
+    "start-kbd-macro",
+    "tab-insert",
+    "tilde-expand",
+    "transpose-chars",
+    "transpose-words",
+    "tty-status",
+    "undo",
+    "universal-argument",
+    "unix-filename-rubout",
+    "unix-line-discard",
+    "unix-word-rubout",
+    "upcase-word",
+    "vi-append-eol",
+    "vi-append-mode",
+    "vi-arg-digit",
+    "vi-bWord",
+    "vi-back-to-indent",
+    "vi-backward-bigword",
+    "vi-backward-word",
+    "vi-bword",
+    "vi-change-case",
+    "vi-change-char",
+    "vi-change-to",
+    "vi-char-search",
+    "vi-column",
+    "vi-complete",
+    "vi-delete",
+    "vi-delete-to",
+    "vi-eWord",
+    "vi-edit-and-execute-command",
+    "vi-editing-mode",
+    "vi-end-bigword",
+    "vi-end-word",
+    "vi-eof-maybe",
+    "vi-eword",
+    "vi-fWord",
+    "vi-fetch-history",
+    "vi-first-print",
+    "vi-forward-bigword",
+    "vi-forward-word",
+    "vi-fword",
+    "vi-goto-mark",
+    "vi-insert-beg",
+    "vi-insertion-mode",
+    "vi-match",
+    "vi-movement-mode",
+    "vi-next-word",
+    "vi-overstrike",
+    "vi-overstrike-delete",
+    "vi-prev-word",
+    "vi-put",
+    "vi-redo",
+    "vi-replace",
+    "vi-rubout",
+    "vi-search",
+    "vi-search-again",
+    "vi-set-mark",
+    "vi-subst",
+    "vi-tilde-expand",
+    "vi-undo",
+    "vi-unix-word-rubout",
+    "vi-yank-arg",
+    "vi-yank-pop",
+    "vi-yank-to",
+    "yank",
+    "yank-last-arg",
+    "yank-nth-arg",
+    "yank-pop",
+];
+
+/// Returns the canonical name of the named readline function; a few functions are known by
+/// more than one name.
+///
+/// # Arguments
+///
+/// * `name` - The name of the function.
+pub fn canonical_function_name(name: &str) -> &str {
+    match name {
+        "insert-last-argument" => "yank-last-arg",
+        other => other,
+    }
+}
+
+/// Default bindings for the emacs key map.
+const DEFAULT_EMACS_BINDINGS: &[(&str, &str)] = &[
+    (r"\C-g", "abort"),
+    (r"\C-x\C-g", "abort"),
+    (r"\M-\C-g", "abort"),
+    (r"\C-j", "accept-line"),
+    (r"\C-m", "accept-line"),
+    (r"\C-b", "backward-char"),
+    (r"\M-OD", "backward-char"),
+    (r"\M-[D", "backward-char"),
+    (r"\C-h", "backward-delete-char"),
+    (r"\C-?", "backward-delete-char"),
+    (r"\C-x\C-?", "backward-kill-line"),
+    (r"\M-\C-h", "backward-kill-word"),
+    (r"\M-\C-?", "backward-kill-word"),
+    (r"\M-[1;3D", "backward-word"),
+    (r"\M-[1;5D", "backward-word"),
+    (r"\M-b", "backward-word"),
+    (r"\M-<", "beginning-of-history"),
+    (r"\C-a", "beginning-of-line"),
+    (r"\M-OH", "beginning-of-line"),
+    (r"\M-[H", "beginning-of-line"),
+    (r"\M-[200~", "bracketed-paste-begin"),
+    (r"\C-xe", "call-last-kbd-macro"),
+    (r"\M-c", "capitalize-word"),
+    (r"\C-]", "character-search"),
+    (r"\M-\C-]", "character-search-backward"),
+    (r"\M-\C-l", "clear-display"),
+    (r"\C-l", "clear-screen"),
+    (r"\C-i", "complete"),
+    (r"\M-\e", "complete"),
+    (r"\M-!", "complete-command"),
+    (r"\M-/", "complete-filename"),
+    (r"\M-@", "complete-hostname"),
+    (r"\M-{", "complete-into-braces"),
+    (r"\M-~", "complete-username"),
+    (r"\M-$", "complete-variable"),
+    (r"\C-d", "delete-char"),
+    (r"\M-\\", "delete-horizontal-space"),
+    (r"\C-x\C-v", "display-shell-version"),
+    (r"\M-l", "downcase-word"),
+    (r"\M-\C-i", "dynamic-complete-history"),
+    (r"\C-x\C-e", "edit-and-execute-command"),
+    (r"\C-x)", "end-kbd-macro"),
+    (r"\M->", "end-of-history"),
+    (r"\C-e", "end-of-line"),
+    (r"\M-OF", "end-of-line"),
+    (r"\M-[F", "end-of-line"),
+    (r"\C-x\C-x", "exchange-point-and-mark"),
+    (r"\C-f", "forward-char"),
+    (r"\M-OC", "forward-char"),
+    (r"\M-[C", "forward-char"),
+    (r"\C-s", "forward-search-history"),
+    (r"\M-[1;3C", "forward-word"),
+    (r"\M-[1;5C", "forward-word"),
+    (r"\M-f", "forward-word"),
+    (r"\M-g", "glob-complete-word"),
+    (r"\C-x*", "glob-expand-word"),
+    (r"\C-xg", "glob-list-expansions"),
+    (r"\M-^", "history-expand-line"),
+    (r"\M-#", "insert-comment"),
+    (r"\M-*", "insert-completions"),
+    (r"\M-.", "insert-last-argument"),
+    (r"\M-_", "insert-last-argument"),
+    (r"\C-k", "kill-line"),
+    (r"\M-[3;5~", "kill-word"),
+    (r"\M-d", "kill-word"),
+    (r"\C-n", "next-history"),
+    (r"\M-OB", "next-history"),
+    (r"\M-[B", "next-history"),
+    (r"\M-n", "non-incremental-forward-search-history"),
+    (r"\M-p", "non-incremental-reverse-search-history"),
+    (r"\C-o", "operate-and-get-next"),
+    (r"\C-x!", "possible-command-completions"),
+    (r"\M-=", "possible-completions"),
+    (r"\M-?", "possible-completions"),
+    (r"\C-x/", "possible-filename-completions"),
+    (r"\C-x@", "possible-hostname-completions"),
+    (r"\C-x~", "possible-username-completions"),
+    (r"\C-x$", "possible-variable-completions"),
+    (r"\C-p", "previous-history"),
+    (r"\M-OA", "previous-history"),
+    (r"\M-[A", "previous-history"),
+    (r"\C-q", "quoted-insert"),
+    (r"\C-v", "quoted-insert"),
+    (r"\C-x\C-r", "re-read-init-file"),
+    (r"\C-r", "reverse-search-history"),
+    (r"\M-\C-r", "revert-line"),
+    (r"\M-r", "revert-line"),
+    (r"\C-@", "set-mark"),
+    (r"\M- ", "set-mark"),
+    (r"\M-\C-b", "shell-backward-word"),
+    (r"\M-\C-e", "shell-expand-line"),
+    (r"\M-\C-f", "shell-forward-word"),
+    (r"\M-\C-d", "shell-kill-word"),
+    (r"\M-\C-t", "shell-transpose-words"),
+    (r"\C-xs", "spell-correct-word"),
+    (r"\C-x(", "start-kbd-macro"),
+    (r"\M-&", "tilde-expand"),
+    (r"\C-t", "transpose-chars"),
+    (r"\M-t", "transpose-words"),
+    (r"\C-x\C-u", "undo"),
+    (r"\C-_", "undo"),
+    (r"\C-u", "unix-line-discard"),
+    (r"\C-w", "unix-word-rubout"),
+    (r"\M-u", "upcase-word"),
+    (r"\C-y", "yank"),
+    (r"\M-.", "yank-last-arg"),
+    (r"\M-_", "yank-last-arg"),
+    (r"\M-\C-y", "yank-nth-arg"),
+    (r"\M-y", "yank-pop"),
+];
+
+/// Default bindings for the vi insertion key map.
+const DEFAULT_VI_INSERT_BINDINGS: &[(&str, &str)] = &[
+    (r"\C-j", "accept-line"),
+    (r"\C-m", "accept-line"),
+    (r"\M-OD", "backward-char"),
+    (r"\M-[D", "backward-char"),
+    (r"\C-h", "backward-delete-char"),
+    (r"\C-?", "backward-delete-char"),
+    (r"\M-[1;3D", "backward-word"),
+    (r"\M-[1;5D", "backward-word"),
+    (r"\M-OH", "beginning-of-line"),
+    (r"\M-[H", "beginning-of-line"),
+    (r"\M-[200~", "bracketed-paste-begin"),
+    (r"\C-i", "complete"),
+    (r"\M-OF", "end-of-line"),
+    (r"\M-[F", "end-of-line"),
+    (r"\M-OC", "forward-char"),
+    (r"\M-[C", "forward-char"),
+    (r"\C-s", "forward-search-history"),
+    (r"\M-[1;3C", "forward-word"),
+    (r"\M-[1;5C", "forward-word"),
+    (r"\M-[3;5~", "kill-word"),
+    (r"\C-n", "menu-complete"),
+    (r"\C-p", "menu-complete-backward"),
+    (r"\M-OB", "next-history"),
+    (r"\M-[B", "next-history"),
+    (r"\M-OA", "previous-history"),
+    (r"\M-[A", "previous-history"),
+    (r"\C-v", "quoted-insert"),
+    (r"\C-r", "reverse-search-history"),
+    (r"\C-t", "transpose-chars"),
+    (r"\C-u", "unix-line-discard"),
+    (r"\C-d", "vi-eof-maybe"),
+    (r"\e", "vi-movement-mode"),
+    (r"\C-_", "vi-undo"),
+    (r"\C-w", "vi-unix-word-rubout"),
+    (r"\C-y", "yank"),
+];
+
+/// Default bindings for the vi command key map.
+const DEFAULT_VI_COMMAND_BINDINGS: &[(&str, &str)] = &[
+    (r"\C-g", "abort"),
+    (r"\C-j", "accept-line"),
+    (r"\C-m", "accept-line"),
+    (r"\C-h", "backward-char"),
+    (r"\M-OD", "backward-char"),
+    (r"\M-[D", "backward-char"),
+    (r"h", "backward-char"),
+    (r"\M-[1;3D", "backward-word"),
+    (r"\M-[1;5D", "backward-word"),
+    (r"\M-OH", "beginning-of-line"),
+    (r"\M-[H", "beginning-of-line"),
+    (r"0", "beginning-of-line"),
+    (r"\C-l", "clear-screen"),
+    (r"\M-OF", "end-of-line"),
+    (r"\M-[F", "end-of-line"),
+    (r"$", "end-of-line"),
+    (r"\M-OC", "forward-char"),
+    (r"\M-[C", "forward-char"),
+    (r" ", "forward-char"),
+    (r"l", "forward-char"),
+    (r"\C-s", "forward-search-history"),
+    (r"\M-[1;3C", "forward-word"),
+    (r"\M-[1;5C", "forward-word"),
+    (r"#", "insert-comment"),
+    (r"\C-k", "kill-line"),
+    (r"\M-[3;5~", "kill-word"),
+    (r"\C-n", "next-history"),
+    (r"\M-OB", "next-history"),
+    (r"\M-[B", "next-history"),
+    (r"+", "next-history"),
+    (r"j", "next-history"),
+    (r"\C-p", "previous-history"),
+    (r"\M-OA", "previous-history"),
+    (r"\M-[A", "previous-history"),
+    (r"-", "previous-history"),
+    (r"k", "previous-history"),
+    (r"\C-q", "quoted-insert"),
+    (r"\C-v", "quoted-insert"),
+    (r"\C-r", "reverse-search-history"),
+    (r"U", "revert-line"),
+    (r"\C-t", "transpose-chars"),
+    (r"\C-u", "unix-line-discard"),
+    (r"A", "vi-append-eol"),
+    (r"a", "vi-append-mode"),
+    (r"1", "vi-arg-digit"),
+    (r"2", "vi-arg-digit"),
+    (r"3", "vi-arg-digit"),
+    (r"4", "vi-arg-digit"),
+    (r"5", "vi-arg-digit"),
+    (r"6", "vi-arg-digit"),
+    (r"7", "vi-arg-digit"),
+    (r"8", "vi-arg-digit"),
+    (r"9", "vi-arg-digit"),
+    (r"~", "vi-change-case"),
+    (r"r", "vi-change-char"),
+    (r"C", "vi-change-to"),
+    (r"c", "vi-change-to"),
+    (r",", "vi-char-search"),
+    (r";", "vi-char-search"),
+    (r"F", "vi-char-search"),
+    (r"T", "vi-char-search"),
+    (r"f", "vi-char-search"),
+    (r"t", "vi-char-search"),
+    (r"|", "vi-column"),
+    (r"x", "vi-delete"),
+    (r"D", "vi-delete-to"),
+    (r"d", "vi-delete-to"),
+    (r"v", "vi-edit-and-execute-command"),
+    (r"E", "vi-end-word"),
+    (r"e", "vi-end-word"),
+    (r"\C-d", "vi-eof-maybe"),
+    (r"G", "vi-fetch-history"),
+    (r"^", "vi-first-print"),
+    (r"`", "vi-goto-mark"),
+    (r"I", "vi-insert-beg"),
+    (r"i", "vi-insertion-mode"),
+    (r"%", "vi-match"),
+    (r"W", "vi-next-word"),
+    (r"w", "vi-next-word"),
+    (r"B", "vi-prev-word"),
+    (r"b", "vi-prev-word"),
+    (r"P", "vi-put"),
+    (r"p", "vi-put"),
+    (r".", "vi-redo"),
+    (r"R", "vi-replace"),
+    (r"X", "vi-rubout"),
+    (r"/", "vi-search"),
+    (r"?", "vi-search"),
+    (r"N", "vi-search-again"),
+    (r"n", "vi-search-again"),
+    (r"m", "vi-set-mark"),
+    (r"S", "vi-subst"),
+    (r"s", "vi-subst"),
+    (r"&", "vi-tilde-expand"),
+    (r"\C-_", "vi-undo"),
+    (r"u", "vi-undo"),
+    (r"\C-w", "vi-unix-word-rubout"),
+    (r"_", "vi-yank-arg"),
+    (r"Y", "vi-yank-to"),
+    (r"y", "vi-yank-to"),
+    (r"\C-y", "yank"),
+];
+
+/// Default bindings for keypad keys whose sequences vary by terminal; readline looks them up
+/// in the terminal's terminfo entry.
+const DEFAULT_KEYPAD_BINDINGS: &[(&str, &str)] = &[
+    (r"\M-[2~", "overwrite-mode"),
+    (r"\M-[3~", "delete-char"),
+    (r"\M-[5~", "history-search-backward"),
+    (r"\M-[6~", "history-search-forward"),
+];
+
+/// Terminals known to send the key sequences in `DEFAULT_KEYPAD_BINDINGS`.
+const KEYPAD_TERMINALS: &[&str] = &["xterm", "screen", "tmux", "rxvt", "linux", "konsole"];
+
+/// Boolean variables, with their default values.
+const DEFAULT_BOOLEAN_VARIABLES: &[(&str, bool)] = &[
+    ("bind-tty-special-chars", true),
+    ("blink-matching-paren", false),
+    ("byte-oriented", false),
+    ("colored-completion-prefix", false),
+    ("colored-stats", false),
+    ("completion-ignore-case", false),
+    ("completion-map-case", false),
+    ("convert-meta", true),
+    ("disable-completion", false),
+    ("echo-control-characters", true),
+    ("enable-active-region", true),
+    ("enable-bracketed-paste", true),
+    ("enable-keypad", false),
+    ("enable-meta-key", true),
+    ("expand-tilde", false),
+    ("history-preserve-point", false),
+    ("horizontal-scroll-mode", false),
+    ("input-meta", false),
+    ("mark-directories", true),
+    ("mark-modified-lines", false),
+    ("mark-symlinked-directories", false),
+    ("match-hidden-files", true),
+    ("menu-complete-display-prefix", false),
+    ("meta-flag", false),
+    ("output-meta", false),
+    ("page-completions", true),
+    ("prefer-visible-bell", true),
+    ("print-completions-horizontally", false),
+    ("revert-all-at-newline", false),
+    ("show-all-if-ambiguous", false),
+    ("show-all-if-unmodified", false),
+    ("show-mode-in-prompt", false),
+    ("skip-completed-text", false),
+    ("visible-stats", false),
+];
+
+/// Variables with string (or numeric) values, with their default values.
+const DEFAULT_STRING_VARIABLES: &[(&str, &str)] = &[
+    ("bell-style", "audible"),
+    ("comment-begin", "#"),
+    ("completion-display-width", "-1"),
+    ("completion-prefix-display-length", "0"),
+    ("completion-query-items", "100"),
+    ("editing-mode", "emacs"),
+    ("emacs-mode-string", "@"),
+    ("history-size", "0"),
+    ("keymap", "emacs"),
+    ("keyseq-timeout", "500"),
+    ("vi-cmd-mode-string", "(cmd)"),
+    ("vi-ins-mode-string", "(ins)"),
+];
+
+/// Variables whose values are numeric.
+const NUMERIC_VARIABLES: &[&str] = &[
+    "completion-display-width",
+    "completion-prefix-display-length",
+    "completion-query-items",
+    "history-size",
+    "keyseq-timeout",
+];
+
+/// Represents an error encountered while processing readline configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// The named variable is not known.
+    #[error("{0}: unknown variable name")]
+    UnknownVariable(String),
+
+    /// The value could not be assigned to the named variable.
+    #[error("{0}: could not set value to `{1}'")]
+    InvalidVariableValue(String, String),
+
+    /// A key sequence was missing its closing quote.
+    #[error("{0}: no closing `\"' in key binding")]
+    UnterminatedKeySequence(String),
+
+    /// A binding didn't indicate what to bind its key sequence to.
+    #[error("{0}: no key sequence terminator")]
+    MissingBindingValue(String),
+
+    /// A parser directive was not recognized.
+    #[error("{0}: unknown parser directive")]
+    UnknownDirective(String),
+
+    /// An `$else` or `$endif` directive was found outside of a conditional.
+    #[error("{0} without matching $if")]
+    UnmatchedDirective(String),
+
+    /// An included file could not be read.
+    #[error("{0}: cannot read: {1}")]
+    UnreadableFile(PathBuf, std::io::Error),
+
+    /// A shell command binding didn't start with a quoted key sequence.
+    #[error("{0}: first non-whitespace character is not `\"'")]
+    UnquotedShellCommandKeySequence(String),
+
+    /// A shell command binding didn't separate its key sequence from its command.
+    #[error("{0}: missing colon separator")]
+    MissingColonSeparator(String),
+
+    /// A shell command binding was missing a closing quote.
+    #[error("no closing `{1}' in {0}")]
+    UnterminatedShellCommandBinding(String, char),
+}
+
+/// Identifies a set of key bindings.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum KeyMap {
+    /// Bindings used in emacs editing mode.
+    Emacs,
+    /// Bindings in emacs editing mode for key sequences starting with the meta (escape) key.
+    EmacsMeta,
+    /// Bindings in emacs editing mode for key sequences starting with Ctrl+X.
+    EmacsCtlx,
+    /// Bindings used in vi command (a.k.a. movement) mode.
+    ViCommand,
+    /// Bindings used in vi insertion mode.
+    ViInsert,
+}
+
+impl KeyMap {
+    /// Parses a key map name, as accepted by readline.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to parse.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "emacs" | "emacs-standard" => Some(Self::Emacs),
+            "emacs-meta" => Some(Self::EmacsMeta),
+            "emacs-ctlx" => Some(Self::EmacsCtlx),
+            "vi" | "vi-move" | "vi-command" => Some(Self::ViCommand),
+            "vi-insert" => Some(Self::ViInsert),
+            _ => None,
+        }
+    }
+
+    /// Returns the canonical name of the key map.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Emacs => "emacs",
+            Self::EmacsMeta => "emacs-meta",
+            Self::EmacsCtlx => "emacs-ctlx",
+            Self::ViCommand => "vi-command",
+            Self::ViInsert => "vi-insert",
+        }
+    }
+
+    /// Returns the key map that actually holds this key map's bindings, along with the
+    /// key sequence prefix its bindings are stored under.
+    fn storage(self) -> (Self, &'static [u8]) {
+        match self {
+            Self::EmacsMeta => (Self::Emacs, b"\x1b"),
+            Self::EmacsCtlx => (Self::Emacs, b"\x18"),
+            other => (other, b""),
+        }
+    }
+
+    /// Returns the default bindings of the key map that holds this key map's bindings.
+    ///
+    /// # Arguments
+    ///
+    /// * `term` - The name of the terminal in use.
+    fn default_bindings(self, term: &str) -> BTreeMap<Vec<u8>, KeyAction> {
+        let storage = self.storage().0;
+
+        let table = match storage {
+            Self::ViCommand => DEFAULT_VI_COMMAND_BINDINGS,
+            Self::ViInsert => DEFAULT_VI_INSERT_BINDINGS,
+            _ => DEFAULT_EMACS_BINDINGS,
+        };
+
+        let keypad_table = if KEYPAD_TERMINALS
+            .iter()
+            .any(|prefix| term.starts_with(prefix))
+        {
+            DEFAULT_KEYPAD_BINDINGS
+        } else {
+            &[]
+        };
+
+        let mut bindings: BTreeMap<_, _> = table
+            .iter()
+            .chain(keypad_table)
+            .map(|(key_sequence, function)| {
+                (
+                    parse_key_sequence(key_sequence),
+                    KeyAction::Function((*function).to_owned()),
+                )
+            })
+            .collect();
+
+        let mut add = |key_sequence: Vec<u8>, function: &str| {
+            bindings.insert(key_sequence, KeyAction::Function(function.to_owned()));
+        };
+
+        // Bindings that follow a regular pattern aren't worth spelling out in the tables.
+        match storage {
+            Self::Emacs => {
+                for key in (b' '..=b'~').chain(0x80..=0xff) {
+                    add(vec![key], "self-insert");
+                }
+                for key in b"-0123456789" {
+                    add(vec![b'\x1b', *key], "digit-argument");
+                }
+                for key in b'A'..=b'Z' {
+                    add(vec![b'\x18', key], "do-lowercase-version");
+                    // N.B. Meta-O is used as a prefix by some terminals' keypad sequences.
+                    if key != b'O' {
+                        add(vec![b'\x1b', key], "do-lowercase-version");
+                    }
+                }
+            }
+            Self::ViInsert => {
+                for key in b"abcefgkloqxz\\]^" {
+                    add(vec![control_key(*key)], "self-insert");
+                }
+                for key in (b' '..=b'~').chain(0x80..=0xff) {
+                    add(vec![key], "self-insert");
+                }
+            }
+            _ => (),
+        }
+
+        bindings
+    }
+}
+
+/// An action that can be bound to a key sequence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyAction {
+    /// Invokes the named readline function.
+    Function(String),
+    /// Inserts the given text, as if it had been typed.
+    Macro(String),
+    /// Runs the given shell command, which may inspect and modify the line being edited.
+    ShellCommand(String),
+}
+
+/// Readline configuration for a shell.
+#[derive(Clone)]
+pub struct Config {
+    /// Changes made to the default bindings of each key map; a change of `None` removes
+    /// the binding for that key sequence.
+    binding_changes: HashMap<KeyMap, BTreeMap<Vec<u8>, Option<KeyAction>>>,
+    /// Current values of all variables.
+    variables: BTreeMap<String, String>,
+    /// Name of the terminal in use.
+    term: String,
+    /// Incremented each time the configuration changes.
+    generation: u64,
+    /// Whether the user's init file has been loaded.
+    pub(crate) init_file_loaded: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut variables = BTreeMap::new();
+        for (name, value) in DEFAULT_BOOLEAN_VARIABLES {
+            variables.insert((*name).to_owned(), bool_to_str(*value).to_owned());
+        }
+        for (name, value) in DEFAULT_STRING_VARIABLES {
+            variables.insert((*name).to_owned(), (*value).to_owned());
+        }
+
+        Self {
+            binding_changes: HashMap::new(),
+            variables,
+            term: String::new(),
+            generation: 0,
+            init_file_loaded: false,
+        }
+    }
+}
+
+/// State maintained while processing a sequence of configuration lines (e.g., the lines
+/// of an init file).
+pub struct ParseContext {
+    /// Key map that bindings are added to.
+    pub keymap: KeyMap,
+    /// Name of the terminal in use, for evaluating `$if term=...` conditionals.
+    term: String,
+    /// Enclosing conditional sections.
+    conditionals: Vec<Conditional>,
+    /// Number of files currently being included.
+    include_depth: usize,
+}
+
+struct Conditional {
+    /// Whether the section enclosing the conditional is being processed.
+    parent_active: bool,
+    /// Whether the current branch of the conditional is being processed.
+    active: bool,
+}
+
+impl ParseContext {
+    /// Returns a new context for processing configuration lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map that bindings are initially added to.
+    /// * `term` - The name of the terminal in use.
+    pub fn new(keymap: KeyMap, term: &str) -> Self {
+        Self {
+            keymap,
+            term: term.to_owned(),
+            conditionals: vec![],
+            include_depth: 0,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.conditionals
+            .last()
+            .map_or(true, |c| c.parent_active && c.active)
+    }
+}
+
+impl Config {
+    /// Returns a counter that changes each time the configuration is changed.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Updates the configuration for the terminal in use; some default bindings and
+    /// variable values depend on the terminal's capabilities.
+    ///
+    /// # Arguments
+    ///
+    /// * `term` - The name of the terminal in use.
+    pub fn set_terminal(&mut self, term: &str) {
+        term.clone_into(&mut self.term);
+
+        // Features that rely on terminal control sequences are disabled for terminals
+        // that can't be expected to support them.
+        if term.is_empty() || term == "dumb" {
+            for name in ["enable-active-region", "enable-bracketed-paste"] {
+                self.variables
+                    .insert(name.to_owned(), bool_to_str(false).to_owned());
+            }
+        }
+
+        self.generation += 1;
+    }
+
+    /// Returns the key map currently selected by the `keymap` variable.
+    pub fn current_keymap(&self) -> KeyMap {
+        self.get_variable("keymap")
+            .and_then(KeyMap::parse)
+            .unwrap_or(KeyMap::Emacs)
+    }
+
+    /// Returns the text to display in the prompt to indicate that the given key map is in
+    /// use, or `None` if `show-mode-in-prompt` is off. The text comes from the
+    /// `emacs-mode-string`, `vi-ins-mode-string`, or `vi-cmd-mode-string` variable, with
+    /// escape sequences expanded as they are in key bindings.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map in use.
+    pub fn mode_string(&self, keymap: KeyMap) -> Option<String> {
+        if !self.is_variable_on("show-mode-in-prompt") {
+            return None;
+        }
+
+        let name = match keymap {
+            KeyMap::Emacs | KeyMap::EmacsMeta | KeyMap::EmacsCtlx => "emacs-mode-string",
+            KeyMap::ViCommand => "vi-cmd-mode-string",
+            KeyMap::ViInsert => "vi-ins-mode-string",
+        };
+
+        // N.B. `\1` and `\2` delimit non-printing characters, which needn't be marked here.
+        let text = parse_key_sequence(self.get_variable(name).unwrap_or_default());
+        let text = String::from_utf8_lossy(&text).replace(['\x01', '\x02'], "");
+
+        Some(text)
+    }
+
+    /// Returns the effective bindings of the given key map, ordered by key sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map to retrieve bindings from.
+    pub fn bindings(&self, keymap: KeyMap) -> BTreeMap<Vec<u8>, KeyAction> {
+        let (storage, prefix) = keymap.storage();
+
+        let mut bindings = keymap.default_bindings(self.term.as_str());
+
+        if let Some(changes) = self.binding_changes.get(&storage) {
+            for (key_sequence, action) in changes {
+                if let Some(action) = action {
+                    bindings.insert(key_sequence.clone(), action.clone());
+                } else {
+                    bindings.remove(key_sequence);
+                }
+            }
+        }
+
+        bindings
+            .into_iter()
+            .filter_map(|(key_sequence, action)| {
+                key_sequence
+                    .strip_prefix(prefix)
+                    .filter(|remaining| !remaining.is_empty())
+                    .map(|remaining| (remaining.to_vec(), action))
+            })
+            .collect()
+    }
+
+    /// Returns the changes that have been made to the default bindings of the given key
+    /// map, ordered by key sequence; a change of `None` removes the binding.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map to retrieve changes for.
+    pub fn binding_changes(
+        &self,
+        keymap: KeyMap,
+    ) -> impl Iterator<Item = (&[u8], Option<&KeyAction>)> {
+        self.binding_changes
+            .get(&keymap.storage().0)
+            .into_iter()
+            .flatten()
+            .map(|(key_sequence, action)| (key_sequence.as_slice(), action.as_ref()))
+    }
+
+    /// Returns the key sequences in the given key map that are bound to the named function.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map to search.
+    /// * `function` - The name of the function.
+    pub fn key_sequences_for_function(&self, keymap: KeyMap, function: &str) -> Vec<Vec<u8>> {
+        self.bindings(keymap)
+            .into_iter()
+            .filter(|(_, action)| {
+                matches!(action, KeyAction::Function(f)
+                    if canonical_function_name(f) == canonical_function_name(function))
+            })
+            .map(|(key_sequence, _)| key_sequence)
+            .collect()
+    }
+
+    /// Binds a key sequence to an action.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map to add the binding to.
+    /// * `key_sequence` - The key sequence to bind.
+    /// * `action` - The action to bind it to.
+    pub fn bind(&mut self, keymap: KeyMap, key_sequence: &[u8], action: KeyAction) {
+        self.change_binding(keymap, key_sequence, Some(action));
+    }
+
+    /// Removes any binding for a key sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map to remove the binding from.
+    /// * `key_sequence` - The key sequence to unbind.
+    pub fn unbind(&mut self, keymap: KeyMap, key_sequence: &[u8]) {
+        self.change_binding(keymap, key_sequence, None);
+    }
+
+    /// Removes all bindings to the named function.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The key map to remove bindings from.
+    /// * `function` - The name of the function.
+    pub fn unbind_function(&mut self, keymap: KeyMap, function: &str) {
+        for key_sequence in self.key_sequences_for_function(keymap, function) {
+            self.unbind(keymap, key_sequence.as_slice());
+        }
+    }
+
+    fn change_binding(&mut self, keymap: KeyMap, key_sequence: &[u8], action: Option<KeyAction>) {
+        let (storage, prefix) = keymap.storage();
+
+        let mut full_key_sequence = prefix.to_vec();
+        full_key_sequence.extend_from_slice(key_sequence);
+
+        self.binding_changes
+            .entry(storage)
+            .or_default()
+            .insert(full_key_sequence, action);
+        self.generation += 1;
+    }
+
+    /// Returns the value of the named variable, if it's known.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable.
+    pub fn get_variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(|value| value.as_str())
+    }
+
+    /// Returns whether the named boolean variable is on.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable.
+    pub fn is_variable_on(&self, name: &str) -> bool {
+        self.get_variable(name).is_some_and(|value| value == "on")
+    }
+
+    /// Returns the names and values of all variables, with boolean variables first; each
+    /// group is ordered by name.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &str)> {
+        let booleans = self
+            .variables
+            .iter()
+            .filter(|(name, _)| is_boolean_variable(name));
+        let others = self
+            .variables
+            .iter()
+            .filter(|(name, _)| !is_boolean_variable(name));
+
+        booleans
+            .chain(others)
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Sets the value of the named variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable.
+    /// * `value` - The value to assign to it.
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        let name = name.to_ascii_lowercase();
+
+        let value = if is_boolean_variable(name.as_str()) {
+            // N.B. An empty value turns the variable on.
+            let on = value.is_empty() || value.eq_ignore_ascii_case("on") || value == "1";
+            bool_to_str(on).to_owned()
+        } else if NUMERIC_VARIABLES.contains(&name.as_str()) {
+            parse_leading_int(value).to_string()
+        } else {
+            match name.as_str() {
+                "bell-style" => match value.to_ascii_lowercase().as_str() {
+                    v @ ("none" | "visible" | "audible") => v.to_owned(),
+                    "off" => "none".to_owned(),
+                    "on" => "audible".to_owned(),
+                    _ => return Err(ConfigError::InvalidVariableValue(name, value.to_owned())),
+                },
+                "editing-mode" => match value {
+                    "emacs" | "vi" => {
+                        let keymap = if value == "vi" {
+                            KeyMap::ViInsert
+                        } else {
+                            KeyMap::Emacs
+                        };
+                        self.variables
+                            .insert("keymap".to_owned(), keymap.name().to_owned());
+                        value.to_owned()
+                    }
+                    _ => return Err(ConfigError::InvalidVariableValue(name, value.to_owned())),
+                },
+                "keymap" => match KeyMap::parse(value) {
+                    Some(keymap) => keymap.name().to_owned(),
+                    None => return Err(ConfigError::InvalidVariableValue(name, value.to_owned())),
+                },
+                _ if self.variables.contains_key(name.as_str()) => value.to_owned(),
+                _ => return Err(ConfigError::UnknownVariable(name)),
+            }
+        };
+
+        // N.B. meta-flag is a synonym for input-meta.
+        if let Some(synonym) = match name.as_str() {
+            "input-meta" => Some("meta-flag"),
+            "meta-flag" => Some("input-meta"),
+            _ => None,
+        } {
+            self.variables.insert(synonym.to_owned(), value.clone());
+        }
+
+        self.variables.insert(name, value);
+        self.generation += 1;
+
+        Ok(())
+    }
+
+    /// Processes one line of configuration, in the syntax used by init files. Returns the
+    /// path of a file to include if the line is an active `$include` directive.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to process.
+    /// * `context` - The state of the ongoing processing.
+    pub fn parse_line(
+        &mut self,
+        line: &str,
+        context: &mut ParseContext,
+    ) -> Result<Option<PathBuf>, ConfigError> {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        if let Some(directive) = line.strip_prefix('$') {
+            return self.parse_directive(directive, context);
+        }
+
+        if !context.is_active() {
+            return Ok(None);
+        }
+
+        if let Some(assignment) = strip_keyword(line, "set") {
+            let (name, value) = split_word(assignment);
+            let (value, _) = split_word(value);
+
+            self.set_variable(name, value)?;
+
+            // Changing the key map or editing mode affects later bindings.
+            if matches!(
+                name.to_ascii_lowercase().as_str(),
+                "keymap" | "editing-mode"
+            ) {
+                context.keymap = self.current_keymap();
+            }
+
+            return Ok(None);
+        }
+
+        let (key_sequence, value) = parse_binding_key_sequence(line)?;
+
+        let value = value.trim_start();
+        let action = if let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let text = &value[1..];
+            let end = find_closing_quote(text, quote).unwrap_or(text.len());
+            let macro_bytes = parse_key_sequence(&text[..end]);
+            KeyAction::Macro(String::from_utf8_lossy(&macro_bytes).into_owned())
+        } else {
+            let (function, _) = split_word(value);
+            if function.is_empty() {
+                return Err(ConfigError::MissingBindingValue(line.to_owned()));
+            }
+
+            // N.B. Like readline, we silently ignore bindings to unknown functions.
+            let function = function.to_ascii_lowercase();
+            if !FUNCTION_NAMES.contains(&function.as_str()) {
+                return Ok(None);
+            }
+
+            KeyAction::Function(canonical_function_name(function.as_str()).to_owned())
+        };
+
+        self.bind(context.keymap, key_sequence.as_slice(), action);
+
+        Ok(None)
+    }
+
+    fn parse_directive(
+        &mut self,
+        directive: &str,
+        context: &mut ParseContext,
+    ) -> Result<Option<PathBuf>, ConfigError> {
+        let (name, args) = split_word(directive);
+        let args = args.trim();
+
+        match name.to_ascii_lowercase().as_str() {
+            "if" => {
+                let parent_active = context.is_active();
+                let active = parent_active && self.evaluate_condition(args, context);
+                context.conditionals.push(Conditional {
+                    parent_active,
+                    active,
+                });
+            }
+            "else" => {
+                let Some(conditional) = context.conditionals.last_mut() else {
+                    return Err(ConfigError::UnmatchedDirective("$else".to_owned()));
+                };
+                conditional.active = !conditional.active;
+            }
+            "endif" => {
+                if context.conditionals.pop().is_none() {
+                    return Err(ConfigError::UnmatchedDirective("$endif".to_owned()));
+                }
+            }
+            "include" => {
+                if context.is_active() {
+                    return Ok(Some(PathBuf::from(args)));
+                }
+            }
+            _ => {
+                if context.is_active() {
+                    return Err(ConfigError::UnknownDirective(name.to_owned()));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn evaluate_condition(&self, condition: &str, context: &ParseContext) -> bool {
+        if let Some(mode) = strip_test_name(condition, "mode") {
+            return self.get_variable("editing-mode") == Some(mode);
+        }
+
+        if let Some(term) = strip_test_name(condition, "term") {
+            let short_term = context.term.split('-').next().unwrap_or_default();
+            return context.term == term || short_term == term;
+        }
+
+        // Otherwise, it may be a comparison of the readline version or of a variable's value.
+        for op in ["==", "!=", ">=", "<=", "=", ">", "<"] {
+            if let Some((left, right)) = condition.split_once(op) {
+                let (left, right) = (left.trim(), right.trim());
+
+                if left.eq_ignore_ascii_case("version") {
+                    let version = parse_version(right);
+                    return match op {
+                        "=" | "==" => READLINE_VERSION == version,
+                        "!=" => READLINE_VERSION != version,
+                        ">=" => READLINE_VERSION >= version,
+                        "<=" => READLINE_VERSION <= version,
+                        ">" => READLINE_VERSION > version,
+                        _ => READLINE_VERSION < version,
+                    };
+                }
+
+                if let Some(value) = self.get_variable(left.to_ascii_lowercase().as_str()) {
+                    return match op {
+                        "=" | "==" => value == right,
+                        "!=" => value != right,
+                        _ => false,
+                    };
+                }
+            }
+        }
+
+        // Anything else names an application.
+        let (application, _) = split_word(condition);
+        APPLICATION_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(application))
+    }
+
+    /// Processes an init file, returning descriptions of any errors encountered.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to process.
+    /// * `context` - The state of the ongoing processing.
+    pub fn load_file(
+        &mut self,
+        path: &Path,
+        context: &mut ParseContext,
+    ) -> Result<Vec<String>, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::UnreadableFile(path.to_owned(), e))?;
+
+        let mut errors = vec![];
+        let conditional_depth = context.conditionals.len();
+
+        for (index, line) in contents.lines().enumerate() {
+            match self.parse_line(line, context) {
+                Ok(Some(include_path)) => {
+                    let include_path = resolve_include_path(path, include_path.as_path());
+                    if context.include_depth >= MAX_INCLUDE_DEPTH {
+                        continue;
+                    }
+
+                    context.include_depth += 1;
+                    match self.load_file(include_path.as_path(), context) {
+                        Ok(include_errors) => errors.extend(include_errors),
+                        Err(e) => {
+                            errors.push(std::format!(
+                                "{}: line {}: {e}",
+                                path.display(),
+                                index + 1
+                            ));
+                        }
+                    }
+                    context.include_depth -= 1;
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    errors.push(std::format!("{}: line {}: {e}", path.display(), index + 1));
+                }
+            }
+        }
+
+        // Conditionals don't extend past the end of the file they start in.
+        context.conditionals.truncate(conditional_depth);
+
+        Ok(errors)
+    }
+}
+
+/// Parses a key sequence written with readline's escape sequences (e.g., `\C-x\C-e`),
+/// returning the bytes it represents.
+///
+/// # Arguments
+///
+/// * `s` - The key sequence to parse, without enclosing quotes.
+pub fn parse_key_sequence(s: &str) -> Vec<u8> {
+    let mut result = vec![];
+    let mut chars = s.chars().peekable();
+
+    while chars.peek().is_some() {
+        result.extend(parse_key(&mut chars));
+    }
+
+    result
+}
+
+/// Parses the next key (or escape sequence) from the given characters.
+#[allow(clippy::cast_possible_truncation)]
+fn parse_key(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Vec<u8> {
+    let Some(c) = chars.next() else {
+        return vec![];
+    };
+
+    if c != '\\' {
+        let mut buffer = [0; 4];
+        return c.encode_utf8(&mut buffer).as_bytes().to_vec();
+    }
+
+    let Some(c) = chars.next() else {
+        return vec![b'\\'];
+    };
+
+    match c {
+        'C' | 'M' if chars.peek() == Some(&'-') => {
+            chars.next();
+            let mut key = parse_key(chars);
+            if c == 'C' {
+                if let Some(last) = key.last_mut() {
+                    *last = control_key(*last);
+                }
+            } else {
+                key.insert(0, b'\x1b');
+            }
+            key
+        }
+        'e' => vec![b'\x1b'],
+        'a' => vec![b'\x07'],
+        'b' => vec![b'\x08'],
+        'd' => vec![b'\x7f'],
+        'f' => vec![b'\x0c'],
+        'n' => vec![b'\n'],
+        'r' => vec![b'\r'],
+        't' => vec![b'\t'],
+        'v' => vec![b'\x0b'],
+        '0'..='7' => {
+            let mut value = c.to_digit(8).unwrap_or_default();
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(8)) {
+                    Some(digit) => {
+                        value = value * 8 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            vec![(value & 0xff) as u8]
+        }
+        'x' => {
+            let mut value = None;
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(16)) {
+                    Some(digit) => {
+                        value = Some(value.unwrap_or(0) * 16 + digit);
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            value.map_or_else(|| vec![b'x'], |value| vec![value as u8])
+        }
+        other => {
+            let mut buffer = [0; 4];
+            other.encode_utf8(&mut buffer).as_bytes().to_vec()
+        }
+    }
+}
+
+/// Parses a key name as written on the left-hand side of an unquoted binding (e.g.,
+/// `Control-u` or `Meta-Rubout`), returning the bytes it represents.
+fn parse_key_name(name: &str) -> Vec<u8> {
+    let mut control = false;
+    let mut meta = false;
+    let mut remaining = name;
+
+    loop {
+        let lower = remaining.to_ascii_lowercase();
+        let prefix_len = if lower.starts_with("control-") {
+            control = true;
+            "control-".len()
+        } else if lower.starts_with("c-") {
+            control = true;
+            "c-".len()
+        } else if lower.starts_with("meta-") {
+            meta = true;
+            "meta-".len()
+        } else if lower.starts_with("m-") {
+            meta = true;
+            "m-".len()
+        } else {
+            break;
+        };
+
+        if remaining.len() <= prefix_len {
+            break;
+        }
+        remaining = &remaining[prefix_len..];
+    }
+
+    let mut key = match remaining.to_ascii_lowercase().as_str() {
+        "del" | "rubout" => vec![b'\x7f'],
+        "esc" | "escape" => vec![b'\x1b'],
+        "lfd" | "newline" => vec![b'\n'],
+        "ret" | "return" => vec![b'\r'],
+        "spc" | "space" => vec![b' '],
+        "tab" => vec![b'\t'],
+        _ => remaining
+            .chars()
+            .next()
+            .map(|c| {
+                let mut buffer = [0; 4];
+                c.encode_utf8(&mut buffer).as_bytes().to_vec()
+            })
+            .unwrap_or_default(),
+    };
+
+    if control {
+        if let Some(last) = key.last_mut() {
+            *last = control_key(*last);
+        }
+    }
+
+    if meta {
+        key.insert(0, b'\x1b');
+    }
+
+    key
+}
+
+/// Formats a key sequence using readline's escape sequences, as displayed by `bind`.
+///
+/// # Arguments
+///
+/// * `key_sequence` - The key sequence to format.
+pub fn format_key_sequence(key_sequence: &[u8]) -> String {
+    format_bytes(key_sequence, false)
+}
+
+/// Formats the text of a macro using readline's escape sequences, as displayed by `bind`.
+///
+/// # Arguments
+///
+/// * `text` - The text of the macro.
+pub fn format_macro(text: &str) -> String {
+    format_bytes(text.as_bytes(), true)
+}
+
+fn format_bytes(bytes: &[u8], is_macro: bool) -> String {
+    let mut result = vec![];
+
+    for (i, byte) in bytes.iter().copied().enumerate() {
+        match byte {
+            // N.B. Within a key sequence, an escape that's followed by another key
+            // represents the meta modifier.
+            b'\x1b' if !is_macro && i + 1 < bytes.len() => result.extend_from_slice(b"\\M-"),
+            b'\x1b' => result.extend_from_slice(b"\\e"),
+            b'\x7f' => result.extend_from_slice(b"\\C-?"),
+            b'\x01'..=b'\x1a' => {
+                result.extend_from_slice(b"\\C-");
+                result.push(byte + 0x60);
+            }
+            b'\0' | b'\x1c'..=b'\x1f' => {
+                result.extend_from_slice(b"\\C-");
+                if byte + 0x40 == b'\\' {
+                    result.push(b'\\');
+                }
+                result.push(byte + 0x40);
+            }
+            b'\\' => result.extend_from_slice(b"\\\\"),
+            b'"' => result.extend_from_slice(b"\\\""),
+            0x80.. if !is_macro => result.extend(std::format!("\\{byte:03o}").bytes()),
+            _ => result.push(byte),
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Returns the byte produced by pressing the given key while holding the Control key.
+fn control_key(key: u8) -> u8 {
+    if key == b'?' {
+        b'\x7f'
+    } else {
+        key.to_ascii_uppercase() & 0x1f
+    }
+}
+
+/// Splits the key sequence of a binding from the rest of its line.
+fn parse_binding_key_sequence(line: &str) -> Result<(Vec<u8>, &str), ConfigError> {
+    if let Some(quoted) = line.strip_prefix('"') {
+        let Some(end) = find_closing_quote(quoted, '"') else {
+            return Err(ConfigError::UnterminatedKeySequence(line.to_owned()));
+        };
+
+        let key_sequence = parse_key_sequence(&quoted[..end]);
+        let remaining = &quoted[end + 1..];
+
+        // Skip past the separating colon.
+        let remaining = remaining
+            .find(':')
+            .map_or("", |colon| &remaining[colon + 1..]);
+
+        Ok((key_sequence, remaining))
+    } else {
+        let end = line
+            .find(|c: char| c == ':' || c.is_whitespace())
+            .unwrap_or(line.len());
+        let key_sequence = parse_key_name(&line[..end]);
+
+        let remaining = &line[end..];
+        let remaining = remaining
+            .find(':')
+            .map_or("", |colon| &remaining[colon + 1..]);
+
+        Ok((key_sequence, remaining))
+    }
+}
+
+/// Parses a binding of a key sequence to a shell command, in the syntax accepted by
+/// `bind -x` (e.g., `"\C-r": command`), returning the key sequence and command.
+///
+/// # Arguments
+///
+/// * `binding` - The binding to parse.
+pub fn parse_shell_command_binding(binding: &str) -> Result<(Vec<u8>, String), ConfigError> {
+    let Some(quoted) = binding.trim_start().strip_prefix('"') else {
+        return Err(ConfigError::UnquotedShellCommandKeySequence(
+            binding.to_owned(),
+        ));
+    };
+
+    let Some(end) = find_closing_quote(quoted, '"') else {
+        return Err(ConfigError::UnterminatedShellCommandBinding(
+            binding.to_owned(),
+            '"',
+        ));
+    };
+
+    let key_sequence = parse_key_sequence(&quoted[..end]);
+
+    let Some(value) = quoted[end + 1..].trim_start().strip_prefix(':') else {
+        return Err(ConfigError::MissingColonSeparator(binding.to_owned()));
+    };
+
+    // N.B. A quoted command is taken as written, without processing any escapes.
+    let value = value.trim_start();
+    let command = if let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let text = &value[1..];
+        let Some(end) = find_closing_quote(text, quote) else {
+            return Err(ConfigError::UnterminatedShellCommandBinding(
+                binding.to_owned(),
+                quote,
+            ));
+        };
+        &text[..end]
+    } else {
+        value
+    };
+
+    Ok((key_sequence, command.to_owned()))
+}
+
+/// Finds the index of the first unescaped instance of the given quote character.
+fn find_closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Splits off the first whitespace-delimited word of the given string.
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    (&s[..end], s[end..].trim_start())
+}
+
+/// Returns the remainder of the line if it starts with the given keyword.
+fn strip_keyword<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let (word, remaining) = split_word(line);
+    word.eq_ignore_ascii_case(keyword).then_some(remaining)
+}
+
+/// Returns the value tested by a conditional of the form `name=value`.
+fn strip_test_name<'a>(condition: &'a str, name: &str) -> Option<&'a str> {
+    let (left, right) = condition.split_once('=')?;
+    if left.trim().eq_ignore_ascii_case(name) && !right.starts_with('=') {
+        Some(right.trim())
+    } else {
+        None
+    }
+}
+
+fn parse_version(s: &str) -> (u32, u32) {
+    let (major, minor) = s.split_once('.').unwrap_or((s, "0"));
+    (
+        major.trim().parse().unwrap_or_default(),
+        minor.trim().parse().unwrap_or_default(),
+    )
+}
+
+fn parse_leading_int(s: &str) -> i64 {
+    let s = s.trim();
+    let end = s
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+        .map_or(s.len(), |(i, _)| i);
+    s[..end].parse().unwrap_or_default()
+}
+
+fn resolve_include_path(including_file: &Path, include_path: &Path) -> PathBuf {
+    if let Ok(stripped) = include_path.strip_prefix("~") {
+        if let Some(home) = std::env::var_os("HOME") {
+            return Path::new(&home).join(stripped);
+        }
+    }
+
+    if include_path.is_relative() {
+        if let Some(dir) = including_file.parent() {
+            return dir.join(include_path);
+        }
+    }
+
+    include_path.to_owned()
+}
+
+fn is_boolean_variable(name: &str) -> bool {
+    DEFAULT_BOOLEAN_VARIABLES.iter().any(|(n, _)| *n == name)
+}
+
+const fn bool_to_str(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_sequence() {
+        assert_eq!(parse_key_sequence(r"\C-x\C-e"), b"\x18\x05");
+        assert_eq!(parse_key_sequence(r"\M-f"), b"\x1bf");
+        assert_eq!(parse_key_sequence(r"\e[A"), b"\x1b[A");
+        assert_eq!(parse_key_sequence(r"\C-?"), b"\x7f");
+        assert_eq!(parse_key_sequence(r"\M-\C-h"), b"\x1b\x08");
+        assert_eq!(parse_key_sequence(r"a\\b\x41\101"), b"a\\bAA");
+    }
+
+    #[test]
+    fn test_parse_key_name() {
+        assert_eq!(parse_key_name("Control-u"), b"\x15");
+        assert_eq!(parse_key_name("C-t"), b"\x14");
+        assert_eq!(parse_key_name("Meta-Rubout"), b"\x1b\x7f");
+        assert_eq!(parse_key_name("TAB"), b"\t");
+        assert_eq!(parse_key_name("x"), b"x");
+    }
+
+    #[test]
+    fn test_format_key_sequence() {
+        assert_eq!(format_key_sequence(b"\x18\x05"), r"\C-x\C-e");
+        assert_eq!(format_key_sequence(b"\x1b\x1b"), r"\M-\e");
+        assert_eq!(format_key_sequence(b"\x1b[A"), r"\M-[A");
+        assert_eq!(format_key_sequence(b"\x1c\x7f"), r"\C-\\\C-?");
+        assert_eq!(format_macro("\x01\x1bx\"\\"), r#"\C-a\ex\"\\"#);
+    }
+
+    #[test]
+    fn test_parse_binding_lines() {
+        let mut config = Config::default();
+        let mut context = ParseContext::new(KeyMap::Emacs, "xterm-256color");
+
+        for line in [
+            r#""\C-x\C-e": edit-and-execute-command"#,
+            "Control-t: forward-char",
+            r#""\C-xa": "hello\n""#,
+            "# comment",
+            "set completion-ignore-case on",
+            "$if mode=vi",
+            r#""\C-xb": backward-char"#,
+            "$else",
+            r#""\C-xc": backward-char"#,
+            "$endif",
+            "$if term=xterm",
+            r#""\C-xd": backward-char"#,
+            "$endif",
+        ] {
+            assert!(config.parse_line(line, &mut context).unwrap().is_none());
+        }
+
+        let bindings = config.bindings(KeyMap::Emacs);
+        assert_eq!(
+            bindings.get(b"\x18\x05".as_slice()),
+            Some(&KeyAction::Function("edit-and-execute-command".to_owned()))
+        );
+        assert_eq!(
+            bindings.get(b"\x14".as_slice()),
+            Some(&KeyAction::Function("forward-char".to_owned()))
+        );
+        assert_eq!(
+            bindings.get(b"\x18a".as_slice()),
+            Some(&KeyAction::Macro("hello\n".to_owned()))
+        );
+        assert!(!bindings.contains_key(b"\x18b".as_slice()));
+        assert!(bindings.contains_key(b"\x18c".as_slice()));
+        assert!(bindings.contains_key(b"\x18d".as_slice()));
+        assert!(config.is_variable_on("completion-ignore-case"));
+    }
+
+    #[test]
+    fn test_parse_shell_command_binding() {
+        assert_eq!(
+            parse_shell_command_binding(r#""\C-r": __fzf_history"#).unwrap(),
+            (b"\x12".to_vec(), "__fzf_history".to_owned())
+        );
+        assert_eq!(
+            parse_shell_command_binding(r#" "\C-t" : "echo \"a\nb\"""#).unwrap(),
+            (b"\x14".to_vec(), r#"echo \"a\nb\""#.to_owned())
+        );
+        assert!(matches!(
+            parse_shell_command_binding(r"\C-t: foo"),
+            Err(ConfigError::UnquotedShellCommandKeySequence(_))
+        ));
+        assert!(matches!(
+            parse_shell_command_binding(r#""\C-t" foo"#),
+            Err(ConfigError::MissingColonSeparator(_))
+        ));
+        assert!(matches!(
+            parse_shell_command_binding(r#""\C-t": 'foo"#),
+            Err(ConfigError::UnterminatedShellCommandBinding(_, '\''))
+        ));
+    }
+
+    #[test]
+    fn test_mode_string() {
+        let mut config = Config::default();
+        assert_eq!(config.mode_string(KeyMap::ViInsert), None);
+
+        config.set_variable("show-mode-in-prompt", "on").unwrap();
+        config
+            .set_variable("vi-cmd-mode-string", r"\1\e[1m\2CMD\1\e[0m\2")
+            .unwrap();
+        assert_eq!(config.mode_string(KeyMap::Emacs).as_deref(), Some("@"));
+        assert_eq!(
+            config.mode_string(KeyMap::ViInsert).as_deref(),
+            Some("(ins)")
+        );
+        assert_eq!(
+            config.mode_string(KeyMap::ViCommand).as_deref(),
+            Some("\x1b[1mCMD\x1b[0m")
+        );
+    }
+
+    #[test]
+    fn test_unbind() {
+        let mut config = Config::default();
+
+        config.unbind_function(KeyMap::Emacs, "beginning-of-line");
+        assert!(config
+            .key_sequences_for_function(KeyMap::Emacs, "beginning-of-line")
+            .is_empty());
+
+        config.unbind(KeyMap::EmacsCtlx, b"\x05");
+        assert!(config
+            .key_sequences_for_function(KeyMap::Emacs, "edit-and-execute-command")
+            .is_empty());
+    }
+}
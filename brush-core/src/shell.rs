@@ -1,5 +1,5 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt::Write as _;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -14,8 +14,8 @@ use crate::options::RuntimeOptions;
 use crate::sys::fs::PathExt;
 use crate::variables::{self, ShellValue, ShellVariable};
 use crate::{
-    builtins, commands, completion, env, error, expansion, functions, jobs, keywords, openfiles,
-    patterns, prompt, sys::users, traps,
+    builtins, commands, completion, editor, env, error, expansion, functions, histexpansion,
+    history, jobs, keywords, openfiles, patterns, prompt, readline, sys::users, traps,
 };
 use crate::{pathcache, sys, trace_categories};
 
@@ -26,6 +26,8 @@ const BASH_BUILD: u32 = 1;
 const BASH_RELEASE: &str = "release";
 const BASH_MACHINE: &str = "unknown";
 
+const DEFAULT_HISTORY_SIZE: &str = "500";
+
 /// Represents an instance of a shell.
 pub struct Shell {
     //
@@ -34,6 +36,9 @@ pub struct Shell {
     pub traps: traps::TrapHandlerConfig,
     /// Manages files opened and accessible via redirection operators.
     open_files: openfiles::OpenFiles,
+    /// Incremented whenever files are added to `open_files` outside of any redirection
+    /// (i.e., via `exec` or for a coprocess).
+    open_files_version: u64,
     /// The current working directory.
     pub working_dir: PathBuf,
     /// The shell environment, containing shell variables.
@@ -45,7 +50,7 @@ pub struct Shell {
     /// State of managed jobs.
     pub jobs: jobs::JobManager,
     /// Shell aliases.
-    pub aliases: HashMap<String, String>,
+    pub aliases: Arc<BTreeMap<String, String>>,
 
     //
     // Additional state
@@ -58,6 +63,18 @@ pub struct Shell {
     /// Clone depth from the original ancestor shell.
     depth: usize,
 
+    /// Depth of nested contexts (e.g., `if` conditions) in which failing commands neither
+    /// trigger the `ERR` trap nor cause the shell to exit under `errexit`.
+    ignore_failures_depth: usize,
+
+    /// Whether the shell's jobs have been shown to the user (by `jobs`, or by a warning
+    /// when trying to exit) since the last other command; if so, the shell may exit
+    /// without warning about them again.
+    pub(crate) jobs_acknowledged: bool,
+
+    /// Whether the user's logout files have been sourced.
+    logout_files_sourced: bool,
+
     /// Shell name (a.k.a. $0)
     pub shell_name: Option<String>,
 
@@ -67,11 +84,8 @@ pub struct Shell {
     /// Detailed display string for the shell
     pub shell_product_display_str: Option<String>,
 
-    /// Script call stack.
-    script_call_stack: VecDeque<(ScriptCallType, String)>,
-
-    /// Function call stack.
-    function_call_stack: VecDeque<FunctionCall>,
+    /// Stack of active function calls and script invocations, innermost first.
+    call_stack: VecDeque<CallFrame>,
 
     /// Directory stack used by pushd et al.
     pub directory_stack: Vec<PathBuf>,
@@ -79,6 +93,13 @@ pub struct Shell {
     /// Current line number being processed.
     current_line_number: u32,
 
+    /// Text of the simple command currently being executed, reflected in `BASH_COMMAND`.
+    current_command: String,
+
+    /// Positional parameters reflected at the bottom of `BASH_ARGV` and `BASH_ARGC` outside
+    /// of extended debugging mode; captured when they're first referenced.
+    top_level_bash_argv: std::sync::OnceLock<Vec<String>>,
+
     /// Completion configuration.
     pub completion_config: completion::Config,
 
@@ -88,6 +109,21 @@ pub struct Shell {
     /// Shell program location cache.
     pub program_location_cache: pathcache::PathCache,
 
+    /// Command history.
+    history: Arc<history::History>,
+
+    /// Line editor made available by the interactive layer, if any.
+    pub line_editor: Option<Arc<dyn editor::LineEditor>>,
+
+    /// Line editing configuration (key bindings and variables).
+    pub readline_config: Arc<readline::Config>,
+
+    /// State carried over between history expansions.
+    history_expansion_state: histexpansion::ExpansionState,
+
+    /// Time at which the shell was started.
+    start_time: std::time::SystemTime,
+
     /// Last "SECONDS" captured time.
     last_stopwatch_time: std::time::SystemTime,
 
@@ -98,8 +134,9 @@ pub struct Shell {
 impl Clone for Shell {
     fn clone(&self) -> Self {
         Self {
-            traps: self.traps.clone(),
+            traps: self.traps.clone_for_subshell(&self.options),
             open_files: self.open_files.clone(),
+            open_files_version: self.open_files_version,
             working_dir: self.working_dir.clone(),
             env: self.env.clone(),
             funcs: self.funcs.clone(),
@@ -111,20 +148,36 @@ impl Clone for Shell {
             positional_parameters: self.positional_parameters.clone(),
             shell_name: self.shell_name.clone(),
             shell_product_display_str: self.shell_product_display_str.clone(),
-            function_call_stack: self.function_call_stack.clone(),
-            script_call_stack: self.script_call_stack.clone(),
+            call_stack: self.call_stack.clone(),
             directory_stack: self.directory_stack.clone(),
             current_line_number: self.current_line_number,
+            current_command: self.current_command.clone(),
+            top_level_bash_argv: self.top_level_bash_argv.clone(),
             completion_config: self.completion_config.clone(),
             builtins: self.builtins.clone(),
             program_location_cache: self.program_location_cache.clone(),
+            history: self.history.clone(),
+            line_editor: self.line_editor.clone(),
+            readline_config: self.readline_config.clone(),
+            history_expansion_state: self.history_expansion_state.clone(),
+            start_time: self.start_time,
             last_stopwatch_time: self.last_stopwatch_time,
             last_stopwatch_offset: self.last_stopwatch_offset,
             depth: self.depth + 1,
+            ignore_failures_depth: self.ignore_failures_depth,
+            jobs_acknowledged: false,
+            logout_files_sourced: self.logout_files_sourced,
         }
     }
 }
 
+impl Drop for Shell {
+    fn drop(&mut self) {
+        // Subshells run in-process, so put back any signal dispositions they changed.
+        let _ = self.traps.restore_inherited_dispositions();
+    }
+}
+
 impl AsRef<Shell> for Shell {
     fn as_ref(&self) -> &Shell {
         self
@@ -196,6 +249,57 @@ pub struct FunctionCall {
     function_name: String,
     /// The definition of the invoked function.
     function_definition: Arc<brush_parser::ast::FunctionDefinition>,
+    /// Trap handlers of the caller that were set aside for the duration of the call.
+    saved_trap_handlers: Vec<(traps::TrapSignal, String)>,
+}
+
+/// Represents a function call or script invocation on the shell's call stack.
+#[derive(Clone, Debug)]
+pub(crate) struct CallFrame {
+    /// The function or script invoked.
+    call: Call,
+    /// Number of the line being executed by the caller when the call was made.
+    call_line: u32,
+    /// Arguments that the call contributes to `BASH_ARGV`, if any.
+    argv: Option<Vec<String>>,
+    /// Positional parameters of the shell's top level, recorded for `BASH_ARGV` when this is
+    /// the outermost call and the shell isn't running a script.
+    top_level_argv: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug)]
+enum Call {
+    Function(FunctionCall),
+    Script(ScriptCallType, String),
+}
+
+impl CallFrame {
+    /// Returns the name reported for the call in `FUNCNAME`.
+    pub(crate) fn name(&self) -> &str {
+        match &self.call {
+            Call::Function(call) => call.function_name.as_str(),
+            Call::Script(ScriptCallType::Sourced, _) => "source",
+            Call::Script(ScriptCallType::Executed, _) => "main",
+        }
+    }
+
+    /// Returns the file reported for the call in `BASH_SOURCE`; for a function call, this is
+    /// the file in which the function was defined.
+    pub(crate) fn source(&self) -> &str {
+        match &self.call {
+            Call::Function(call) => call.function_definition.source.as_str(),
+            Call::Script(_, path) => path.as_str(),
+        }
+    }
+
+    /// Returns the number of the line from which the call was made.
+    pub(crate) const fn call_line(&self) -> u32 {
+        self.call_line
+    }
+
+    const fn is_function(&self) -> bool {
+        matches!(self.call, Call::Function(_))
+    }
 }
 
 impl Shell {
@@ -209,32 +313,44 @@ impl Shell {
         let mut shell = Shell {
             traps: traps::TrapHandlerConfig::default(),
             open_files: openfiles::OpenFiles::default(),
+            open_files_version: 0,
             working_dir: std::env::current_dir()?,
             env: env::ShellEnvironment::new(),
             funcs: functions::FunctionEnv::default(),
             options: RuntimeOptions::defaults_from(options),
             jobs: jobs::JobManager::new(),
-            aliases: HashMap::default(),
+            aliases: Arc::default(),
             last_exit_status: 0,
             last_pipeline_statuses: vec![0],
             positional_parameters: vec![],
             shell_name: options.shell_name.clone(),
             shell_product_display_str: options.shell_product_display_str.clone(),
-            function_call_stack: VecDeque::new(),
-            script_call_stack: VecDeque::new(),
+            call_stack: VecDeque::new(),
             directory_stack: vec![],
             current_line_number: 0,
+            current_command: String::new(),
+            top_level_bash_argv: std::sync::OnceLock::new(),
             completion_config: completion::Config::default(),
             builtins: builtins::get_default_builtins(options),
             program_location_cache: pathcache::PathCache::default(),
+            history: Arc::new(history::History::default()),
+            line_editor: None,
+            readline_config: Arc::new(readline::Config::default()),
+            history_expansion_state: histexpansion::ExpansionState::default(),
+            start_time: std::time::SystemTime::now(),
             last_stopwatch_time: std::time::SystemTime::now(),
             last_stopwatch_offset: 0,
             depth: 0,
+            ignore_failures_depth: 0,
+            jobs_acknowledged: false,
+            logout_files_sourced: false,
         };
 
-        // TODO: Without this a script that sets extglob will fail because we
-        // parse the entire script with the same settings.
-        shell.options.extended_globbing = true;
+        // Interactive shells catch SIGHUP, so they can pass it along to their jobs
+        // before exiting.
+        if shell.options.interactive {
+            sys::signal::catch_hangup()?;
+        }
 
         // Initialize environment.
         shell.initialize_vars(options)?;
@@ -242,6 +358,17 @@ impl Shell {
         // Load profiles/configuration.
         shell.load_config(options).await?;
 
+        // Load history, now that configuration has had a chance to customize it.
+        if shell.options.interactive && shell.options.enable_command_history {
+            shell.load_history();
+        }
+
+        // Load the line editor's init file, unless configuration already caused it to be
+        // loaded.
+        if shell.options.interactive {
+            shell.load_readline_init_file();
+        }
+
         Ok(shell)
     }
 
@@ -298,9 +425,23 @@ impl Shell {
             }),
         )?;
 
-        // TODO(vars): when extdebug is enabled, BASH_ARGC and BASH_ARGV are set to valid values
-        // TODO(vars): implement BASH_ARGC
-        // TODO(vars): implement BASH_ARGV
+        // BASH_ARGC
+        self.env.set_global(
+            "BASH_ARGC",
+            ShellVariable::new(ShellValue::Dynamic {
+                getter: |shell| shell.get_bash_argc_value(),
+                setter: |_| (),
+            }),
+        )?;
+
+        // BASH_ARGV
+        self.env.set_global(
+            "BASH_ARGV",
+            ShellVariable::new(ShellValue::Dynamic {
+                getter: |shell| shell.get_bash_argv_value(),
+                setter: |_| (),
+            }),
+        )?;
 
         // BASH_ARGV0
         self.env.set_global(
@@ -324,9 +465,23 @@ impl Shell {
             }),
         )?;
 
-        // TODO(vars): implement BASH_COMMAND
-        // TODO(vars): implement BASH_EXECUTIION_STRING
-        // TODO(vars): implement BASH_LINENO
+        // BASH_COMMAND
+        self.env.set_global(
+            "BASH_COMMAND",
+            ShellVariable::new(ShellValue::Dynamic {
+                getter: |shell| shell.current_command.clone().into(),
+                setter: |_| (),
+            }),
+        )?;
+
+        // BASH_LINENO
+        self.env.set_global(
+            "BASH_LINENO",
+            ShellVariable::new(ShellValue::Dynamic {
+                getter: |shell| shell.get_bash_lineno_value(),
+                setter: |_| (),
+            }),
+        )?;
 
         // BASH_SOURCE
         self.env.set_global(
@@ -471,6 +626,16 @@ impl Shell {
             }
         }
 
+        // HISTSIZE and HISTFILESIZE (if not already set)
+        if options.interactive {
+            for name in ["HISTSIZE", "HISTFILESIZE"] {
+                if !self.env.is_set(name) {
+                    self.env
+                        .set_global(name, ShellVariable::new(DEFAULT_HISTORY_SIZE.into()))?;
+                }
+            }
+        }
+
         // HOSTNAME
         self.env.set_global(
             "HOSTNAME",
@@ -744,6 +909,51 @@ impl Shell {
         Ok(())
     }
 
+    /// Sources the user's logout files (`~/.bash_logout`, followed by `~/.brush_logout`),
+    /// if this is a login shell that is exiting. Only has an effect the first time it's
+    /// invoked, and never in a subshell. The shell's last exit status is preserved, unless
+    /// one of the files exits the shell; callers should check the returned result for that.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub async fn source_logout_files(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let mut result = ExecutionResult::new(self.last_exit_status);
+
+        if !self.options.login_shell || self.depth > 0 || self.logout_files_sourced {
+            return Ok(result);
+        }
+
+        self.logout_files_sourced = true;
+
+        let prior_exit_status = self.last_exit_status;
+
+        if let Some(home_path) = self.get_home_dir() {
+            for file_name in [".bash_logout", ".brush_logout"] {
+                let path = home_path.join(file_name);
+                if !path.exists() {
+                    tracing::debug!("skipping non-existent file: {}", path.display());
+                    continue;
+                }
+
+                result = self
+                    .source_script(path.as_path(), std::iter::empty::<String>(), params)
+                    .await?;
+                if result.exit_shell {
+                    self.last_exit_status = result.exit_code;
+                    return Ok(result);
+                }
+            }
+        }
+
+        self.last_exit_status = prior_exit_status;
+
+        Ok(result)
+    }
+
     async fn source_if_exists(
         &mut self,
         path: &Path,
@@ -805,6 +1015,7 @@ impl Shell {
 
         let source_info = brush_parser::SourceInfo {
             source: path.to_string_lossy().to_string(),
+            ..Default::default()
         };
 
         self.source_file(opened_file, &source_info, args, params, call_type)
@@ -833,33 +1044,73 @@ impl Shell {
             brush_parser::Parser::new(&mut reader, &self.parser_options(), source_info);
 
         tracing::debug!(target: trace_categories::PARSE, "Parsing sourced file: {}", source_info.source);
-        let parse_result = parser.parse();
 
-        let mut other_positional_parameters = args.map(|s| s.as_ref().to_owned()).collect();
+        let mut other_positional_parameters: Vec<_> = args.map(|s| s.as_ref().to_owned()).collect();
         let mut other_shell_name = Some(source_info.source.clone());
 
+        // A script sourced without arguments shares its caller's positional parameters.
+        let replace_positional_parameters = matches!(call_type, ScriptCallType::Executed)
+            || !other_positional_parameters.is_empty();
+
         // TODO: Find a cleaner way to change args.
         std::mem::swap(&mut self.shell_name, &mut other_shell_name);
-        std::mem::swap(
-            &mut self.positional_parameters,
-            &mut other_positional_parameters,
+        if replace_positional_parameters {
+            std::mem::swap(
+                &mut self.positional_parameters,
+                &mut other_positional_parameters,
+            );
+        }
+
+        // The script's arguments show up in BASH_ARGV when executing a script or, in
+        // extended debugging mode, when sourcing one; a script sourced without arguments
+        // contributes its own path instead.
+        let argv = if matches!(call_type, ScriptCallType::Executed) {
+            Some(self.positional_parameters.clone())
+        } else if !replace_positional_parameters {
+            Some(vec![source_info.source.clone()])
+        } else if self.options.enable_debugger {
+            Some(self.positional_parameters.clone())
+        } else {
+            None
+        };
+
+        let call_line = if matches!(call_type, ScriptCallType::Executed) {
+            0
+        } else {
+            self.current_line_number
+        };
+
+        self.push_call_frame(
+            Call::Script(call_type.clone(), source_info.source.clone()),
+            call_line,
+            argv,
         );
 
-        self.script_call_stack
-            .push_front((call_type.clone(), source_info.source.clone()));
+        let prior_line_number = self.current_line_number;
 
-        let result = self
-            .run_parsed_result(parse_result, source_info, params)
+        let mut result = self
+            .run_parsed_commands(&mut parser, source_info, params)
             .await;
 
-        self.script_call_stack.pop_front();
+        // Sourced scripts trigger the RETURN trap as they complete.
+        if matches!(call_type, ScriptCallType::Sourced) {
+            result = match result {
+                Ok(result) => self.run_return_trap(result, params).await,
+                Err(e) => Err(e),
+            };
+        }
+
+        self.call_stack.pop_front();
 
         // Restore.
+        self.current_line_number = prior_line_number;
         std::mem::swap(&mut self.shell_name, &mut other_shell_name);
-        std::mem::swap(
-            &mut self.positional_parameters,
-            &mut other_positional_parameters,
-        );
+        if replace_positional_parameters {
+            std::mem::swap(
+                &mut self.positional_parameters,
+                &mut other_positional_parameters,
+            );
+        }
 
         result
     }
@@ -909,6 +1160,8 @@ impl Shell {
     }
 
     /// Executes the given string as a shell program, returning the resulting exit status.
+    /// The string is taken to be the next input to the shell; its lines are numbered
+    /// following the last line of input executed.
     ///
     /// # Arguments
     ///
@@ -919,14 +1172,64 @@ impl Shell {
         command: String,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        // TODO: Actually track line numbers; this is something of a hack, assuming each time
-        // this function is invoked we are on the next line of the input. For one thing,
-        // each string we run could be multiple lines.
-        self.current_line_number += 1;
+        let start_line = self.current_line_number.saturating_add(1);
+        let line_count = u32::try_from(command.lines().count()).unwrap_or(u32::MAX);
+
+        // Commands are parsed one at a time, so that earlier ones (e.g., `shopt -s extglob`)
+        // can affect how later ones are parsed.
+        let source_info = brush_parser::SourceInfo {
+            source: String::from("main"),
+            start_line: i32::try_from(start_line).unwrap_or(i32::MAX),
+        };
+        let mut reader = std::io::Cursor::new(command);
+        let mut parser =
+            brush_parser::Parser::new(&mut reader, &self.parser_options(), &source_info);
+
+        let result = self
+            .run_parsed_commands(&mut parser, &source_info, params)
+            .await;
+
+        self.current_line_number = start_line.saturating_add(line_count).saturating_sub(1);
+
+        result
+    }
+
+    /// Executes the given string as a shell program embedded in the command currently being
+    /// executed (e.g., via `eval` or a command substitution), returning the resulting exit
+    /// status. The string's first line is taken to be the line currently being executed.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to execute.
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_embedded_string(
+        &mut self,
+        command: String,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let line_number = self.current_line_number;
+
+        let result = self
+            .run_string_at_line(command, line_number.max(1), params)
+            .await;
+
+        self.current_line_number = line_number;
+
+        result
+    }
+
+    async fn run_string_at_line(
+        &mut self,
+        command: String,
+        start_line: u32,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let start_line = i32::try_from(start_line).unwrap_or(i32::MAX);
+        let parse_result = parse_string_impl(command, self.parser_options(), start_line);
 
-        let parse_result = self.parse_string(command);
         let source_info = brush_parser::SourceInfo {
             source: String::from("main"),
+            start_line,
         };
         self.run_parsed_result(parse_result, &source_info, params)
             .await
@@ -942,7 +1245,7 @@ impl Shell {
         &self,
         s: String,
     ) -> Result<brush_parser::ast::Program, brush_parser::ParseError> {
-        parse_string_impl(s, self.parser_options())
+        parse_string_impl(s, self.parser_options(), 1)
     }
 
     /// Applies basic shell expansion to the provided string.
@@ -978,6 +1281,7 @@ impl Shell {
     pub fn default_exec_params(&self) -> ExecutionParameters {
         ExecutionParameters {
             open_files: self.open_files.clone(),
+            open_files_version: self.open_files_version,
             ..Default::default()
         }
     }
@@ -998,6 +1302,40 @@ impl Shell {
             .await
     }
 
+    /// Reads, parses, and executes commands from the given parser one at a time, until
+    /// the input is exhausted or a command requests that execution stop. Each command
+    /// is parsed with the shell's options as they stand after executing the previous one.
+    async fn run_parsed_commands<R: std::io::BufRead>(
+        &mut self,
+        parser: &mut brush_parser::Parser<R>,
+        source_info: &brush_parser::SourceInfo,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let mut result = ExecutionResult::success();
+
+        loop {
+            let parse_result = match parser.parse_next(&self.parser_options()) {
+                Ok(Some(prog)) => Ok(prog),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+
+            // A syntax error stops execution of the remainder of the input.
+            let stop = parse_result.is_err();
+
+            result = self
+                .run_parsed_result(parse_result, source_info, params)
+                .await?;
+
+            if stop || result.exit_shell || result.return_from_function_or_script {
+                break;
+            }
+        }
+
+        self.last_exit_status = result.exit_code;
+        Ok(result)
+    }
+
     async fn run_parsed_result(
         &mut self,
         parse_result: Result<brush_parser::ast::Program, brush_parser::ParseError>,
@@ -1074,6 +1412,202 @@ impl Shell {
         program.execute(self, params).await
     }
 
+    /// Runs the shell's `EXIT` trap handler, if one is registered. Expected to be invoked
+    /// as the shell (or subshell) is terminating. The handler observes the shell's last
+    /// exit status in `$?`; that status is preserved unless the handler itself exits.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub async fn run_exit_trap(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<(), error::Error> {
+        // Take the handler out of the table so it can only ever run once.
+        let Some(handler) = self.traps.handlers.remove(&traps::TrapSignal::Exit) else {
+            return Ok(());
+        };
+
+        self.run_async_trap_handler(handler, params).await?;
+
+        Ok(())
+    }
+
+    /// Runs the shell's `ERR` trap handler, if one is registered and we're not already
+    /// running a trap handler. Expected to be invoked after a command fails in a context
+    /// where its failure is checked. Returns the result of running the handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_err_trap(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        if self.traps.handler_depth > 0 {
+            return Ok(ExecutionResult::new(self.last_exit_status));
+        }
+
+        let Some(handler) = self.traps.handlers.get(&traps::TrapSignal::Err).cloned() else {
+            return Ok(ExecutionResult::new(self.last_exit_status));
+        };
+
+        self.run_trap_handler(handler, params).await
+    }
+
+    /// Runs the shell's `DEBUG` trap handler, if one is registered and we're not already
+    /// running a trap handler. Expected to be invoked before a command is executed.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_debug_trap(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<(), error::Error> {
+        if self.traps.handler_depth > 0 {
+            return Ok(());
+        }
+
+        let Some(handler) = self.traps.handlers.get(&traps::TrapSignal::Debug).cloned() else {
+            return Ok(());
+        };
+
+        // TODO: Confirm whether trap handlers should be executed in the same process
+        // group.
+        let handler_params = ExecutionParameters {
+            open_files: params.open_files.clone(),
+            process_group_policy: interp::ProcessGroupPolicy::SameProcessGroup,
+            open_files_version: params.open_files_version,
+        };
+
+        self.run_trap_handler(handler, &handler_params).await?;
+
+        Ok(())
+    }
+
+    /// Runs the shell's `RETURN` trap handler, if one is registered and we're not already
+    /// running a trap handler. Expected to be invoked as a function or sourced script
+    /// completes with the given result; returns the result to report for it, which only
+    /// differs from the given one if the handler exits the shell.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The result of the function or sourced script.
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_return_trap(
+        &mut self,
+        result: ExecutionResult,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        if result.exit_shell || self.traps.handler_depth > 0 {
+            return Ok(result);
+        }
+
+        let Some(handler) = self.traps.handlers.get(&traps::TrapSignal::Return).cloned() else {
+            return Ok(result);
+        };
+
+        let handler_result = self.run_trap_handler(handler, params).await?;
+        if handler_result.exit_shell {
+            Ok(handler_result)
+        } else {
+            Ok(result)
+        }
+    }
+
+    /// Runs the trap handlers for any caught signals that have been received since this
+    /// was last called. Expected to be invoked between commands. Returns the result of the
+    /// last handler run, which callers should check for a request to exit the shell.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub async fn run_pending_traps(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let mut result = ExecutionResult::new(self.last_exit_status);
+
+        // Don't interrupt a running handler; we'll get to any new signals after it's done.
+        if self.traps.handler_depth > 0 {
+            return Ok(result);
+        }
+
+        for signal in self.traps.caught_signals() {
+            if !sys::signal::take_pending_signal(signal) {
+                continue;
+            }
+
+            if let Some(handler) = self.traps.handlers.get(&signal).cloned() {
+                result = self.run_async_trap_handler(handler, params).await?;
+                if result.exit_shell {
+                    break;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Runs the given handler for an asynchronously delivered trap (i.e., a signal or `EXIT`).
+    /// As in bash, the handler's lines are numbered from the start of the handler rather
+    /// than from the line being executed when the trap fired.
+    async fn run_async_trap_handler(
+        &mut self,
+        handler: String,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let line_number = self.current_line_number;
+        self.current_line_number = 0;
+
+        let result = self.run_trap_handler(handler, params).await;
+
+        self.current_line_number = line_number;
+
+        result
+    }
+
+    /// Runs the given trap handler command string. The handler observes the shell's last
+    /// exit status in `$?`; that status is preserved unless the handler exits the shell.
+    async fn run_trap_handler(
+        &mut self,
+        handler: String,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let prior_exit_status = self.last_exit_status;
+
+        self.traps.handler_depth += 1;
+        let result = self.run_embedded_string(handler, params).await;
+        self.traps.handler_depth -= 1;
+
+        let result = result?;
+        if !result.exit_shell {
+            self.last_exit_status = prior_exit_status;
+        }
+
+        Ok(result)
+    }
+
+    /// Updates the shell's internal tracking state to reflect that it is entering a
+    /// context (e.g., an `if` condition) in which command failures are not checked, i.e.,
+    /// they neither trigger the `ERR` trap nor cause the shell to exit under `errexit`.
+    pub(crate) fn enter_ignore_failures_context(&mut self) {
+        self.ignore_failures_depth += 1;
+    }
+
+    /// Updates the shell's internal tracking state to reflect that it has left the
+    /// innermost context in which command failures are not checked.
+    pub(crate) fn leave_ignore_failures_context(&mut self) {
+        self.ignore_failures_depth -= 1;
+    }
+
+    /// Returns whether the failure of a command executed now should be checked, i.e.,
+    /// whether it may trigger the `ERR` trap or cause the shell to exit under `errexit`.
+    pub(crate) const fn failures_are_checked(&self) -> bool {
+        self.ignore_failures_depth == 0
+    }
+
     fn default_prompt(&self) -> &'static str {
         if self.options.sh_mode {
             "$ "
@@ -1133,6 +1667,11 @@ impl Shell {
         self.last_exit_status
     }
 
+    /// Returns the time at which the shell was started.
+    pub fn start_time(&self) -> std::time::SystemTime {
+        self.start_time
+    }
+
     fn parameter_or_default<'a>(&'a self, name: &str, default: &'a str) -> Cow<'a, str> {
         self.get_env_str(name).unwrap_or(default.into())
     }
@@ -1145,19 +1684,51 @@ impl Shell {
             posix_mode: self.options.posix_mode,
             sh_mode: self.options.sh_mode,
             tilde_expansion: true,
+            aliases: (self.options.expand_aliases && !self.aliases.is_empty())
+                .then(|| self.aliases.clone()),
         }
     }
 
     /// Returns whether or not the shell is actively executing in a sourced script.
     pub(crate) fn in_sourced_script(&self) -> bool {
-        self.script_call_stack
-            .front()
-            .is_some_and(|(ty, _)| matches!(ty, ScriptCallType::Sourced))
+        self.call_stack
+            .iter()
+            .find_map(|frame| match &frame.call {
+                Call::Script(call_type, _) => Some(call_type),
+                Call::Function(_) => None,
+            })
+            .is_some_and(|call_type| matches!(call_type, ScriptCallType::Sourced))
     }
 
     /// Returns whether or not the shell is actively executing in a shell function.
     pub(crate) fn in_function(&self) -> bool {
-        !self.function_call_stack.is_empty()
+        self.call_stack.iter().any(CallFrame::is_function)
+    }
+
+    /// Returns the shell's stack of active function calls and script invocations,
+    /// innermost first.
+    pub(crate) const fn call_stack(&self) -> &VecDeque<CallFrame> {
+        &self.call_stack
+    }
+
+    fn function_call_depth(&self) -> usize {
+        self.call_stack
+            .iter()
+            .filter(|frame| frame.is_function())
+            .count()
+    }
+
+    fn push_call_frame(&mut self, call: Call, call_line: u32, argv: Option<Vec<String>>) {
+        let top_level_argv = (self.call_stack.is_empty()
+            && !matches!(call, Call::Script(ScriptCallType::Executed, _)))
+        .then(|| self.positional_parameters.clone());
+
+        self.call_stack.push_front(CallFrame {
+            call,
+            call_line,
+            argv,
+            top_level_argv,
+        });
     }
 
     /// Updates the shell's internal tracking state to reflect that a new shell
@@ -1167,27 +1738,65 @@ impl Shell {
     ///
     /// * `name` - The name of the function being entered.
     /// * `function_def` - The definition of the function being entered.
+    /// * `args` - The arguments passed to the function.
     pub(crate) fn enter_function(
         &mut self,
         name: &str,
         function_def: &Arc<brush_parser::ast::FunctionDefinition>,
+        args: &[String],
     ) -> Result<(), error::Error> {
         if let Some(max_call_depth) = self.options.max_function_call_depth {
-            if self.function_call_stack.len() >= max_call_depth {
+            if self.function_call_depth() >= max_call_depth {
                 return Err(error::Error::MaxFunctionCallDepthExceeded);
             }
         }
 
         if tracing::enabled!(target: trace_categories::FUNCTIONS, tracing::Level::DEBUG) {
-            let depth = self.function_call_stack.len();
+            let depth = self.function_call_depth();
             let prefix = repeated_char_str(' ', depth);
             tracing::debug!(target: trace_categories::FUNCTIONS, "Entering func [depth={depth}]: {prefix}{name}");
         }
 
-        self.function_call_stack.push_front(FunctionCall {
-            function_name: name.to_owned(),
-            function_definition: function_def.clone(),
-        });
+        // Functions only inherit the ERR trap when errtrace is enabled, and only inherit the
+        // DEBUG and RETURN traps when functrace is enabled or they have the trace attribute.
+        // Set aside any traps they don't inherit until they return; as in bash, they're
+        // then neither reported by `trap -p` nor cleared by `trap -` within the function.
+        let mut uninherited_traps = vec![];
+        if !self.options.shell_functions_inherit_err_trap {
+            uninherited_traps.push(traps::TrapSignal::Err);
+        }
+        if !self.options.shell_functions_inherit_debug_and_return_traps
+            && !self
+                .funcs
+                .get(name)
+                .is_some_and(|registration| registration.is_trace_enabled())
+        {
+            uninherited_traps.push(traps::TrapSignal::Debug);
+            uninherited_traps.push(traps::TrapSignal::Return);
+        }
+
+        let saved_trap_handlers = uninherited_traps
+            .into_iter()
+            .filter_map(|signal| {
+                self.traps
+                    .handlers
+                    .remove(&signal)
+                    .map(|handler| (signal, handler))
+            })
+            .collect();
+
+        // The function's arguments only show up in BASH_ARGV in extended debugging mode.
+        let argv = self.options.enable_debugger.then(|| args.to_vec());
+
+        self.push_call_frame(
+            Call::Function(FunctionCall {
+                function_name: name.to_owned(),
+                function_definition: function_def.clone(),
+                saved_trap_handlers,
+            }),
+            self.current_line_number,
+            argv,
+        );
         self.env.push_scope(env::EnvironmentScope::Local);
         Ok(())
     }
@@ -1197,55 +1806,466 @@ impl Shell {
     pub(crate) fn leave_function(&mut self) -> Result<(), error::Error> {
         self.env.pop_scope(env::EnvironmentScope::Local)?;
 
-        if let Some(exited_call) = self.function_call_stack.pop_front() {
+        if let Some(CallFrame {
+            call: Call::Function(exited_call),
+            ..
+        }) = self.call_stack.pop_front()
+        {
             if tracing::enabled!(target: trace_categories::FUNCTIONS, tracing::Level::DEBUG) {
-                let depth = self.function_call_stack.len();
+                let depth = self.function_call_depth();
                 let prefix = repeated_char_str(' ', depth);
                 tracing::debug!(target: trace_categories::FUNCTIONS, "Exiting func  [depth={depth}]: {prefix}{}", exited_call.function_name);
             }
+
+            // Restore the caller's traps, except for any the function registered itself;
+            // bash likewise keeps the caller's trap if the function only reset its own.
+            for (signal, handler) in exited_call.saved_trap_handlers {
+                self.traps.handlers.entry(signal).or_insert(handler);
+            }
         }
 
         Ok(())
     }
 
     fn get_funcname_value(&self) -> variables::ShellValue {
-        if self.function_call_stack.is_empty() {
-            ShellValue::Unset(variables::ShellValueUnsetType::IndexedArray)
-        } else {
-            self.function_call_stack
+        // N.B. The call stack is only reflected in FUNCNAME while a function is executing.
+        if self.in_function() {
+            self.call_stack
                 .iter()
-                .map(|s| s.function_name.as_str())
+                .map(CallFrame::name)
                 .collect::<Vec<_>>()
                 .into()
+        } else {
+            ShellValue::Unset(variables::ShellValueUnsetType::IndexedArray)
         }
     }
 
     fn get_bash_source_value(&self) -> variables::ShellValue {
-        if self.function_call_stack.is_empty() {
-            self.script_call_stack
-                .front()
-                .map_or_else(Vec::new, |(_call_type, s)| vec![s.as_ref()])
-                .into()
-        } else {
-            self.function_call_stack
+        self.call_stack
+            .iter()
+            .map(CallFrame::source)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn get_bash_lineno_value(&self) -> variables::ShellValue {
+        ShellValue::indexed_array_from_strings(
+            self.call_stack
                 .iter()
-                .map(|s| s.function_definition.source.as_ref())
-                .collect::<Vec<_>>()
-                .into()
-        }
+                .map(|frame| frame.call_line.to_string()),
+        )
+    }
+
+    /// Returns the argument lists reflected in `BASH_ARGV` and `BASH_ARGC`, innermost first.
+    fn get_bash_argv_lists(&self) -> impl Iterator<Item = &Vec<String>> {
+        let debugging = self.options.enable_debugger;
+
+        let top_level_argv = if debugging {
+            match self.call_stack.back() {
+                Some(frame) => frame.top_level_argv.as_ref(),
+                None => Some(&self.positional_parameters),
+            }
+        } else if self.in_function() {
+            self.top_level_bash_argv.get()
+        } else {
+            // Outside of extended debugging mode, bash only captures the positional
+            // parameters once these are first referenced outside of a function.
+            Some(
+                self.top_level_bash_argv
+                    .get_or_init(|| self.positional_parameters.clone()),
+            )
+        };
+
+        self.call_stack
+            .iter()
+            .filter(move |frame| {
+                debugging || !matches!(frame.call, Call::Script(ScriptCallType::Executed, _))
+            })
+            .filter_map(|frame| frame.argv.as_ref())
+            .chain(top_level_argv)
+    }
+
+    fn get_bash_argv_value(&self) -> variables::ShellValue {
+        ShellValue::indexed_array_from_strings(
+            self.get_bash_argv_lists()
+                .flat_map(|argv| argv.iter().rev().cloned()),
+        )
+    }
+
+    fn get_bash_argc_value(&self) -> variables::ShellValue {
+        ShellValue::indexed_array_from_strings(
+            self.get_bash_argv_lists()
+                .map(|argv| argv.len().to_string()),
+        )
     }
 
     /// Returns the path to the history file used by the shell, if one is set.
     pub fn get_history_file_path(&self) -> Option<PathBuf> {
         self.get_env_str("HISTFILE")
+            .filter(|s| !s.is_empty())
             .map(|s| PathBuf::from(s.into_owned()))
     }
 
+    /// Returns the shell's command history list.
+    pub fn history(&self) -> &history::History {
+        &self.history
+    }
+
+    /// Returns a mutable reference to the shell's command history list.
+    pub(crate) fn history_mut(&mut self) -> &mut history::History {
+        Arc::make_mut(&mut self.history)
+    }
+
+    /// Performs bang-style history expansion (e.g., `!!`, `!$`, `^old^new`) on a line of
+    /// input, using the special characters configured by `histchars`.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to expand.
+    pub fn expand_history(
+        &mut self,
+        line: &str,
+    ) -> Result<histexpansion::HistoryExpansion, error::Error> {
+        let chars = match self.get_env_str("histchars") {
+            Some(value) => histexpansion::HistoryChars::parse(value.as_ref()),
+            None => Some(histexpansion::HistoryChars::default()),
+        };
+
+        // An empty `histchars` disables history expansion.
+        let Some(chars) = chars else {
+            return Ok(histexpansion::HistoryExpansion {
+                line: line.to_owned(),
+                expanded: false,
+                print_only: false,
+            });
+        };
+
+        histexpansion::expand(
+            line,
+            &self.history,
+            chars,
+            self.options.extended_globbing,
+            &mut self.history_expansion_state,
+        )
+        .map_err(error::Error::HistoryExpansionFailed)
+    }
+
+    /// Adds a command line that was read as input to the shell's history list, if command
+    /// history is enabled. Honors the `cmdhist` and `lithist` options for commands spanning
+    /// multiple lines, as well as `HISTCONTROL`, `HISTIGNORE`, and `HISTSIZE`.
+    ///
+    /// # Arguments
+    ///
+    /// * `command_line` - The command line that was read.
+    pub fn add_to_history(&mut self, command_line: &str) -> Result<(), error::Error> {
+        if !self.options.enable_command_history {
+            return Ok(());
+        }
+
+        let command_line = command_line.trim_end_matches('\n');
+        if command_line.trim().is_empty() {
+            return Ok(());
+        }
+
+        if !self.options.save_multiline_cmds_in_history {
+            for line in command_line.lines() {
+                if !line.trim().is_empty() {
+                    self.add_history_entry(line.to_owned())?;
+                }
+            }
+        } else if self.options.embed_newlines_in_multiline_cmds_in_history {
+            self.add_history_entry(command_line.to_owned())?;
+        } else {
+            self.add_history_entry(history::join_command_lines(command_line))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds an entry to the shell's history list, unless `HISTCONTROL` or `HISTIGNORE`
+    /// call for it to be ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to add.
+    pub(crate) fn add_history_entry(&mut self, entry: String) -> Result<(), error::Error> {
+        let mut ignore_space = false;
+        let mut ignore_dups = false;
+        let mut erase_dups = false;
+        if let Some(control) = self.get_env_str("HISTCONTROL") {
+            for value in control.split(':') {
+                match value {
+                    "ignorespace" => ignore_space = true,
+                    "ignoredups" => ignore_dups = true,
+                    "ignoreboth" => {
+                        ignore_space = true;
+                        ignore_dups = true;
+                    }
+                    "erasedups" => erase_dups = true,
+                    _ => (),
+                }
+            }
+        }
+
+        if ignore_space && entry.starts_with([' ', '\t']) {
+            return Ok(());
+        }
+
+        let previous = self.history.last().map(|item| item.command_line.as_str());
+        if ignore_dups && previous == Some(entry.as_str()) {
+            return Ok(());
+        }
+
+        if let Some(ignore_patterns) = self.get_env_str("HISTIGNORE") {
+            for pattern in split_history_ignore_patterns(ignore_patterns.as_ref()) {
+                let ignored = if pattern == "&" {
+                    previous == Some(entry.as_str())
+                } else {
+                    patterns::Pattern::from(pattern)
+                        .set_extended_globbing(self.options.extended_globbing)
+                        .exactly_matches(entry.as_str())?
+                };
+
+                if ignored {
+                    return Ok(());
+                }
+            }
+        }
+
+        if erase_dups {
+            Arc::make_mut(&mut self.history).remove_matching(entry.as_str());
+        }
+
+        Arc::make_mut(&mut self.history).add(history::HistoryItem::new(entry));
+        self.apply_history_size();
+
+        Ok(())
+    }
+
+    /// Trims the shell's history list to the number of items allowed by `HISTSIZE`.
+    pub(crate) fn apply_history_size(&mut self) {
+        if let Some(max_items) = self.get_history_size_limit("HISTSIZE") {
+            Arc::make_mut(&mut self.history).truncate_to(max_items);
+        }
+    }
+
+    /// Returns the limit stored in the given history size variable, if it holds one. An unset,
+    /// non-numeric, or negative value means there is no limit.
+    fn get_history_size_limit(&self, name: &str) -> Option<usize> {
+        self.get_env_str(name)
+            .and_then(|value| value.parse::<i64>().ok())
+            .and_then(|value| usize::try_from(value).ok())
+    }
+
+    /// Returns whether timestamps should be written to history files, which is the case when
+    /// `HISTTIMEFORMAT` is set.
+    pub(crate) fn should_write_history_timestamps(&self) -> bool {
+        self.env.is_set("HISTTIMEFORMAT")
+    }
+
+    /// Reads the history file (if there is one) into the shell's history list.
+    pub fn load_history(&mut self) {
+        if let Some(path) = self.get_history_file_path() {
+            let multiline_items = self.options.embed_newlines_in_multiline_cmds_in_history;
+            if let Err(e) =
+                Arc::make_mut(&mut self.history).read_file(&path, false, multiline_items)
+            {
+                tracing::debug!("couldn't read history file: {e}");
+            }
+            self.apply_history_size();
+        }
+    }
+
+    /// Loads the line editor's init file, if it hasn't already been loaded. The file is
+    /// found via `INPUTRC`, falling back to `~/.inputrc` and then `/etc/inputrc`. Problems
+    /// with the file are reported as warnings. The line editor's configuration is also
+    /// adjusted for the terminal named by `TERM`.
+    pub fn load_readline_init_file(&mut self) {
+        if self.readline_config.init_file_loaded {
+            return;
+        }
+        Arc::make_mut(&mut self.readline_config).init_file_loaded = true;
+
+        let path = if let Some(inputrc) = self.get_env_str("INPUTRC") {
+            self.get_absolute_path(Path::new(inputrc.as_ref()))
+        } else {
+            match self.get_home_dir().map(|home| home.join(".inputrc")) {
+                Some(path) if path.exists() => path,
+                _ => PathBuf::from("/etc/inputrc"),
+            }
+        };
+
+        let term = self.get_env_str("TERM").unwrap_or_default().to_string();
+        Arc::make_mut(&mut self.readline_config).set_terminal(term.as_str());
+
+        if !path.exists() {
+            return;
+        }
+
+        self.update_readline_editing_mode();
+
+        let mut context =
+            readline::ParseContext::new(self.readline_config.current_keymap(), term.as_str());
+
+        match Arc::make_mut(&mut self.readline_config).load_file(&path, &mut context) {
+            Ok(errors) => {
+                for error in errors {
+                    tracing::warn!("{error}");
+                }
+            }
+            Err(e) => tracing::warn!("{e}"),
+        }
+
+        self.apply_readline_editing_mode();
+    }
+
+    /// Updates the line editor's `editing-mode` variable to reflect the shell's `emacs` and
+    /// `vi` options.
+    pub fn update_readline_editing_mode(&mut self) {
+        let mode = if self.options.vi_mode {
+            "vi"
+        } else if self.options.emacs_mode {
+            "emacs"
+        } else {
+            return;
+        };
+
+        if self.readline_config.get_variable("editing-mode") != Some(mode) {
+            let _ = Arc::make_mut(&mut self.readline_config).set_variable("editing-mode", mode);
+        }
+    }
+
+    /// Updates the shell's `emacs` and `vi` options to reflect the line editor's
+    /// `editing-mode` variable (e.g., after it was changed by an init file).
+    pub(crate) fn apply_readline_editing_mode(&mut self) {
+        match self.readline_config.get_variable("editing-mode") {
+            Some("vi") => {
+                self.options.vi_mode = true;
+                self.options.emacs_mode = false;
+            }
+            Some("emacs") if self.options.vi_mode => {
+                self.options.vi_mode = false;
+                self.options.emacs_mode = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Runs a shell command bound to a key sequence (e.g., via `bind -x`). While the command
+    /// runs, the line being edited is available in `READLINE_LINE`, and the cursor position
+    /// (in characters) in `READLINE_POINT`; `READLINE_MARK` is also provided. Changes the
+    /// command makes to these variables are applied to the buffer afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to run.
+    /// * `buffer` - The line editor's input buffer.
+    pub async fn run_key_binding_command(
+        &mut self,
+        command: &str,
+        buffer: &mut editor::EditBuffer,
+    ) -> Result<ExecutionResult, error::Error> {
+        let point = buffer.text[..buffer.cursor].chars().count();
+
+        for (name, value) in [
+            ("READLINE_LINE", buffer.text.clone()),
+            ("READLINE_POINT", point.to_string()),
+            ("READLINE_MARK", "0".to_owned()),
+        ] {
+            self.env.update_or_add(
+                name,
+                variables::ShellValueLiteral::Scalar(value),
+                |_| Ok(()),
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )?;
+        }
+
+        let params = self.default_exec_params();
+        let result = self.run_string(command.to_owned(), &params).await;
+
+        let text = self.get_env_str("READLINE_LINE").map(|s| s.to_string());
+        let point = self
+            .get_env_str("READLINE_POINT")
+            .and_then(|s| s.trim().parse::<i64>().ok());
+
+        for name in ["READLINE_LINE", "READLINE_POINT", "READLINE_MARK"] {
+            self.env.unset(name)?;
+        }
+
+        if let Some(text) = text {
+            buffer.text = text;
+            buffer.cursor = buffer.cursor.min(buffer.text.len());
+        }
+
+        // Out-of-range positions are clamped to the bounds of the line.
+        if let Some(point) = point {
+            let point = usize::try_from(point).unwrap_or(0);
+            buffer.cursor = buffer
+                .text
+                .char_indices()
+                .nth(point)
+                .map_or(buffer.text.len(), |(i, _)| i);
+        } else {
+            while !buffer.text.is_char_boundary(buffer.cursor) {
+                buffer.cursor -= 1;
+            }
+        }
+
+        result
+    }
+
+    /// Saves the shell's history list to the history file (if there is one), appending to the
+    /// file when `histappend` is enabled and replacing its contents otherwise. The file is then
+    /// truncated to the number of lines allowed by `HISTFILESIZE`.
+    pub fn save_history(&mut self) -> Result<(), error::Error> {
+        if !self.options.enable_command_history {
+            return Ok(());
+        }
+
+        if let Some(path) = self.get_history_file_path() {
+            let write_timestamps = self.should_write_history_timestamps();
+            Arc::make_mut(&mut self.history).write_file(
+                &path,
+                self.options.append_to_history_file,
+                write_timestamps,
+            )?;
+
+            if let Some(max_lines) = self.get_history_size_limit("HISTFILESIZE") {
+                history::truncate_file(&path, max_lines)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns the number of the line being executed in the currently executing program.
     pub(crate) fn get_current_input_line_number(&self) -> u32 {
         self.current_line_number
     }
 
+    /// Updates the number of the line being executed in the currently executing program.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The 1-based line number.
+    pub(crate) fn set_current_input_line_number(&mut self, line: u32) {
+        self.current_line_number = line;
+    }
+
+    /// Updates the text of the simple command being executed, as reflected in
+    /// `BASH_COMMAND`. While a trap handler runs, the command that was executing when the
+    /// trap fired is left in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The text of the command.
+    pub(crate) fn set_current_command(&mut self, command: &brush_parser::ast::SimpleCommand) {
+        if self.traps.handler_depth == 0 {
+            self.current_command = command.to_string();
+        }
+    }
+
     /// Tries to retrieve a variable from the shell's environment, converting it into its
     /// string form.
     ///
@@ -1427,6 +2447,63 @@ impl Shell {
     /// * `open_files` - The new file descriptor table to use.
     pub(crate) fn replace_open_files(&mut self, open_files: openfiles::OpenFiles) {
         self.open_files = open_files;
+        self.open_files_version += 1;
+    }
+
+    /// Adds the given file to the shell's file descriptor table, at a file descriptor
+    /// not in use by either the shell or the given execution parameters. Returns the
+    /// file descriptor assigned to the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The file to add.
+    /// * `params` - Execution parameters.
+    pub(crate) fn add_open_file(
+        &mut self,
+        file: openfiles::OpenFile,
+        params: &ExecutionParameters,
+    ) -> Result<u32, error::Error> {
+        // Starting at 63 (a.k.a. 64-1)--and decrementing--look for an
+        // available fd.
+        let mut candidate_fd_num = 63;
+        while self.open_files.files.contains_key(&candidate_fd_num)
+            || params.open_files.files.contains_key(&candidate_fd_num)
+        {
+            candidate_fd_num -= 1;
+            if candidate_fd_num == 0 {
+                return error::unimp("no available file descriptors");
+            }
+        }
+
+        self.open_files.files.insert(candidate_fd_num, file);
+        self.open_files_version += 1;
+        Ok(candidate_fd_num)
+    }
+
+    /// Adds to the given execution parameters any files the shell has opened (e.g., via
+    /// `exec` or for a coprocess) since they were captured, and that they don't already
+    /// have an entry for.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The execution parameters to add to.
+    pub(crate) fn add_missing_open_files(
+        &self,
+        params: &mut ExecutionParameters,
+    ) -> Result<(), error::Error> {
+        if params.open_files_version == self.open_files_version {
+            return Ok(());
+        }
+
+        for (fd_num, file) in &self.open_files.files {
+            if !params.open_files.files.contains_key(fd_num) {
+                params.open_files.files.insert(*fd_num, file.try_dup()?);
+            }
+        }
+
+        params.open_files_version = self.open_files_version;
+
+        Ok(())
     }
 
     /// Sets the shell's current working directory to the given path.
@@ -1532,7 +2609,8 @@ impl Shell {
 
         let mut prefix = ps4.to_string();
 
-        let additional_depth = self.script_call_stack.len() + self.depth;
+        let script_depth = self.call_stack.len() - self.function_call_depth();
+        let additional_depth = script_depth + self.depth;
         if let Some(c) = prefix.chars().next() {
             for _ in 0..additional_depth {
                 prefix.insert(0, c);
@@ -1569,6 +2647,8 @@ impl Shell {
     pub fn check_for_completed_jobs(&mut self) -> Result<(), error::Error> {
         let results = self.jobs.poll()?;
 
+        self.release_completed_coprocesses(results.iter().map(|(job, _result)| job))?;
+
         if self.options.enable_job_control {
             for (job, _result) in results {
                 writeln!(self.stderr(), "{job}")?;
@@ -1578,6 +2658,94 @@ impl Shell {
         Ok(())
     }
 
+    /// Checks whether the shell may exit, given the state of its jobs. An interactive
+    /// shell with stopped jobs (or, if `checkjobs` is enabled, running jobs) warns the
+    /// user about them instead; a second attempt to exit immediately afterward succeeds.
+    /// Returns whether the shell may exit.
+    pub fn confirm_exit(&mut self) -> Result<bool, error::Error> {
+        if !self.options.interactive || self.jobs_acknowledged {
+            return Ok(true);
+        }
+
+        let has_stopped_jobs = self
+            .jobs
+            .jobs
+            .iter()
+            .any(|job| matches!(job.state, jobs::JobState::Stopped));
+        let has_running_jobs = self
+            .jobs
+            .jobs
+            .iter()
+            .any(|job| matches!(job.state, jobs::JobState::Running));
+
+        if has_stopped_jobs {
+            writeln!(self.stderr(), "There are stopped jobs.")?;
+        } else if self.options.check_jobs_before_exit && has_running_jobs {
+            writeln!(self.stderr(), "There are running jobs.")?;
+        } else {
+            return Ok(true);
+        }
+
+        if self.options.check_jobs_before_exit {
+            for job in &self.jobs.jobs {
+                writeln!(self.stdout(), "{job}")?;
+            }
+        }
+
+        self.jobs_acknowledged = true;
+
+        Ok(false)
+    }
+
+    /// Checks whether the shell has received SIGHUP (without a trap handler registered
+    /// for it) since this was last called; if so, sends SIGHUP to the shell's jobs.
+    /// Returns whether SIGHUP was received, in which case the shell should exit.
+    pub fn handle_hangup(&mut self) -> bool {
+        if !sys::signal::take_pending_hangup() {
+            return false;
+        }
+
+        self.jobs.hangup_all();
+
+        true
+    }
+
+    /// Prepares the shell's jobs for the shell to exit: sends SIGHUP to them if this is
+    /// an interactive login shell with `huponexit` enabled.
+    pub fn hangup_jobs_on_exit(&mut self) {
+        if self.options.interactive
+            && self.options.login_shell
+            && self.options.send_sighup_to_all_jobs_on_exit
+        {
+            self.jobs.hangup_all();
+        }
+    }
+
+    /// Releases any coprocesses among the given completed jobs: closes the shell's
+    /// file descriptors for them and unsets their variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `jobs` - The completed jobs.
+    pub(crate) fn release_completed_coprocesses<'a>(
+        &mut self,
+        jobs: impl Iterator<Item = &'a jobs::Job>,
+    ) -> Result<(), error::Error> {
+        for name in jobs.filter_map(|job| job.coprocess_name.as_ref()) {
+            if let Some(var) = self.env.unset(name)? {
+                for fd in var.value().get_element_values(self) {
+                    if let Ok(fd) = fd.parse::<u32>() {
+                        self.open_files.files.remove(&fd);
+                    }
+                }
+            }
+
+            self.env.unset(std::format!("{name}_PID").as_str())?;
+        }
+
+        Ok(())
+    }
+
     /// Evaluate the given arithmetic expression, returning the result.
     pub fn eval_arithmetic(
         &mut self,
@@ -1591,10 +2759,12 @@ impl Shell {
 fn parse_string_impl(
     s: String,
     parser_options: brush_parser::ParserOptions,
+    start_line: i32,
 ) -> Result<brush_parser::ast::Program, brush_parser::ParseError> {
     let mut reader = std::io::BufReader::new(s.as_bytes());
     let source_info = brush_parser::SourceInfo {
         source: String::from("main"),
+        start_line,
     };
     let mut parser: brush_parser::Parser<&mut std::io::BufReader<&[u8]>> =
         brush_parser::Parser::new(&mut reader, &parser_options, &source_info);
@@ -1620,3 +2790,30 @@ fn get_srandom_value(_shell: &Shell) -> ShellValue {
     let str = num.to_string();
     str.into()
 }
+
+/// Splits the value of `HISTIGNORE` into its colon-separated patterns; a colon may be
+/// escaped with a backslash to include it in a pattern.
+fn split_history_ignore_patterns(value: &str) -> Vec<String> {
+    let mut patterns = vec![];
+    let mut current = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(':') => current.push(':'),
+                Some(next) => {
+                    current.push('\\');
+                    current.push(next);
+                }
+                None => current.push('\\'),
+            },
+            ':' => patterns.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    patterns.push(current);
+
+    patterns.retain(|p| !p.is_empty());
+    patterns
+}
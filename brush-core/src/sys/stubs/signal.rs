@@ -1,7 +1,11 @@
 use crate::{error, sys, traps};
 
-pub(crate) fn continue_process(_pid: sys::process::ProcessId) -> Result<(), error::Error> {
-    error::unimp("continue process")
+pub(crate) fn continue_process_group(_pgid: sys::process::ProcessId) -> Result<(), error::Error> {
+    error::unimp("continue process group")
+}
+
+pub(crate) fn hangup_process_group(_pgid: sys::process::ProcessId) -> Result<(), error::Error> {
+    error::unimp("hang up process group")
 }
 
 pub(crate) fn kill_process(
@@ -47,3 +51,31 @@ pub(crate) fn mask_sigttou() -> Result<(), error::Error> {
 pub(crate) fn poll_for_stopped_children() -> Result<bool, error::Error> {
     Ok(false)
 }
+
+pub(crate) fn catch_signal(_signal: traps::TrapSignal) -> Result<(), error::Error> {
+    Ok(())
+}
+
+pub(crate) fn ignore_signal(_signal: traps::TrapSignal) -> Result<(), error::Error> {
+    Ok(())
+}
+
+pub(crate) fn reset_signal(_signal: traps::TrapSignal) -> Result<(), error::Error> {
+    Ok(())
+}
+
+pub(crate) fn take_pending_signal(_signal: traps::TrapSignal) -> bool {
+    false
+}
+
+pub(crate) fn catch_hangup() -> Result<(), error::Error> {
+    Ok(())
+}
+
+pub(crate) fn take_pending_hangup() -> bool {
+    false
+}
+
+pub(crate) async fn await_pending_signal(_signals: &[traps::TrapSignal]) -> traps::TrapSignal {
+    futures::future::pending().await
+}
@@ -28,6 +28,19 @@ pub(crate) fn set_term_attr_now<Fd>(
     Ok(())
 }
 
+pub(crate) fn set_nonblocking<Fd>(_fd: Fd, _value: bool) -> Result<bool, error::Error> {
+    Ok(false)
+}
+
+pub(crate) fn poll_for_input<Fd>(
+    _fd: Fd,
+    _timeout: std::time::Duration,
+) -> Result<bool, error::Error> {
+    // N.B. Without a way to wait for input, we report it as available; reading it will
+    // block until it is.
+    Ok(true)
+}
+
 pub(crate) fn get_parent_process_id() -> Option<sys::process::ProcessId> {
     None
 }
@@ -1,5 +1,7 @@
 use crate::error;
 
+pub(crate) use nix::sys::resource::{rlim_t, Resource, RLIM_INFINITY};
+
 #[allow(clippy::unnecessary_wraps)]
 pub(crate) fn get_self_user_and_system_time(
 ) -> Result<(std::time::Duration, std::time::Duration), error::Error> {
@@ -25,3 +27,28 @@ fn convert_rusage_time(time: nix::sys::time::TimeVal) -> std::time::Duration {
     #[allow(clippy::cast_possible_truncation)]
     std::time::Duration::new(time.tv_sec() as u64, time.tv_usec() as u32 * 1000)
 }
+
+/// Returns the soft and hard limits on the given resource.
+///
+/// # Arguments
+///
+/// * `resource` - The resource whose limits should be retrieved.
+pub(crate) fn get_resource_limits(resource: Resource) -> Result<(rlim_t, rlim_t), nix::Error> {
+    nix::sys::resource::getrlimit(resource)
+}
+
+/// Sets the soft and hard limits on the given resource, for the shell and the processes
+/// it subsequently starts.
+///
+/// # Arguments
+///
+/// * `resource` - The resource whose limits should be updated.
+/// * `soft_limit` - The new soft limit.
+/// * `hard_limit` - The new hard limit.
+pub(crate) fn set_resource_limits(
+    resource: Resource,
+    soft_limit: rlim_t,
+    hard_limit: rlim_t,
+) -> Result<(), nix::Error> {
+    nix::sys::resource::setrlimit(resource, soft_limit, hard_limit)
+}
@@ -1,8 +1,20 @@
+use std::collections::HashMap;
+use std::os::fd::IntoRawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal};
+
 use crate::{error, sys, traps};
 
-pub(crate) fn continue_process(pid: sys::process::ProcessId) -> Result<(), error::Error> {
-    #[allow(clippy::cast_possible_wrap)]
-    nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), nix::sys::signal::SIGCONT)
+pub(crate) fn continue_process_group(pgid: sys::process::ProcessId) -> Result<(), error::Error> {
+    nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pgid), nix::sys::signal::SIGCONT)
+        .map_err(|_errno| error::Error::FailedToSendSignal)?;
+    Ok(())
+}
+
+pub(crate) fn hangup_process_group(pgid: sys::process::ProcessId) -> Result<(), error::Error> {
+    nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pgid), nix::sys::signal::SIGHUP)
         .map_err(|_errno| error::Error::FailedToSendSignal)?;
     Ok(())
 }
@@ -13,11 +25,22 @@ pub(crate) fn kill_process(
 ) -> Result<(), error::Error> {
     let translated_signal = match signal {
         traps::TrapSignal::Signal(signal) => signal,
-        traps::TrapSignal::Debug | traps::TrapSignal::Err | traps::TrapSignal::Exit => {
+        traps::TrapSignal::Debug
+        | traps::TrapSignal::Err
+        | traps::TrapSignal::Exit
+        | traps::TrapSignal::Return => {
             return Err(error::Error::InvalidSignal(signal.to_string()));
         }
     };
 
+    // A signal sent to ourselves is raised on the current thread instead, so that it's
+    // delivered (and, if trapped, recorded as pending) before we return.
+    if nix::unistd::Pid::from_raw(pid) == nix::unistd::getpid() {
+        nix::sys::signal::raise(translated_signal)
+            .map_err(|_errno| error::Error::FailedToSendSignal)?;
+        return Ok(());
+    }
+
     nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), translated_signal)
         .map_err(|_errno| error::Error::FailedToSendSignal)?;
 
@@ -135,3 +158,342 @@ fn siginfo_to_wait_status(
 
     Ok(status)
 }
+
+//
+// Delivery of trapped signals.
+//
+// When the shell registers a trap handler for a signal, we install a process-wide
+// handler that records the signal as pending and wakes up anyone waiting for a
+// signal. The shell then runs the corresponding trap handler at a safe point (e.g.,
+// between commands, or while blocked in `wait`). Any handler that was installed before
+// ours (e.g., by tokio) is chained to, so it continues to see the signal.
+//
+
+/// Number of signal numbers we track; covers all signals on supported platforms.
+const MAX_SIGNAL: usize = 64;
+
+/// Bitmask of trapped signals that have been received but not yet handled.
+static PENDING_SIGNALS: AtomicU64 = AtomicU64::new(0);
+
+/// Bitmask of signals that the shell has been asked to ignore.
+static IGNORED_SIGNALS: AtomicU64 = AtomicU64::new(0);
+
+/// Write end of the pipe used to wake up the signal notification thread; -1 if not
+/// yet created.
+static WAKE_PIPE_FD: AtomicI32 = AtomicI32::new(-1);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_CHAINED_HANDLER: AtomicUsize = AtomicUsize::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const NOT_SIGINFO: AtomicBool = AtomicBool::new(false);
+
+/// Addresses of handlers that were installed before ours, indexed by signal number.
+static CHAINED_HANDLERS: [AtomicUsize; MAX_SIGNAL] = [NO_CHAINED_HANDLER; MAX_SIGNAL];
+
+/// Whether each of the chained handlers expects `siginfo` arguments.
+static CHAINED_HANDLERS_TAKE_SIGINFO: [AtomicBool; MAX_SIGNAL] = [NOT_SIGINFO; MAX_SIGNAL];
+
+lazy_static::lazy_static! {
+    /// Dispositions of signals before the shell first changed them.
+    static ref ORIGINAL_ACTIONS: Mutex<HashMap<Signal, SigAction>> = Mutex::new(HashMap::new());
+
+    /// Notified whenever a trapped signal is received.
+    static ref SIGNAL_RECEIVED: tokio::sync::Notify = tokio::sync::Notify::new();
+}
+
+extern "C" fn handle_trapped_signal(
+    signo: nix::libc::c_int,
+    info: *mut nix::libc::siginfo_t,
+    context: *mut nix::libc::c_void,
+) {
+    let saved_errno = nix::errno::Errno::last_raw();
+
+    if let Some(index) = signal_index(signo) {
+        PENDING_SIGNALS.fetch_or(1 << index, Ordering::SeqCst);
+
+        let chained = CHAINED_HANDLERS[index].load(Ordering::SeqCst);
+        if chained != 0 {
+            if CHAINED_HANDLERS_TAKE_SIGINFO[index].load(Ordering::SeqCst) {
+                let chained: extern "C" fn(
+                    nix::libc::c_int,
+                    *mut nix::libc::siginfo_t,
+                    *mut nix::libc::c_void,
+                ) = unsafe { std::mem::transmute(chained) };
+                chained(signo, info, context);
+            } else {
+                let chained: extern "C" fn(nix::libc::c_int) =
+                    unsafe { std::mem::transmute(chained) };
+                chained(signo);
+            }
+        }
+    }
+
+    let wake_fd = WAKE_PIPE_FD.load(Ordering::SeqCst);
+    if wake_fd >= 0 {
+        let byte = 0u8;
+        unsafe { nix::libc::write(wake_fd, std::ptr::addr_of!(byte).cast(), 1) };
+    }
+
+    nix::errno::Errno::set_raw(saved_errno);
+}
+
+fn signal_index(signo: nix::libc::c_int) -> Option<usize> {
+    usize::try_from(signo)
+        .ok()
+        .filter(|index| *index < MAX_SIGNAL)
+}
+
+/// Returns the system signal corresponding to the given trap signal, if it's one whose
+/// disposition can be changed.
+fn trappable_signal(signal: traps::TrapSignal) -> Option<Signal> {
+    match signal {
+        traps::TrapSignal::Signal(Signal::SIGKILL | Signal::SIGSTOP) => None,
+        traps::TrapSignal::Signal(signal) => Some(signal),
+        traps::TrapSignal::Debug
+        | traps::TrapSignal::Err
+        | traps::TrapSignal::Exit
+        | traps::TrapSignal::Return => None,
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+const fn signal_bit(signal: Signal) -> u64 {
+    1 << (signal as i32 as u64)
+}
+
+/// Locks the table of original signal dispositions.
+fn lock_original_actions(
+) -> Result<std::sync::MutexGuard<'static, HashMap<Signal, SigAction>>, error::Error> {
+    ORIGINAL_ACTIONS
+        .lock()
+        .map_err(|_| error::Error::PoisonedLock)
+}
+
+/// Returns the address of the handler currently installed for the given signal, along
+/// with whether that handler expects `siginfo` arguments.
+fn query_handler(signal: Signal) -> Result<(usize, bool), error::Error> {
+    let mut current: nix::libc::sigaction = unsafe { std::mem::zeroed() };
+    nix::errno::Errno::result(unsafe {
+        nix::libc::sigaction(signal as nix::libc::c_int, std::ptr::null(), &mut current)
+    })?;
+    Ok((
+        current.sa_sigaction,
+        current.sa_flags & nix::libc::SA_SIGINFO != 0,
+    ))
+}
+
+/// Creates the pipe used to wake up waiters from within the signal handler, along with
+/// the thread that reads from it; does nothing if they already exist.
+fn ensure_wake_pipe() -> Result<(), error::Error> {
+    if WAKE_PIPE_FD.load(Ordering::SeqCst) >= 0 {
+        return Ok(());
+    }
+
+    let (reader, writer) = nix::unistd::pipe()?;
+    for fd in [&reader, &writer] {
+        nix::fcntl::fcntl(
+            std::os::fd::AsRawFd::as_raw_fd(fd),
+            nix::fcntl::FcntlArg::F_SETFD(nix::fcntl::FdFlag::FD_CLOEXEC),
+        )?;
+    }
+    nix::fcntl::fcntl(
+        std::os::fd::AsRawFd::as_raw_fd(&writer),
+        nix::fcntl::FcntlArg::F_SETFL(nix::fcntl::OFlag::O_NONBLOCK),
+    )?;
+
+    std::thread::Builder::new()
+        .name("signal-notifier".to_owned())
+        .spawn(move || {
+            let mut buffer = [0u8; 64];
+            loop {
+                match nix::unistd::read(std::os::fd::AsRawFd::as_raw_fd(&reader), &mut buffer) {
+                    Ok(0) => break,
+                    Ok(_) => SIGNAL_RECEIVED.notify_waiters(),
+                    Err(nix::errno::Errno::EINTR) => (),
+                    Err(_) => break,
+                }
+            }
+        })?;
+
+    // N.B. The write end is intentionally leaked; it's used for the lifetime of the process.
+    WAKE_PIPE_FD.store(writer.into_raw_fd(), Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Arranges for the given signal to be caught and recorded as pending, so the shell
+/// can run its trap handler.
+///
+/// # Arguments
+///
+/// * `signal` - The signal to catch.
+pub(crate) fn catch_signal(signal: traps::TrapSignal) -> Result<(), error::Error> {
+    let Some(signal) = trappable_signal(signal) else {
+        return Ok(());
+    };
+
+    let mut original_actions = lock_original_actions()?;
+
+    ensure_wake_pipe()?;
+
+    let (current_handler, takes_siginfo) = query_handler(signal)?;
+
+    if current_handler != handle_trapped_signal as *const () as usize {
+        // Remember whatever handler is currently installed so we can chain to it.
+        let index = signal as usize;
+        let chained =
+            if current_handler == nix::libc::SIG_DFL || current_handler == nix::libc::SIG_IGN {
+                0
+            } else {
+                current_handler
+            };
+        CHAINED_HANDLERS[index].store(chained, Ordering::SeqCst);
+        CHAINED_HANDLERS_TAKE_SIGINFO[index].store(takes_siginfo, Ordering::SeqCst);
+
+        let action = SigAction::new(
+            SigHandler::SigAction(handle_trapped_signal),
+            SaFlags::SA_RESTART | SaFlags::SA_SIGINFO,
+            SigSet::empty(),
+        );
+        let previous_action = unsafe { nix::sys::signal::sigaction(signal, &action) }?;
+        original_actions.entry(signal).or_insert(previous_action);
+    }
+
+    IGNORED_SIGNALS.fetch_and(!signal_bit(signal), Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Arranges for the given signal to be ignored by the shell and the commands it launches.
+///
+/// # Arguments
+///
+/// * `signal` - The signal to ignore.
+pub(crate) fn ignore_signal(signal: traps::TrapSignal) -> Result<(), error::Error> {
+    let Some(signal) = trappable_signal(signal) else {
+        return Ok(());
+    };
+
+    // Truly ignoring SIGCHLD would interfere with reaping child processes; instead, we
+    // catch it and just never run a handler for it.
+    if signal == Signal::SIGCHLD {
+        return catch_signal(traps::TrapSignal::Signal(signal));
+    }
+
+    let mut original_actions = lock_original_actions()?;
+
+    let action = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+    let previous_action = unsafe { nix::sys::signal::sigaction(signal, &action) }?;
+    original_actions.entry(signal).or_insert(previous_action);
+
+    IGNORED_SIGNALS.fetch_or(signal_bit(signal), Ordering::SeqCst);
+    PENDING_SIGNALS.fetch_and(!signal_bit(signal), Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Restores the given signal to the disposition it had before the shell changed it.
+///
+/// # Arguments
+///
+/// * `signal` - The signal to reset.
+pub(crate) fn reset_signal(signal: traps::TrapSignal) -> Result<(), error::Error> {
+    let Some(signal) = trappable_signal(signal) else {
+        return Ok(());
+    };
+
+    let mut original_actions = lock_original_actions()?;
+
+    if let Some(original_action) = original_actions.remove(&signal) {
+        // If someone else has since installed their own handler on top of ours, leave it
+        // in place; ours will just keep chaining to the original.
+        let (current_handler, _) = query_handler(signal)?;
+        if current_handler == handle_trapped_signal as *const () as usize
+            || current_handler == nix::libc::SIG_IGN
+        {
+            unsafe { nix::sys::signal::sigaction(signal, &original_action) }?;
+        }
+    }
+
+    IGNORED_SIGNALS.fetch_and(!signal_bit(signal), Ordering::SeqCst);
+    PENDING_SIGNALS.fetch_and(!signal_bit(signal), Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Checks whether the given signal has been received since the last check; if so,
+/// clears its pending state and returns true.
+///
+/// # Arguments
+///
+/// * `signal` - The signal to check.
+pub(crate) fn take_pending_signal(signal: traps::TrapSignal) -> bool {
+    let Some(signal) = trappable_signal(signal) else {
+        return false;
+    };
+
+    let bit = signal_bit(signal);
+    PENDING_SIGNALS.fetch_and(!bit, Ordering::SeqCst) & bit != 0
+}
+
+/// Arranges for SIGHUP to be caught and recorded as pending, even without a trap
+/// handler registered for it; the receipt of it can then be checked for with
+/// [`take_pending_hangup`].
+pub(crate) fn catch_hangup() -> Result<(), error::Error> {
+    catch_signal(traps::TrapSignal::Signal(Signal::SIGHUP))
+}
+
+/// Returns whether SIGHUP has been received since this was last called, clearing it.
+pub(crate) fn take_pending_hangup() -> bool {
+    take_pending_signal(traps::TrapSignal::Signal(Signal::SIGHUP))
+}
+
+/// Waits until one of the given signals is pending, returning it. The signal is left
+/// pending, for the caller to handle via [`take_pending_signal`].
+///
+/// # Arguments
+///
+/// * `signals` - The signals to wait for.
+pub(crate) async fn await_pending_signal(signals: &[traps::TrapSignal]) -> traps::TrapSignal {
+    let watched = signals
+        .iter()
+        .filter_map(|signal| trappable_signal(*signal))
+        .fold(0, |mask, signal| mask | signal_bit(signal));
+
+    loop {
+        // Register interest before checking, so we can't miss a notification.
+        let notified = SIGNAL_RECEIVED.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let pending = PENDING_SIGNALS.load(Ordering::SeqCst) & watched;
+        if pending != 0 {
+            #[allow(clippy::cast_possible_wrap)]
+            let signo = pending.trailing_zeros() as i32;
+            if let Ok(signal) = Signal::try_from(signo) {
+                return traps::TrapSignal::Signal(signal);
+            }
+        }
+
+        notified.await;
+    }
+}
+
+/// Returns whether the shell has been asked to ignore any signals.
+pub(crate) fn any_signals_ignored() -> bool {
+    IGNORED_SIGNALS.load(Ordering::SeqCst) != 0
+}
+
+/// Re-applies the shell's ignored signal dispositions; intended to be called in a child
+/// process before it execs its target, since the standard library resets some signal
+/// dispositions (e.g., `SIGPIPE`) in that window.
+pub(crate) fn reapply_ignored_signals() {
+    let ignored = IGNORED_SIGNALS.load(Ordering::SeqCst);
+    for index in 1..MAX_SIGNAL {
+        if ignored & (1 << index) != 0 {
+            #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+            let signo = index as nix::libc::c_int;
+            unsafe { nix::libc::signal(signo, nix::libc::SIG_IGN) };
+        }
+    }
+}
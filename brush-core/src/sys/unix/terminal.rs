@@ -1,5 +1,8 @@
 use crate::{error, sys};
-use std::{io::IsTerminal, os::fd::AsFd};
+use std::{
+    io::IsTerminal,
+    os::fd::{AsFd, AsRawFd},
+};
 
 #[derive(Clone)]
 pub(crate) struct TerminalSettings {
@@ -12,7 +15,7 @@ impl TerminalSettings {
     }
 
     pub fn set_echo(&mut self, value: bool) {
-        self.set_local_flag(nix::sys::termios::LocalFlags::ICANON, value);
+        self.set_local_flag(nix::sys::termios::LocalFlags::ECHO, value);
     }
 
     pub fn set_int_signal(&mut self, value: bool) {
@@ -42,6 +45,51 @@ pub(crate) fn set_term_attr_now<Fd: AsFd>(
     Ok(())
 }
 
+/// Sets whether or not I/O on the given file descriptor is non-blocking. Returns the
+/// previous setting.
+pub(crate) fn set_nonblocking<Fd: AsFd>(fd: Fd, value: bool) -> Result<bool, error::Error> {
+    let raw_fd = fd.as_fd().as_raw_fd();
+    let flags = nix::fcntl::OFlag::from_bits_truncate(nix::fcntl::fcntl(
+        raw_fd,
+        nix::fcntl::FcntlArg::F_GETFL,
+    )?);
+
+    let was_nonblocking = flags.contains(nix::fcntl::OFlag::O_NONBLOCK);
+    if was_nonblocking != value {
+        let mut updated_flags = flags;
+        updated_flags.set(nix::fcntl::OFlag::O_NONBLOCK, value);
+        nix::fcntl::fcntl(raw_fd, nix::fcntl::FcntlArg::F_SETFL(updated_flags))?;
+    }
+
+    Ok(was_nonblocking)
+}
+
+/// Waits up to the given duration for input to be available to read from the given file
+/// descriptor (or for it to reach end of input). Returns whether or not it became available.
+pub(crate) fn poll_for_input<Fd: AsFd>(
+    fd: Fd,
+    timeout: std::time::Duration,
+) -> Result<bool, error::Error> {
+    let deadline = std::time::Instant::now() + timeout;
+    let mut poll_fds = [nix::poll::PollFd::new(
+        fd.as_fd(),
+        nix::poll::PollFlags::POLLIN,
+    )];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        let poll_timeout =
+            nix::poll::PollTimeout::try_from(remaining).unwrap_or(nix::poll::PollTimeout::MAX);
+
+        match nix::poll::poll(&mut poll_fds, poll_timeout) {
+            Ok(ready_count) => return Ok(ready_count > 0),
+            // Keep waiting if we were interrupted by a signal (e.g., a child exiting).
+            Err(nix::errno::Errno::EINTR) => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 #[allow(clippy::unnecessary_wraps)]
 pub(crate) fn get_parent_process_id() -> Option<sys::process::ProcessId> {
     Some(nix::unistd::getppid().as_raw())
@@ -1,12 +1,14 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::str::FromStr;
-use std::{collections::HashMap, fmt::Display};
 
 use itertools::Itertools as _;
 
-use crate::error;
+use crate::options::RuntimeOptions;
+use crate::{error, sys};
 
 /// Type of signal that can be trapped in the shell.
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum TrapSignal {
     /// A system signal.
     #[cfg(unix)]
@@ -17,6 +19,8 @@ pub enum TrapSignal {
     Err,
     /// The `EXIT` trap.
     Exit,
+    /// The `RETURN` trap.
+    Return,
 }
 
 impl Display for TrapSignal {
@@ -28,7 +32,12 @@ impl Display for TrapSignal {
 impl TrapSignal {
     /// Returns all possible values of [`TrapSignal`].
     pub fn iterator() -> impl Iterator<Item = TrapSignal> {
-        const SIGNALS: &[TrapSignal] = &[TrapSignal::Debug, TrapSignal::Err, TrapSignal::Exit];
+        const SIGNALS: &[TrapSignal] = &[
+            TrapSignal::Debug,
+            TrapSignal::Err,
+            TrapSignal::Exit,
+            TrapSignal::Return,
+        ];
         let iter = SIGNALS.iter().copied();
 
         #[cfg(unix)]
@@ -48,6 +57,7 @@ impl TrapSignal {
             TrapSignal::Debug => "DEBUG",
             TrapSignal::Err => "ERR",
             TrapSignal::Exit => "EXIT",
+            TrapSignal::Return => "RETURN",
         }
     }
 }
@@ -113,6 +123,7 @@ impl TryFrom<&str> for TrapSignal {
             "DEBUG" => TrapSignal::Debug,
             "ERR" => TrapSignal::Err,
             "EXIT" => TrapSignal::Exit,
+            "RETURN" => TrapSignal::Return,
 
             #[cfg(unix)]
             _ => {
@@ -153,6 +164,20 @@ pub struct TrapHandlerConfig {
     pub handlers: HashMap<TrapSignal, String>,
     /// Current depth of the handler stack.
     pub handler_depth: i32,
+    /// For a subshell, the signal dispositions of the shell it was cloned from.
+    inherited: Option<InheritedDispositions>,
+}
+
+/// Tracks what's needed to restore the signal dispositions of the shell a subshell was
+/// cloned from. Signal dispositions are process-wide, but subshells run in-process.
+#[derive(Clone)]
+struct InheritedDispositions {
+    /// Handlers registered in the shell the subshell was cloned from.
+    handlers: HashMap<TrapSignal, String>,
+    /// Whether the shell the subshell was cloned from is interactive.
+    interactive: bool,
+    /// Signals whose dispositions have been changed by the subshell.
+    changed: HashSet<TrapSignal>,
 }
 
 impl TrapHandlerConfig {
@@ -174,4 +199,109 @@ impl TrapHandlerConfig {
     pub fn remove_handlers(&mut self, signal_type: TrapSignal) {
         self.handlers.remove(&signal_type);
     }
+
+    /// Returns the signals for which a (non-empty) handler command is registered.
+    pub(crate) fn caught_signals(&self) -> Vec<TrapSignal> {
+        self.handlers
+            .iter()
+            .filter(|(signal, handler)| {
+                !matches!(
+                    signal,
+                    TrapSignal::Debug | TrapSignal::Err | TrapSignal::Exit | TrapSignal::Return
+                ) && !handler.is_empty()
+            })
+            .map(|(signal, _)| *signal)
+            .sorted_by_key(|signal| i32::try_from(*signal).unwrap_or_default())
+            .collect()
+    }
+
+    /// Returns a copy of this configuration suitable for use in a subshell. Subshells
+    /// do not inherit the parent's `EXIT` trap or its handlers for caught signals (though
+    /// ignored signals stay ignored). They only inherit its `ERR` trap when errtrace
+    /// (`set -E`) is enabled, and its `DEBUG` and `RETURN` traps when functrace (`set -T`)
+    /// is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The runtime options of the shell being cloned.
+    pub(crate) fn clone_for_subshell(&self, options: &RuntimeOptions) -> Self {
+        let mut cloned = self.clone();
+        cloned.inherited = Some(InheritedDispositions {
+            handlers: self.handlers.clone(),
+            interactive: options.interactive,
+            changed: HashSet::new(),
+        });
+        cloned.remove_handlers(TrapSignal::Exit);
+        for signal in self.caught_signals() {
+            cloned.remove_handlers(signal);
+        }
+        if !options.shell_functions_inherit_err_trap {
+            cloned.remove_handlers(TrapSignal::Err);
+        }
+        if !options.shell_functions_inherit_debug_and_return_traps {
+            cloned.remove_handlers(TrapSignal::Debug);
+            cloned.remove_handlers(TrapSignal::Return);
+        }
+        cloned
+    }
+
+    /// Updates the process's disposition for the given signal to match the handler
+    /// registered for it: caught, ignored (for an empty handler), or restored to how
+    /// it was before the shell changed it.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - The signal to update the disposition of.
+    /// * `interactive` - Whether the shell is interactive.
+    pub(crate) fn apply_disposition(
+        &mut self,
+        signal: TrapSignal,
+        interactive: bool,
+    ) -> Result<(), error::Error> {
+        if let Some(inherited) = &mut self.inherited {
+            inherited.changed.insert(signal);
+        }
+
+        set_disposition(signal, self.handlers.get(&signal), interactive)
+    }
+
+    /// For a subshell, restores the dispositions of any signals it changed to match the
+    /// shell it was cloned from. Expected to be invoked when the subshell is done.
+    pub(crate) fn restore_inherited_dispositions(&mut self) -> Result<(), error::Error> {
+        let Some(inherited) = &mut self.inherited else {
+            return Ok(());
+        };
+
+        for signal in inherited.changed.drain() {
+            set_disposition(
+                signal,
+                inherited.handlers.get(&signal),
+                inherited.interactive,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn set_disposition(
+    signal: TrapSignal,
+    handler: Option<&String>,
+    interactive: bool,
+) -> Result<(), error::Error> {
+    match handler {
+        Some(handler) if handler.is_empty() => sys::signal::ignore_signal(signal),
+        Some(_) => sys::signal::catch_signal(signal),
+        None => {
+            sys::signal::reset_signal(signal)?;
+
+            // Interactive shells always catch SIGHUP, so they can pass it along to their jobs.
+            #[cfg(unix)]
+            if interactive && signal == TrapSignal::Signal(nix::sys::signal::Signal::SIGHUP) {
+                sys::signal::catch_hangup()?;
+            }
+
+            Ok(())
+        }
+    }
 }
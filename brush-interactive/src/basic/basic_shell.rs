@@ -1,9 +1,10 @@
+use std::collections::HashMap;
 use std::io::{IsTerminal, Write};
 
 use crate::{
     completion,
     interactive_shell::{InteractivePrompt, InteractiveShell, ReadResult},
-    ShellError,
+    key_bindings, ShellError,
 };
 
 use super::term_line_reader;
@@ -12,6 +13,7 @@ use super::term_line_reader;
 /// for completion and test-focused automation via pexpect and similar technologies.
 pub struct BasicShell {
     shell: brush_core::Shell,
+    next_input: Option<String>,
 }
 
 impl BasicShell {
@@ -21,8 +23,15 @@ impl BasicShell {
     ///
     /// * `options` - Options for creating the interactive shell.
     pub async fn new(options: &crate::Options) -> Result<Self, ShellError> {
-        let shell = brush_core::Shell::new(&options.shell).await?;
-        Ok(Self { shell })
+        let mut shell = brush_core::Shell::new(&options.shell).await?;
+
+        // Let builtins (e.g., `read -e`) read input with a line editor.
+        shell.line_editor = Some(std::sync::Arc::new(BasicLineEditor));
+
+        Ok(Self {
+            shell,
+            next_input: None,
+        })
     }
 }
 
@@ -63,8 +72,8 @@ impl InteractiveShell for BasicShell {
         Ok(ReadResult::Input(result))
     }
 
-    fn update_history(&mut self) -> Result<(), ShellError> {
-        Ok(())
+    fn set_next_input(&mut self, text: &str) {
+        self.next_input = Some(text.to_owned());
     }
 }
 
@@ -84,10 +93,43 @@ impl BasicShell {
     }
 
     fn read_input_line(&mut self, prompt: &InteractivePrompt) -> Result<ReadResult, ShellError> {
+        // N.B. Input that's not coming from a terminal can't be edited, so any text
+        // intended to pre-populate it is discarded.
+        let next_input = self.next_input.take().unwrap_or_default();
+
         if std::io::stdin().is_terminal() {
-            term_line_reader::read_line(prompt.prompt.as_str(), |line, cursor| {
-                self.generate_completions(line, cursor)
-            })
+            let shell_command_bindings = key_bindings::shell_command_bindings(
+                &self.shell.readline_config,
+                self.shell.readline_config.current_keymap(),
+            );
+
+            let mut buffer = brush_core::EditBuffer {
+                cursor: next_input.len(),
+                text: next_input,
+            };
+
+            loop {
+                match term_line_reader::read_line(
+                    prompt.prompt.as_str(),
+                    &buffer,
+                    &shell_command_bindings,
+                    |line, cursor| self.generate_completions(line, cursor),
+                )? {
+                    term_line_reader::LineReaderResult::Finished(result) => return Ok(result),
+                    term_line_reader::LineReaderResult::ShellCommand(command, updated_buffer) => {
+                        // Run the command, then resume editing its (possibly updated) line.
+                        buffer = updated_buffer;
+                        if key_bindings::run_shell_command_binding(
+                            &mut self.shell,
+                            command.as_str(),
+                            &mut buffer,
+                        )? {
+                            return Ok(ReadResult::Eof);
+                        }
+                        self.display_prompt(prompt)?;
+                    }
+                }
+            }
         } else {
             let mut input = String::new();
             let bytes_read = std::io::stdin()
@@ -133,3 +175,36 @@ impl BasicShell {
         Ok(completion::complete_async(&mut self.shell, line, cursor).await)
     }
 }
+
+/// Line editor made available to builtins.
+struct BasicLineEditor;
+
+impl brush_core::LineEditor for BasicLineEditor {
+    fn read_line(
+        &self,
+        prompt: &str,
+        initial_text: &str,
+    ) -> Result<Option<String>, brush_core::Error> {
+        eprint!("{prompt}");
+        std::io::stderr().flush()?;
+
+        let initial_buffer = brush_core::EditBuffer {
+            text: initial_text.to_owned(),
+            cursor: initial_text.len(),
+        };
+
+        // N.B. No completions or key bindings to shell commands are offered.
+        let result =
+            term_line_reader::read_line(prompt, &initial_buffer, &HashMap::new(), |_, _| {
+                Ok(brush_core::completion::Completions::default())
+            });
+
+        match result {
+            Ok(term_line_reader::LineReaderResult::Finished(ReadResult::Input(s))) => Ok(Some(s)),
+            Ok(_) => Ok(None),
+            Err(ShellError::ShellError(e)) => Err(e),
+            Err(ShellError::IoError(e)) => Err(e.into()),
+            Err(e) => Err(std::io::Error::other(e.to_string()).into()),
+        }
+    }
+}
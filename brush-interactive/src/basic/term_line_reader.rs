@@ -5,26 +5,41 @@
 //
 
 use crossterm::ExecutableCommand;
+use std::collections::HashMap;
 use std::io::Write;
 
 use super::raw_mode;
+use crate::key_bindings::{self, KeyCombination, SequenceMatch};
 use crate::{ReadResult, ShellError};
 
 const BACKSPACE: char = 8u8 as char;
 
+/// The outcome of reading a line of input.
+pub(crate) enum LineReaderResult {
+    /// Reading finished, with the given result.
+    Finished(ReadResult),
+    /// A key bound to the given shell command was pressed. The command should be run with
+    /// access to the given edit buffer, after which reading may resume.
+    ShellCommand(String, brush_core::EditBuffer),
+}
+
 pub(crate) fn read_line(
     prompt: &str,
+    initial_buffer: &brush_core::EditBuffer,
+    shell_command_bindings: &HashMap<Vec<KeyCombination>, String>,
     mut completion_handler: impl FnMut(
         &str,
         usize,
     ) -> Result<brush_core::completion::Completions, ShellError>,
-) -> Result<ReadResult, ShellError> {
-    let mut state = ReadLineState::new(prompt)?;
+) -> Result<LineReaderResult, ShellError> {
+    let mut state = ReadLineState::new(prompt, initial_buffer)?;
 
     loop {
         state.raw_mode.enable()?;
         if let crossterm::event::Event::Key(event) = crossterm::event::read()? {
-            if let Some(result) = state.on_key(event, &mut completion_handler)? {
+            if let Some(result) =
+                state.on_key_in_sequence(event, shell_command_bindings, &mut completion_handler)?
+            {
                 return Ok(result);
             }
         }
@@ -36,16 +51,83 @@ struct ReadLineState<'a> {
     cursor: usize,
     prompt: &'a str,
     raw_mode: raw_mode::RawModeToggle,
+    /// Keys held back because they begin a key sequence bound to a shell command.
+    pending_keys: Vec<crossterm::event::KeyEvent>,
 }
 
 impl<'a> ReadLineState<'a> {
-    fn new(prompt: &'a str) -> Result<Self, ShellError> {
-        Ok(Self {
-            line: String::new(),
-            cursor: 0,
+    fn new(prompt: &'a str, initial_buffer: &brush_core::EditBuffer) -> Result<Self, ShellError> {
+        let state = Self {
+            line: initial_buffer.text.clone(),
+            cursor: initial_buffer.cursor,
             prompt,
             raw_mode: raw_mode::RawModeToggle::new()?,
-        })
+            pending_keys: vec![],
+        };
+
+        if !state.line.is_empty() {
+            eprint!(
+                "{}{}",
+                state.line,
+                repeated_char_str(BACKSPACE, state.line[state.cursor..].chars().count())
+            );
+            std::io::stderr().flush()?;
+        }
+
+        Ok(state)
+    }
+
+    /// Handles a key, first checking whether it completes (or continues) a key sequence
+    /// bound to a shell command.
+    fn on_key_in_sequence(
+        &mut self,
+        event: crossterm::event::KeyEvent,
+        shell_command_bindings: &HashMap<Vec<KeyCombination>, String>,
+        mut completion_handler: impl FnMut(
+            &str,
+            usize,
+        )
+            -> Result<brush_core::completion::Completions, ShellError>,
+    ) -> Result<Option<LineReaderResult>, ShellError> {
+        let keys: Vec<_> = self
+            .pending_keys
+            .iter()
+            .chain(std::iter::once(&event))
+            .map(|key| key_bindings::normalize_key(key.modifiers, key.code))
+            .collect();
+
+        match key_bindings::match_key_sequence(shell_command_bindings, &keys) {
+            SequenceMatch::Complete(command) => {
+                self.pending_keys.clear();
+                self.display_newline()?;
+                self.raw_mode.disable()?;
+
+                let buffer = brush_core::EditBuffer {
+                    text: std::mem::take(&mut self.line),
+                    cursor: self.cursor,
+                };
+                Ok(Some(LineReaderResult::ShellCommand(
+                    command.clone(),
+                    buffer,
+                )))
+            }
+            SequenceMatch::Prefix => {
+                self.pending_keys.push(event);
+                Ok(None)
+            }
+            SequenceMatch::None => {
+                // Pass along any held-back keys, followed by this one.
+                for key in std::mem::take(&mut self.pending_keys)
+                    .into_iter()
+                    .chain(std::iter::once(event))
+                {
+                    if let Some(result) = self.on_key(key, &mut completion_handler)? {
+                        return Ok(Some(LineReaderResult::Finished(result)));
+                    }
+                }
+                Ok(None)
+            }
+        }
     }
 
     fn display_prompt(&self) -> Result<(), ShellError> {
@@ -46,8 +46,19 @@ pub trait InteractiveShell {
     /// * `prompt` - The prompt to display to the user.
     fn read_line(&mut self, prompt: InteractivePrompt) -> Result<ReadResult, ShellError>;
 
-    /// Update history, if relevant.
-    fn update_history(&mut self) -> Result<(), ShellError>;
+    /// Pre-populates the next line of input with the given text, for the user to edit
+    /// before submitting it. Implementations that don't support line editing discard it.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to place in the input buffer.
+    fn set_next_input(&mut self, _text: &str) {}
+
+    /// Saves the shell's command history to its history file, if relevant.
+    fn update_history(&mut self) -> Result<(), ShellError> {
+        self.shell_mut().as_mut().save_history()?;
+        Ok(())
+    }
 
     /// Runs the interactive shell loop, reading commands from standard input and writing
     /// results to standard output and standard error. Continues until the shell
@@ -60,9 +71,12 @@ pub trait InteractiveShell {
             let _ = brush_core::TerminalControl::acquire()?;
 
             let mut announce_exit = self.shell().as_ref().options.interactive;
+            let mut warned_at_eof = false;
 
             loop {
                 let result = self.run_interactively_once().await?;
+                let at_eof = matches!(result, InteractiveExecutionResult::Eof);
+
                 match result {
                     InteractiveExecutionResult::Executed(brush_core::ExecutionResult {
                         exit_shell,
@@ -82,10 +96,25 @@ pub trait InteractiveShell {
                         tracing::error!("error: {:#}", e);
                     }
                     InteractiveExecutionResult::Eof => {
-                        break;
+                        // As with `exit`, give the user a chance to deal with any jobs;
+                        // a second end of input in a row exits regardless.
+                        if warned_at_eof || self.shell_mut().as_mut().confirm_exit()? {
+                            // Also as with `exit`, an interactive login shell sources the
+                            // user's logout files before its EXIT trap runs.
+                            if self.shell().as_ref().options.interactive {
+                                let mut shell = self.shell_mut();
+                                let shell_mut = shell.as_mut();
+                                let params = shell_mut.default_exec_params();
+                                shell_mut.source_logout_files(&params).await?;
+                            }
+
+                            break;
+                        }
                     }
                 }
 
+                warned_at_eof = at_eof;
+
                 if self.shell().as_ref().options.exit_after_one_command {
                     announce_exit = false;
                     break;
@@ -117,6 +146,21 @@ pub trait InteractiveShell {
             // Check for any completed jobs.
             shell_mut.check_for_completed_jobs()?;
 
+            // Run handlers for any trapped signals received since the last command.
+            let params = shell_mut.default_exec_params();
+            let trap_result = shell_mut.run_pending_traps(&params).await?;
+            if trap_result.exit_shell {
+                return Ok(InteractiveExecutionResult::Executed(trap_result));
+            }
+
+            // If our terminal hung up, pass that along to our jobs and exit.
+            if shell_mut.handle_hangup() {
+                let mut result = brush_core::ExecutionResult::new(128 + 1);
+                result.exit_shell = true;
+                shell_mut.last_exit_status = result.exit_code;
+                return Ok(InteractiveExecutionResult::Executed(result));
+            }
+
             // If there's a variable called PROMPT_COMMAND, then run it first.
             if let Some(prompt_cmd) = shell_mut.get_env_str("PROMPT_COMMAND") {
                 // Save (and later restore) the last exit status.
@@ -144,8 +188,17 @@ pub trait InteractiveShell {
 
             match self.read_line(prompt)? {
                 ReadResult::Input(read_result) => {
+                    let Some(read_result) = self.expand_history(read_result)? else {
+                        let last_exit_status = self.shell().as_ref().last_exit_status;
+                        return Ok(InteractiveExecutionResult::Executed(
+                            brush_core::ExecutionResult::new(last_exit_status),
+                        ));
+                    };
+
                     let mut shell_mut = self.shell_mut();
 
+                    shell_mut.as_mut().add_to_history(read_result.as_str())?;
+
                     let precmd_prompt = shell_mut.as_mut().compose_precmd_prompt().await?;
                     if !precmd_prompt.is_empty() {
                         print!("{precmd_prompt}");
@@ -168,4 +221,69 @@ pub trait InteractiveShell {
             }
         }
     }
+
+    /// Applies bang-style history expansion to a line of input, if it's enabled. Returns
+    /// the line to execute, or `None` if the line shouldn't be executed: because expansion
+    /// failed, because the line was only to be displayed, or because `histverify` calls
+    /// for the expanded line to be edited first.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of input.
+    fn expand_history(&mut self, line: String) -> Result<Option<String>, ShellError> {
+        let mut shell = self.shell_mut();
+        let shell_mut = shell.as_mut();
+
+        if !shell_mut.options.enable_bang_style_history_substitution {
+            return Ok(Some(line));
+        }
+
+        let expansion = match shell_mut.expand_history(line.as_str()) {
+            Ok(expansion) => expansion,
+            Err(e) => {
+                let shell_name = shell_mut.shell_name.clone().unwrap_or_default();
+                writeln!(shell_mut.stderr(), "{shell_name}: {e}")?;
+
+                // With `histreedit`, the failed line is given back to the user to fix.
+                let reedit = shell_mut.options.allow_reedit_failed_history_subst;
+                drop(shell);
+                if reedit {
+                    self.set_next_input(line.as_str());
+                }
+
+                return Ok(None);
+            }
+        };
+
+        if !expansion.expanded {
+            return Ok(Some(line));
+        }
+
+        if expansion.print_only {
+            writeln!(
+                shell_mut.stderr(),
+                "{}",
+                expansion.line.trim_end_matches('\n')
+            )?;
+            shell_mut.add_to_history(expansion.line.as_str())?;
+            return Ok(None);
+        }
+
+        // With `histverify`, the expanded line is given back to the user instead of being
+        // executed right away.
+        if shell_mut.options.allow_modifying_history_substitution {
+            drop(shell);
+            self.set_next_input(expansion.line.trim_end_matches('\n'));
+            return Ok(None);
+        }
+
+        // The expanded line is displayed before it's executed.
+        writeln!(
+            shell_mut.stderr(),
+            "{}",
+            expansion.line.trim_end_matches('\n')
+        )?;
+
+        Ok(Some(expansion.line))
+    }
 }
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::ShellError;
+
+/// A single key press, with its modifiers; characters are normalized to match the way
+/// reedline looks up bindings.
+pub(crate) type KeyCombination = (KeyModifiers, KeyCode);
+
+/// The result of looking up keys pressed so far among bindings of key sequences.
+pub(crate) enum SequenceMatch<'a, T> {
+    /// The keys complete a bound sequence.
+    Complete(&'a T),
+    /// The keys begin one or more bound sequences, but don't complete any.
+    Prefix,
+    /// The keys don't match any bound sequence.
+    None,
+}
+
+/// Looks up the given keys among bindings of key sequences.
+///
+/// # Arguments
+///
+/// * `bindings` - The bindings to search.
+/// * `keys` - The keys pressed so far.
+pub(crate) fn match_key_sequence<'a, T>(
+    bindings: &'a HashMap<Vec<KeyCombination>, T>,
+    keys: &[KeyCombination],
+) -> SequenceMatch<'a, T> {
+    if let Some(bound) = bindings.get(keys) {
+        SequenceMatch::Complete(bound)
+    } else if bindings
+        .keys()
+        .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+    {
+        SequenceMatch::Prefix
+    } else {
+        SequenceMatch::None
+    }
+}
+
+/// Returns the key sequences in the given key map that are bound to shell commands (e.g.,
+/// via `bind -x`), along with the commands.
+///
+/// # Arguments
+///
+/// * `config` - The shell's readline configuration.
+/// * `keymap` - The key map to search.
+#[cfg(feature = "basic")]
+pub(crate) fn shell_command_bindings(
+    config: &brush_core::readline::Config,
+    keymap: brush_core::readline::KeyMap,
+) -> HashMap<Vec<KeyCombination>, String> {
+    config
+        .bindings(keymap)
+        .into_iter()
+        .filter_map(|(key_sequence, action)| match action {
+            brush_core::readline::KeyAction::ShellCommand(command) => {
+                Some((decode_key_sequence(key_sequence.as_slice())?, command))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs a shell command bound to a key sequence, giving it access to the edit buffer,
+/// which it may modify. Returns whether the shell should exit.
+///
+/// # Arguments
+///
+/// * `shell` - The shell to run the command in.
+/// * `command` - The command to run.
+/// * `buffer` - The edit buffer.
+pub(crate) fn run_shell_command_binding(
+    shell: &mut brush_core::Shell,
+    command: &str,
+    buffer: &mut brush_core::EditBuffer,
+) -> Result<bool, ShellError> {
+    let result = tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(shell.run_key_binding_command(command, buffer))
+    });
+
+    match result {
+        Ok(result) => Ok(result.exit_shell),
+        Err(e) => {
+            let shell_name = shell.shell_name.clone().unwrap_or_default();
+            writeln!(shell.stderr(), "{shell_name}: {e}")?;
+            Ok(false)
+        }
+    }
+}
+
+/// Extracts the key pressed in the given event, if any.
+#[cfg(feature = "reedline")]
+pub(crate) fn key_combination(event: &crossterm::event::Event) -> Option<KeyCombination> {
+    match event {
+        crossterm::event::Event::Key(crossterm::event::KeyEvent {
+            code, modifiers, ..
+        }) => Some(normalize_key(*modifiers, *code)),
+        _ => None,
+    }
+}
+
+/// Normalizes a key the way reedline's emacs mode does when looking up its bindings.
+pub(crate) fn normalize_key(modifiers: KeyModifiers, code: KeyCode) -> KeyCombination {
+    match code {
+        KeyCode::Char(c) if modifiers != KeyModifiers::NONE => {
+            (modifiers, KeyCode::Char(c.to_ascii_lowercase()))
+        }
+        code => (modifiers, code),
+    }
+}
+
+/// Decodes the bytes of a key sequence into the keys a terminal reports for them.
+pub(crate) fn decode_key_sequence(bytes: &[u8]) -> Option<Vec<KeyCombination>> {
+    let mut keys = vec![];
+
+    let mut remaining = bytes;
+    while !remaining.is_empty() {
+        let ((modifiers, code), len) = decode_key(remaining)?;
+        keys.push(normalize_key(modifiers, code));
+        remaining = &remaining[len..];
+    }
+
+    Some(keys)
+}
+
+/// Decodes the first key from the given bytes, returning it along with the number of bytes
+/// it took up.
+fn decode_key(bytes: &[u8]) -> Option<(KeyCombination, usize)> {
+    let key = match bytes {
+        [] => return None,
+        [b'\x1b', b'[' | b'O', ..] => return decode_escape_sequence(bytes),
+        [b'\x1b'] => (KeyModifiers::NONE, KeyCode::Esc),
+        [b'\x1b', rest @ ..] => {
+            let ((modifiers, code), len) = decode_key(rest)?;
+            return Some(((modifiers | KeyModifiers::ALT, code), len + 1));
+        }
+        [b'\r', ..] => (KeyModifiers::NONE, KeyCode::Enter),
+        [b'\t', ..] => (KeyModifiers::NONE, KeyCode::Tab),
+        [b'\x7f', ..] => (KeyModifiers::NONE, KeyCode::Backspace),
+        [b'\0', ..] => (KeyModifiers::CONTROL, KeyCode::Char(' ')),
+        [c @ b'\x01'..=b'\x1a', ..] => (
+            KeyModifiers::CONTROL,
+            KeyCode::Char(char::from(c - 0x01 + b'a')),
+        ),
+        [c @ b'\x1c'..=b'\x1f', ..] => (
+            KeyModifiers::CONTROL,
+            KeyCode::Char(char::from(c - 0x1c + b'4')),
+        ),
+        _ => {
+            let len = utf8_char_len(bytes[0]);
+            let c = std::str::from_utf8(bytes.get(..len)?)
+                .ok()?
+                .chars()
+                .next()?;
+            let modifiers = if c.is_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            return Some(((modifiers, KeyCode::Char(c)), len));
+        }
+    };
+
+    Some((key, 1))
+}
+
+/// Decodes a terminal escape sequence (e.g., for an arrow or function key).
+fn decode_escape_sequence(bytes: &[u8]) -> Option<(KeyCombination, usize)> {
+    // Find the end of the sequence: parameters are digits and semicolons.
+    let params_len = bytes[2..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit() || **b == b';')
+        .count();
+    let final_byte = *bytes.get(2 + params_len)?;
+    let len = 3 + params_len;
+
+    let params: Vec<u8> = std::str::from_utf8(&bytes[2..2 + params_len])
+        .ok()?
+        .split(';')
+        .map(|param| param.parse().unwrap_or(1))
+        .collect();
+
+    let code = match (final_byte, params.first()) {
+        (b'A', _) => KeyCode::Up,
+        (b'B', _) => KeyCode::Down,
+        (b'C', _) => KeyCode::Right,
+        (b'D', _) => KeyCode::Left,
+        (b'H', _) => KeyCode::Home,
+        (b'F', _) => KeyCode::End,
+        (b'P'..=b'S', _) => KeyCode::F(final_byte - b'P' + 1),
+        (b'~', Some(1 | 7)) => KeyCode::Home,
+        (b'~', Some(2)) => KeyCode::Insert,
+        (b'~', Some(3)) => KeyCode::Delete,
+        (b'~', Some(4 | 8)) => KeyCode::End,
+        (b'~', Some(5)) => KeyCode::PageUp,
+        (b'~', Some(6)) => KeyCode::PageDown,
+        (b'~', Some(n @ 11..=15)) => KeyCode::F(n - 10),
+        (b'~', Some(n @ 17..=21)) => KeyCode::F(n - 11),
+        (b'~', Some(n @ 23..=24)) => KeyCode::F(n - 12),
+        _ => return None,
+    };
+
+    // A second parameter, if present, encodes modifiers.
+    let mut modifiers = KeyModifiers::NONE;
+    if let Some(mask) = params.get(1).map(|m| m.saturating_sub(1)) {
+        if mask & 1 != 0 {
+            modifiers |= KeyModifiers::SHIFT;
+        }
+        if mask & 2 != 0 {
+            modifiers |= KeyModifiers::ALT;
+        }
+        if mask & 4 != 0 {
+            modifiers |= KeyModifiers::CONTROL;
+        }
+    }
+
+    Some(((modifiers, code), len))
+}
+
+const fn utf8_char_len(first_byte: u8) -> usize {
+    match first_byte {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1,
+    }
+}
@@ -16,6 +16,9 @@ pub use options::Options;
 #[cfg(any(windows, unix))]
 mod completion;
 
+#[cfg(any(feature = "basic", feature = "reedline"))]
+mod key_bindings;
+
 // Reedline-based shell
 #[cfg(feature = "reedline")]
 mod reedline;
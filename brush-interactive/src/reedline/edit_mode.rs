@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use brush_core::readline;
+use crossterm::event::{Event, KeyEvent};
+use reedline::{EditCommand, ReedlineEvent};
+
+use crate::key_bindings::{self, KeyCombination, SequenceMatch};
+
+/// Prefixes the text reedline returns for a key bound to a shell command, to tell it apart
+/// from a line of input; a line can't be entered with a NUL character in it.
+const SHELL_COMMAND_MARKER: &str = "\0brush-shell-command:";
+
+/// Returns the shell command to run if the given text returned by reedline came from a
+/// key bound to one, rather than being a line of input.
+///
+/// # Arguments
+///
+/// * `text` - The text returned by reedline.
+pub(crate) fn bound_shell_command(text: &str) -> Option<&str> {
+    text.strip_prefix(SHELL_COMMAND_MARKER)
+}
+
+/// The effect of a key sequence bound by the shell's readline configuration.
+#[derive(Clone)]
+enum BoundAction {
+    /// Produces the given event.
+    Event(ReedlineEvent),
+    /// Acts as if the given key had been pressed instead; this is how vi modes are
+    /// switched, since only the wrapped edit mode can switch them.
+    Key(KeyCombination),
+}
+
+/// Edit mode that applies the shell's readline key bindings. Bindings of single keys are
+/// handled by the wrapped edit mode; bindings of multi-key sequences (e.g., `\C-x\C-e`) are
+/// matched here, with keys held back until it's clear whether they complete a sequence.
+pub(crate) struct ReadlineEditMode {
+    inner: Box<dyn reedline::EditMode>,
+    /// Multi-key sequences bound in emacs mode or in vi insert mode.
+    sequences: HashMap<Vec<KeyCombination>, BoundAction>,
+    /// Multi-key sequences bound in vi command mode.
+    vi_command_sequences: HashMap<Vec<KeyCombination>, BoundAction>,
+    pending: Vec<Event>,
+}
+
+impl reedline::EditMode for ReadlineEditMode {
+    fn parse_event(&mut self, event: reedline::ReedlineRawEvent) -> ReedlineEvent {
+        let event: Event = event.into();
+
+        let Some(key) = key_bindings::key_combination(&event) else {
+            return self.replay_pending(event);
+        };
+
+        let mut keys: Vec<_> = self
+            .pending
+            .iter()
+            .filter_map(key_bindings::key_combination)
+            .collect();
+        keys.push(key);
+
+        let sequences = match self.inner.edit_mode() {
+            reedline::PromptEditMode::Vi(reedline::PromptViMode::Normal) => {
+                &self.vi_command_sequences
+            }
+            _ => &self.sequences,
+        };
+
+        match key_bindings::match_key_sequence(sequences, &keys) {
+            SequenceMatch::Complete(action) => {
+                let action = action.clone();
+                self.pending.clear();
+                match action {
+                    BoundAction::Event(event) => event,
+                    BoundAction::Key(key) => reedline::ReedlineRawEvent::try_from(Event::Key(
+                        KeyEvent::new(key.1, key.0),
+                    ))
+                    .map_or(ReedlineEvent::None, |event| self.inner.parse_event(event)),
+                }
+            }
+            SequenceMatch::Prefix => {
+                self.pending.push(event);
+                ReedlineEvent::None
+            }
+            SequenceMatch::None => self.replay_pending(event),
+        }
+    }
+
+    fn edit_mode(&self) -> reedline::PromptEditMode {
+        self.inner.edit_mode()
+    }
+}
+
+impl ReadlineEditMode {
+    /// Passes along any held-back keys, followed by the given event, to the wrapped
+    /// edit mode.
+    fn replay_pending(&mut self, event: Event) -> ReedlineEvent {
+        let mut events: Vec<_> = std::mem::take(&mut self.pending)
+            .into_iter()
+            .chain(std::iter::once(event))
+            .filter_map(|event| reedline::ReedlineRawEvent::try_from(event).ok())
+            .map(|event| self.inner.parse_event(event))
+            .filter(|event| *event != ReedlineEvent::None)
+            .collect();
+
+        if events.len() > 1 {
+            ReedlineEvent::Multiple(events)
+        } else {
+            events.pop().unwrap_or(ReedlineEvent::None)
+        }
+    }
+}
+
+/// Creates an edit mode for the editing mode (emacs or vi) selected by the given
+/// configuration, applying the changes it makes to the default key bindings.
+///
+/// # Arguments
+///
+/// * `config` - The shell's readline configuration.
+/// * `completion_menu_name` - The name of the completion menu.
+pub(crate) fn create_edit_mode(
+    config: &readline::Config,
+    completion_menu_name: &str,
+) -> Box<dyn reedline::EditMode> {
+    if config.get_variable("editing-mode") == Some("vi") {
+        let mut insert_key_bindings = reedline::default_vi_insert_keybindings();
+        add_common_key_bindings(&mut insert_key_bindings, completion_menu_name);
+        let sequences = apply_binding_changes(
+            config,
+            readline::KeyMap::ViInsert,
+            &mut insert_key_bindings,
+            completion_menu_name,
+        );
+
+        let mut normal_key_bindings = reedline::default_vi_normal_keybindings();
+        let vi_command_sequences = apply_binding_changes(
+            config,
+            readline::KeyMap::ViCommand,
+            &mut normal_key_bindings,
+            completion_menu_name,
+        );
+
+        Box::new(ReadlineEditMode {
+            inner: Box::new(reedline::Vi::new(insert_key_bindings, normal_key_bindings)),
+            sequences,
+            vi_command_sequences,
+            pending: vec![],
+        })
+    } else {
+        let mut key_bindings = reedline::default_emacs_keybindings();
+        add_common_key_bindings(&mut key_bindings, completion_menu_name);
+
+        // Add comment.
+        key_bindings.add_binding(
+            reedline::KeyModifiers::ALT,
+            reedline::KeyCode::Char('#'),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Edit(vec![
+                    EditCommand::MoveToStart { select: false },
+                    EditCommand::InsertChar('#'),
+                ]),
+                ReedlineEvent::Enter,
+            ]),
+        );
+
+        let sequences = apply_binding_changes(
+            config,
+            readline::KeyMap::Emacs,
+            &mut key_bindings,
+            completion_menu_name,
+        );
+
+        Box::new(ReadlineEditMode {
+            inner: Box::new(reedline::Emacs::new(key_bindings)),
+            sequences,
+            vi_command_sequences: HashMap::new(),
+            pending: vec![],
+        })
+    }
+}
+
+/// Adds the completion and undo bindings used in both emacs mode and vi insert mode.
+fn add_common_key_bindings(key_bindings: &mut reedline::Keybindings, completion_menu_name: &str) {
+    // Wire up tab to completion.
+    key_bindings.add_binding(
+        reedline::KeyModifiers::NONE,
+        reedline::KeyCode::Tab,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Menu(completion_menu_name.to_string()),
+            ReedlineEvent::MenuNext,
+            ReedlineEvent::Edit(vec![EditCommand::Complete]),
+        ]),
+    );
+    // Wire up shift-tab for completion.
+    key_bindings.add_binding(
+        reedline::KeyModifiers::SHIFT,
+        reedline::KeyCode::BackTab,
+        ReedlineEvent::MenuPrevious,
+    );
+
+    // Add undo.
+    // TODO: We would prefer Ctrl+_ to match readline, but that doesn't seem to work.
+    key_bindings.add_binding(
+        reedline::KeyModifiers::ALT,
+        reedline::KeyCode::Char('_'),
+        ReedlineEvent::Edit(vec![EditCommand::Undo]),
+    );
+}
+
+/// Applies the changes the given configuration makes to the default bindings of a key
+/// map. Bindings of single keys are applied to the given key bindings where possible;
+/// other bindings (e.g., of multi-key sequences) are returned.
+fn apply_binding_changes(
+    config: &readline::Config,
+    keymap: readline::KeyMap,
+    key_bindings: &mut reedline::Keybindings,
+    completion_menu_name: &str,
+) -> HashMap<Vec<KeyCombination>, BoundAction> {
+    let mut sequences = HashMap::new();
+
+    for (key_sequence, action) in config.binding_changes(keymap) {
+        let Some(keys) = key_bindings::decode_key_sequence(key_sequence) else {
+            tracing::debug!(
+                "unsupported key sequence: {}",
+                readline::format_key_sequence(key_sequence)
+            );
+            continue;
+        };
+
+        // Switching to vi command mode is done by the wrapped edit mode when Esc is pressed.
+        if matches!(action, Some(readline::KeyAction::Function(f)) if f == "vi-movement-mode") {
+            let esc = (reedline::KeyModifiers::NONE, reedline::KeyCode::Esc);
+            sequences.insert(keys, BoundAction::Key(esc));
+            continue;
+        }
+
+        let event = match action {
+            Some(action) => {
+                let Some(event) = translate_action(action, completion_menu_name) else {
+                    tracing::debug!("unsupported key binding action: {action:?}");
+                    continue;
+                };
+                Some(event)
+            }
+            None => None,
+        };
+
+        match (keys.as_slice(), event) {
+            ([(modifiers, code)], Some(event)) => {
+                key_bindings.add_binding(*modifiers, *code, event);
+            }
+            ([(modifiers, code)], None) => {
+                key_bindings.remove_binding(*modifiers, *code);
+            }
+            (_, event) => {
+                let event = event.unwrap_or(ReedlineEvent::None);
+                sequences.insert(keys, BoundAction::Event(event));
+            }
+        }
+    }
+
+    sequences
+}
+
+/// Translates a readline key binding action to the equivalent reedline event, if there
+/// is one.
+fn translate_action(
+    action: &readline::KeyAction,
+    completion_menu_name: &str,
+) -> Option<ReedlineEvent> {
+    let function = match action {
+        readline::KeyAction::Function(function) => function.as_str(),
+        readline::KeyAction::Macro(text) => {
+            // Newlines in the macro's text accept the line.
+            let mut events = vec![];
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    events.push(ReedlineEvent::Enter);
+                }
+                if !line.is_empty() {
+                    events.push(edit(EditCommand::InsertString(line.to_owned())));
+                }
+            }
+            return Some(ReedlineEvent::Multiple(events));
+        }
+        readline::KeyAction::ShellCommand(command) => {
+            return Some(ReedlineEvent::ExecuteHostCommand(std::format!(
+                "{SHELL_COMMAND_MARKER}{command}"
+            )));
+        }
+    };
+
+    let event = match function {
+        "accept-line" => ReedlineEvent::Enter,
+        "backward-char" => ReedlineEvent::Left,
+        "backward-delete-char" => edit(EditCommand::Backspace),
+        "backward-kill-line" | "unix-line-discard" => edit(EditCommand::CutFromStart),
+        "backward-kill-word" => edit(EditCommand::CutWordLeft),
+        "backward-word" => edit(EditCommand::MoveWordLeft { select: false }),
+        "beginning-of-line" => edit(EditCommand::MoveToStart { select: false }),
+        "capitalize-word" => edit(EditCommand::CapitalizeChar),
+        "clear-display" => ReedlineEvent::ClearScrollback,
+        "clear-screen" => ReedlineEvent::ClearScreen,
+        "complete" => ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Menu(completion_menu_name.to_owned()),
+            ReedlineEvent::MenuNext,
+            edit(EditCommand::Complete),
+        ]),
+        "delete-char" => edit(EditCommand::Delete),
+        "downcase-word" => edit(EditCommand::LowercaseWord),
+        "edit-and-execute-command" => {
+            ReedlineEvent::Multiple(vec![ReedlineEvent::OpenEditor, ReedlineEvent::Enter])
+        }
+        "end-of-line" => edit(EditCommand::MoveToEnd { select: false }),
+        "forward-char" => ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::HistoryHintComplete,
+            ReedlineEvent::Right,
+        ]),
+        "forward-word" => edit(EditCommand::MoveWordRight { select: false }),
+        "insert-comment" => ReedlineEvent::Multiple(vec![
+            edit(EditCommand::MoveToStart { select: false }),
+            edit(EditCommand::InsertChar('#')),
+            ReedlineEvent::Enter,
+        ]),
+        "kill-line" => edit(EditCommand::CutToEnd),
+        "kill-whole-line" => ReedlineEvent::Edit(vec![
+            EditCommand::MoveToStart { select: false },
+            EditCommand::CutToEnd,
+        ]),
+        "kill-word" => edit(EditCommand::CutWordRight),
+        "menu-complete" => ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Menu(completion_menu_name.to_owned()),
+            ReedlineEvent::MenuNext,
+        ]),
+        "menu-complete-backward" => ReedlineEvent::MenuPrevious,
+        "next-history" | "history-search-forward" => {
+            ReedlineEvent::UntilFound(vec![ReedlineEvent::MenuDown, ReedlineEvent::Down])
+        }
+        "possible-completions" => ReedlineEvent::Menu(completion_menu_name.to_owned()),
+        "previous-history" | "history-search-backward" => {
+            ReedlineEvent::UntilFound(vec![ReedlineEvent::MenuUp, ReedlineEvent::Up])
+        }
+        "reverse-search-history" | "forward-search-history" => ReedlineEvent::SearchHistory,
+        "transpose-chars" => edit(EditCommand::SwapGraphemes),
+        "transpose-words" => edit(EditCommand::SwapWords),
+        "undo" => edit(EditCommand::Undo),
+        "unix-word-rubout" => edit(EditCommand::CutBigWordLeft),
+        "upcase-word" => edit(EditCommand::UppercaseWord),
+        "vi-undo" => edit(EditCommand::Undo),
+        "yank" => edit(EditCommand::PasteCutBufferBefore),
+        _ => return None,
+    };
+
+    Some(event)
+}
+
+fn edit(command: EditCommand) -> ReedlineEvent {
+    ReedlineEvent::Edit(vec![command])
+}
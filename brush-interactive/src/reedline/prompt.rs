@@ -1,32 +1,96 @@
+use brush_core::readline;
+
 use crate::interactive_shell::InteractivePrompt;
 
-impl reedline::Prompt for InteractivePrompt {
+/// Prompt displayed by reedline, which may also indicate the current editing mode (as with
+/// readline's `show-mode-in-prompt` variable).
+pub(crate) struct ReedlinePrompt {
+    pub prompt: InteractivePrompt,
+    /// Text indicating each editing mode, if the mode is to be shown.
+    pub mode_strings: Option<ModeStrings>,
+}
+
+/// Text displayed in the prompt to indicate each editing mode.
+pub(crate) struct ModeStrings {
+    emacs: String,
+    vi_insert: String,
+    vi_command: String,
+}
+
+impl ModeStrings {
+    /// Returns the text indicating each editing mode, per the given configuration, or
+    /// `None` if the mode isn't to be shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The shell's readline configuration.
+    pub fn from_config(config: &readline::Config) -> Option<Self> {
+        Some(Self {
+            emacs: config.mode_string(readline::KeyMap::Emacs)?,
+            vi_insert: config.mode_string(readline::KeyMap::ViInsert)?,
+            vi_command: config.mode_string(readline::KeyMap::ViCommand)?,
+        })
+    }
+}
+
+impl ReedlinePrompt {
+    /// Splits the prompt into the lines preceding its last line (including the final
+    /// newline) and its last line.
+    fn split_last_line(&self) -> (&str, &str) {
+        let prompt = self.prompt.prompt.as_str();
+        let end_of_previous_lines = prompt.rfind('\n').map_or(0, |i| i + 1);
+        prompt.split_at(end_of_previous_lines)
+    }
+}
+
+impl reedline::Prompt for ReedlinePrompt {
     fn render_prompt_left(&self) -> std::borrow::Cow<str> {
+        // A mode string is displayed at the start of the prompt's last line, but only the
+        // indicator knows the mode; so, in that case, the indicator displays the last line.
+        let prompt = if self.mode_strings.is_some() {
+            self.split_last_line().0
+        } else {
+            self.prompt.prompt.as_str()
+        };
+
         // [Workaround: see https://github.com/nushell/reedline/issues/707]
         // If the prompt starts with a newline character, then there's a chance
         // that it won't be rendered correctly. For this specific case, insert
         // an extra space character before the newline.
-        if self.prompt.starts_with('\n') {
-            std::format!(" {}", self.prompt).into()
+        if prompt.starts_with('\n') {
+            std::format!(" {prompt}").into()
         } else {
-            self.prompt.as_str().into()
+            prompt.into()
         }
     }
 
     fn render_prompt_right(&self) -> std::borrow::Cow<str> {
-        self.alt_side_prompt.as_str().into()
+        self.prompt.alt_side_prompt.as_str().into()
     }
 
-    // N.B. For now, we don't support prompt indicators.
     fn render_prompt_indicator(
         &self,
-        _prompt_mode: reedline::PromptEditMode,
+        prompt_mode: reedline::PromptEditMode,
     ) -> std::borrow::Cow<str> {
-        "".into()
+        let Some(mode_strings) = &self.mode_strings else {
+            return "".into();
+        };
+
+        let mode_string = match prompt_mode {
+            reedline::PromptEditMode::Vi(reedline::PromptViMode::Insert) => {
+                mode_strings.vi_insert.as_str()
+            }
+            reedline::PromptEditMode::Vi(reedline::PromptViMode::Normal) => {
+                mode_strings.vi_command.as_str()
+            }
+            _ => mode_strings.emacs.as_str(),
+        };
+
+        std::format!("{mode_string}{}", self.split_last_line().1).into()
     }
 
     fn render_prompt_multiline_indicator(&self) -> std::borrow::Cow<str> {
-        self.continuation_prompt.as_str().into()
+        self.prompt.continuation_prompt.as_str().into()
     }
 
     fn render_prompt_history_search_indicator(
@@ -56,7 +120,13 @@ impl reedline::Prompt for InteractivePrompt {
     }
 
     fn get_indicator_color(&self) -> reedline::Color {
-        reedline::Color::Cyan
+        // N.B. When the indicator displays part of the prompt, it's displayed like the rest
+        // of the prompt.
+        if self.mode_strings.is_some() {
+            self.get_prompt_color()
+        } else {
+            reedline::Color::Cyan
+        }
     }
 
     fn get_prompt_right_color(&self) -> reedline::Color {
@@ -1,16 +1,22 @@
+use crossterm::ExecutableCommand;
 use nu_ansi_term::Color;
 use reedline::MenuBuilder;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use super::{completer, highlighter, refs, validator};
-use crate::{interactive_shell::InteractivePrompt, InteractiveShell, ReadResult, ShellError};
+use super::{completer, edit_mode, highlighter, prompt, refs, validator};
+use crate::{
+    interactive_shell::InteractivePrompt, key_bindings, InteractiveShell, ReadResult, ShellError,
+};
 
 /// Represents an interactive shell capable of taking commands from standard input
 /// and reporting results to standard output and standard error streams.
 pub struct ReedlineShell {
     reedline: Option<reedline::Reedline>,
     shell: refs::ShellRef,
+    next_input: Option<String>,
+    /// Generation of the shell's readline configuration last applied to the editor.
+    readline_config_generation: u64,
 }
 
 const COMPLETION_MENU_NAME: &str = "completion_menu";
@@ -24,8 +30,19 @@ impl ReedlineShell {
     pub async fn new(options: &crate::Options) -> Result<ReedlineShell, ShellError> {
         // Set up shell first. Its initialization may influence how the
         // editor needs to operate.
-        let shell = brush_core::Shell::new(&options.shell).await?;
-        let history_file_path = shell.get_history_file_path();
+        let mut shell = brush_core::Shell::new(&options.shell).await?;
+
+        // Let builtins (e.g., `read -e`) read input with a line editor.
+        shell.line_editor = Some(Arc::new(ReedlineLineEditor {
+            disable_bracketed_paste: options.disable_bracketed_paste,
+            disable_color: options.disable_color,
+        }));
+
+        // Set up key bindings for the selected editing mode, applying any customizations
+        // made by the user's configuration.
+        shell.update_readline_editing_mode();
+        let edit_mode = edit_mode::create_edit_mode(&shell.readline_config, COMPLETION_MENU_NAME);
+        let readline_config_generation = shell.readline_config.generation();
 
         let shell_ref = Arc::new(Mutex::new(shell));
 
@@ -57,9 +74,6 @@ impl ReedlineShell {
                 .with_selected_match_text_style(Color::Blue.bold().reverse()),
         );
 
-        // Set up key bindings.
-        let key_bindings = compose_key_bindings(COMPLETION_MENU_NAME);
-
         // Set up default history-based hinter.
         let mut hinter = reedline::DefaultHinter::default();
         if !options.disable_color {
@@ -76,25 +90,24 @@ impl ReedlineShell {
             .with_validator(Box::new(validator))
             .with_hinter(Box::new(hinter))
             .with_menu(reedline::ReedlineMenu::EngineCompleter(completion_menu))
-            .with_edit_mode(Box::new(reedline::Emacs::new(key_bindings)));
+            .with_edit_mode(edit_mode);
 
         // If requested, apply some additional niceties.
         if !options.disable_highlighting && !options.disable_color {
             reedline = reedline.with_highlighter(Box::new(highlighter));
         }
 
-        // If we have a history file, wire it up.
-        if let Some(history_file_path) = history_file_path {
-            if let Ok(history) =
-                reedline::FileBackedHistory::with_file(reedline::HISTORY_SIZE, history_file_path)
-            {
-                reedline = reedline.with_history(Box::new(history));
-            }
+        // Set up an in-memory history; it gets populated from the shell's own history
+        // list before each line is read.
+        if let Ok(history) = reedline::FileBackedHistory::new(reedline::HISTORY_SIZE) {
+            reedline = reedline.with_history(Box::new(history));
         }
 
         Ok(ReedlineShell {
             reedline: Some(reedline),
             shell: shell_ref,
+            next_input: None,
+            readline_config_generation,
         })
     }
 }
@@ -137,65 +150,192 @@ impl InteractiveShell for ReedlineShell {
     ///
     /// * `prompt` - The prompt to display to the user.
     fn read_line(&mut self, prompt: InteractivePrompt) -> Result<ReadResult, ShellError> {
+        self.sync_history()?;
+        self.sync_readline_config();
+
+        let prompt = prompt::ReedlinePrompt {
+            prompt,
+            mode_strings: prompt::ModeStrings::from_config(
+                &self.shell.try_lock().unwrap().readline_config,
+            ),
+        };
+
         if let Some(reedline) = &mut self.reedline {
-            match reedline.read_line(&prompt) {
-                Ok(reedline::Signal::Success(s)) => Ok(ReadResult::Input(s)),
-                Ok(reedline::Signal::CtrlC) => Ok(ReadResult::Interrupted),
-                Ok(reedline::Signal::CtrlD) => Ok(ReadResult::Eof),
-                Err(err) => Err(ShellError::IoError(err)),
+            if let Some(next_input) = self.next_input.take() {
+                reedline.run_edit_commands(&[reedline::EditCommand::InsertString(next_input)]);
+            }
+
+            loop {
+                match reedline.read_line(&prompt).map_err(ShellError::IoError)? {
+                    reedline::Signal::Success(s) => {
+                        // Keys bound to shell commands suspend editing while the command
+                        // runs; editing then resumes with the (possibly updated) line.
+                        if let Some(command) = edit_mode::bound_shell_command(s.as_str()) {
+                            if run_bound_shell_command(reedline, &self.shell, command)? {
+                                return Ok(ReadResult::Eof);
+                            }
+                            continue;
+                        }
+                        return Ok(ReadResult::Input(s));
+                    }
+                    reedline::Signal::CtrlC => return Ok(ReadResult::Interrupted),
+                    reedline::Signal::CtrlD => return Ok(ReadResult::Eof),
+                }
             }
         } else {
             Ok(ReadResult::Eof)
         }
     }
 
-    /// Update history, if relevant.
-    fn update_history(&mut self) -> Result<(), ShellError> {
-        // N.B. With our current usage, reedline auto-updates the history file.
+    fn set_next_input(&mut self, text: &str) {
+        self.next_input = Some(text.to_owned());
+    }
+}
+
+impl ReedlineShell {
+    /// Replaces the contents of the editor's history with the shell's history list, which
+    /// may have been modified (e.g., by the `history` builtin) since the last line was read.
+    fn sync_history(&mut self) -> Result<(), ShellError> {
+        let Some(reedline) = &mut self.reedline else {
+            return Ok(());
+        };
+
+        let shell = self.shell.try_lock().unwrap();
+        let history = shell.history();
+
+        let editor_history = reedline.history_mut();
+        editor_history.clear().map_err(|e| to_io_error(&e))?;
+        for item in history
+            .iter()
+            .skip(history.len().saturating_sub(reedline::HISTORY_SIZE))
+        {
+            editor_history
+                .save(reedline::HistoryItem::from_command_line(
+                    item.command_line.as_str(),
+                ))
+                .map_err(|e| to_io_error(&e))?;
+        }
+
         Ok(())
     }
 }
 
-fn compose_key_bindings(completion_menu_name: &str) -> reedline::Keybindings {
-    let mut key_bindings = reedline::default_emacs_keybindings();
-
-    // Wire up tab to completion.
-    key_bindings.add_binding(
-        reedline::KeyModifiers::NONE,
-        reedline::KeyCode::Tab,
-        reedline::ReedlineEvent::UntilFound(vec![
-            reedline::ReedlineEvent::Menu(completion_menu_name.to_string()),
-            reedline::ReedlineEvent::MenuNext,
-            reedline::ReedlineEvent::Edit(vec![reedline::EditCommand::Complete]),
-        ]),
-    );
-    // Wire up shift-tab for completion.
-    key_bindings.add_binding(
-        reedline::KeyModifiers::SHIFT,
-        reedline::KeyCode::BackTab,
-        reedline::ReedlineEvent::MenuPrevious,
-    );
-
-    // Add undo.
-    // TODO: We would prefer Ctrl+_ to match readline, but that doesn't seem to work.
-    key_bindings.add_binding(
-        reedline::KeyModifiers::ALT,
-        reedline::KeyCode::Char('_'),
-        reedline::ReedlineEvent::Edit(vec![reedline::EditCommand::Undo]),
-    );
-
-    // Add comment.
-    key_bindings.add_binding(
-        reedline::KeyModifiers::ALT,
-        reedline::KeyCode::Char('#'),
-        reedline::ReedlineEvent::Multiple(vec![
-            reedline::ReedlineEvent::Edit(vec![
-                reedline::EditCommand::MoveToStart { select: false },
-                reedline::EditCommand::InsertChar('#'),
-            ]),
-            reedline::ReedlineEvent::Enter,
-        ]),
-    );
-
-    key_bindings
+impl ReedlineShell {
+    /// Reconfigures the editor to reflect the shell's readline configuration and editing
+    /// mode, which may have been modified (e.g., by the `bind` or `set` builtins) since the
+    /// last line was read. Also selects the external editor used by
+    /// `edit-and-execute-command`, which may have changed.
+    fn sync_readline_config(&mut self) {
+        let Some(mut reedline) = self.reedline.take() else {
+            return;
+        };
+
+        let mut shell = self.shell.try_lock().unwrap();
+
+        // Pick up any change to the editing mode made with `set -o`.
+        shell.update_readline_editing_mode();
+
+        let generation = shell.readline_config.generation();
+        if generation != self.readline_config_generation {
+            self.readline_config_generation = generation;
+            reedline = reedline.with_edit_mode(edit_mode::create_edit_mode(
+                &shell.readline_config,
+                COMPLETION_MENU_NAME,
+            ));
+        }
+
+        let editor = shell
+            .get_env_str("VISUAL")
+            .or_else(|| shell.get_env_str("EDITOR"))
+            .map_or_else(|| "vi".to_owned(), |s| s.into_owned());
+
+        let mut editor_words = editor.split_whitespace();
+        if let Some(program) = editor_words.next() {
+            let mut command = std::process::Command::new(program);
+            command.args(editor_words);
+
+            let temp_file =
+                std::env::temp_dir().join(std::format!("brush-edit-{}.sh", std::process::id()));
+            reedline = reedline.with_buffer_editor(command, temp_file);
+        }
+
+        self.reedline = Some(reedline);
+    }
+}
+
+/// Line editor made available to builtins. It uses its own reedline instance, without
+/// the completion, highlighting, or history support used when reading commands.
+struct ReedlineLineEditor {
+    disable_bracketed_paste: bool,
+    disable_color: bool,
+}
+
+impl brush_core::LineEditor for ReedlineLineEditor {
+    fn read_line(
+        &self,
+        prompt: &str,
+        initial_text: &str,
+    ) -> Result<Option<String>, brush_core::Error> {
+        let mut reedline = reedline::Reedline::create()
+            .with_ansi_colors(!self.disable_color)
+            .use_bracketed_paste(!self.disable_bracketed_paste)
+            .with_edit_mode(Box::new(reedline::Emacs::new(
+                reedline::default_emacs_keybindings(),
+            )));
+
+        reedline.run_edit_commands(&[reedline::EditCommand::InsertString(initial_text.to_owned())]);
+
+        let prompt = prompt::ReedlinePrompt {
+            prompt: InteractivePrompt {
+                prompt: prompt.to_owned(),
+                alt_side_prompt: String::new(),
+                continuation_prompt: String::new(),
+            },
+            mode_strings: None,
+        };
+
+        match reedline.read_line(&prompt)? {
+            reedline::Signal::Success(s) => Ok(Some(s)),
+            reedline::Signal::CtrlC | reedline::Signal::CtrlD => Ok(None),
+        }
+    }
+}
+
+/// Runs a shell command bound to a key, giving it access to the editor's buffer. Returns
+/// whether the shell should exit.
+fn run_bound_shell_command(
+    reedline: &mut reedline::Reedline,
+    shell: &refs::ShellRef,
+    command: &str,
+) -> Result<bool, ShellError> {
+    let mut buffer = brush_core::EditBuffer {
+        text: reedline.current_buffer_contents().to_owned(),
+        cursor: reedline.current_insertion_point(),
+    };
+
+    // Like readline, clear the line being edited so any output from the command isn't
+    // mixed in with it; the line is redisplayed when editing resumes.
+    std::io::stdout()
+        .execute(crossterm::cursor::MoveToColumn(0))?
+        .execute(crossterm::terminal::Clear(
+            crossterm::terminal::ClearType::CurrentLine,
+        ))?;
+
+    let mut shell = shell.try_lock().unwrap();
+    let exit_shell = key_bindings::run_shell_command_binding(&mut shell, command, &mut buffer)?;
+
+    reedline.run_edit_commands(&[
+        reedline::EditCommand::Clear,
+        reedline::EditCommand::InsertString(buffer.text),
+        reedline::EditCommand::MoveToPosition {
+            position: buffer.cursor,
+            select: false,
+        },
+    ]);
+
+    Ok(exit_shell)
+}
+
+fn to_io_error(err: &reedline::ReedlineError) -> ShellError {
+    ShellError::IoError(std::io::Error::other(err.to_string()))
 }
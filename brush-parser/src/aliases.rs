@@ -0,0 +1,233 @@
+//! Implements alias substitution over a stream of tokens, ahead of parsing.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::tokenizer::{tokenize_str_with_options, Token, TokenizerOptions};
+
+/// Tracks what kind of token we expect next, so we know where aliases may be substituted.
+#[derive(Clone, Copy)]
+enum Expecting {
+    /// The start of a command; the next word is eligible for alias substitution.
+    Command,
+    /// Arguments (or other non-command words) of a command.
+    Arguments,
+    /// The name of a function being defined with the `function` keyword.
+    FunctionName,
+    /// The word being matched by a `case` command, and the `in` that follows it.
+    CaseWord,
+    /// A pattern in a `case` command.
+    CasePattern,
+    /// The body of an extended test command (`[[ ... ]]`).
+    ExtendedTest,
+    /// The body of an arithmetic command or arithmetic `for` loop, with the current
+    /// parenthesis depth.
+    Arithmetic(usize),
+}
+
+/// A token awaiting alias substitution.
+struct PendingToken {
+    token: Token,
+    /// Names of the aliases whose substitution produced this token; they must not be
+    /// substituted again for it.
+    expanded_from: Vec<String>,
+    /// Whether this word follows an alias whose value ended with a blank, making it
+    /// eligible for substitution even outside of command position.
+    follows_blank_alias: bool,
+}
+
+/// Substitutes aliases in the given tokens, following bash's rules: the first word of a
+/// simple command is replaced by the tokens of its alias's value (which may contain
+/// reserved words or operators); an alias is not substituted again within its own
+/// replacement; and if an alias's value ends in a blank, the word following it is also
+/// checked for substitution.
+///
+/// # Arguments
+///
+/// * `tokens` - The tokens to substitute aliases in.
+/// * `aliases` - The aliases to substitute.
+/// * `options` - The options to use when tokenizing alias values.
+pub(crate) fn expand_aliases(
+    tokens: &[Token],
+    aliases: &BTreeMap<String, String>,
+    options: &TokenizerOptions,
+) -> Vec<Token> {
+    let mut input: VecDeque<_> = tokens
+        .iter()
+        .map(|token| PendingToken {
+            token: token.clone(),
+            expanded_from: vec![],
+            follows_blank_alias: false,
+        })
+        .collect();
+
+    let mut output = vec![];
+    let mut expecting = Expecting::Command;
+    let mut case_depth: usize = 0;
+    let mut words_to_skip = 0;
+
+    while let Some(pending) = input.pop_front() {
+        match &pending.token {
+            Token::Operator(op, loc) => {
+                // Adjacent open parentheses start an arithmetic command (or an arithmetic
+                // `for` loop's header).
+                let starts_arithmetic = op == "("
+                    && !matches!(
+                        expecting,
+                        Expecting::Arithmetic(_) | Expecting::ExtendedTest
+                    )
+                    && matches!(
+                        input.front().map(|p| &p.token),
+                        Some(Token::Operator(next, next_loc))
+                            if next == "(" && next_loc.start.index == loc.end.index
+                    );
+
+                words_to_skip = 0;
+                expecting = match (expecting, op.as_str()) {
+                    _ if starts_arithmetic => Expecting::Arithmetic(1),
+                    (Expecting::Arithmetic(depth), "(") => Expecting::Arithmetic(depth + 1),
+                    (Expecting::Arithmetic(1), ")") => Expecting::Arguments,
+                    (Expecting::Arithmetic(depth), ")") => Expecting::Arithmetic(depth - 1),
+                    (Expecting::Arithmetic(_) | Expecting::ExtendedTest, _) => expecting,
+                    // Redirection targets (and here-document bodies) aren't commands.
+                    (_, "<<" | "<<-") => {
+                        words_to_skip = 3;
+                        expecting
+                    }
+                    (_, op) if op.contains(['<', '>']) => {
+                        words_to_skip = 1;
+                        expecting
+                    }
+                    (Expecting::CasePattern, "(" | "|" | "\n") => Expecting::CasePattern,
+                    (Expecting::CaseWord | Expecting::FunctionName, "\n") => expecting,
+                    (_, ";;" | ";&" | ";;&") if case_depth > 0 => Expecting::CasePattern,
+                    _ => Expecting::Command,
+                };
+
+                output.push(pending.token);
+            }
+            Token::Word(w, _) => {
+                if words_to_skip > 0 {
+                    words_to_skip -= 1;
+                    output.push(pending.token);
+                    continue;
+                }
+
+                let eligible_for_alias = matches!(expecting, Expecting::Command)
+                    || (matches!(expecting, Expecting::Arguments) && pending.follows_blank_alias);
+
+                if eligible_for_alias && !pending.expanded_from.iter().any(|name| name == w) {
+                    if let Some(value) = aliases.get(w.as_str()) {
+                        if let Ok(replacement) = tokenize_str_with_options(value, options) {
+                            substitute_alias(&mut input, pending, replacement, value);
+                            continue;
+                        }
+                    }
+                }
+
+                expecting = match expecting {
+                    Expecting::Command => match w.as_str() {
+                        "!" | "{" | "coproc" | "do" | "elif" | "else" | "if" | "then" | "time"
+                        | "until" | "while" => Expecting::Command,
+                        "case" => {
+                            case_depth += 1;
+                            Expecting::CaseWord
+                        }
+                        "esac" => {
+                            case_depth = case_depth.saturating_sub(1);
+                            Expecting::Arguments
+                        }
+                        "function" => Expecting::FunctionName,
+                        "[[" => Expecting::ExtendedTest,
+                        w if crate::parser::parse_assignment_word(w).is_ok() => Expecting::Command,
+                        _ => Expecting::Arguments,
+                    },
+                    Expecting::FunctionName => Expecting::Command,
+                    Expecting::CaseWord if w == "in" => Expecting::CasePattern,
+                    Expecting::CasePattern if w == "esac" => {
+                        case_depth = case_depth.saturating_sub(1);
+                        Expecting::Arguments
+                    }
+                    Expecting::ExtendedTest if w == "]]" => Expecting::Arguments,
+                    _ => expecting,
+                };
+
+                output.push(pending.token);
+            }
+        }
+    }
+
+    output
+}
+
+/// Queues up the tokens of an alias's value in place of the word that named it.
+fn substitute_alias(
+    input: &mut VecDeque<PendingToken>,
+    pending: PendingToken,
+    replacement: Vec<Token>,
+    value: &str,
+) {
+    let Token::Word(name, loc) = pending.token else {
+        return;
+    };
+
+    if value.ends_with([' ', '\t']) {
+        if let Some(next) = input.front_mut() {
+            next.follows_blank_alias = true;
+        }
+    }
+
+    let mut expanded_from = pending.expanded_from;
+    expanded_from.push(name);
+
+    // Tokens from the alias's value are attributed to the location of the word it replaced.
+    for token in replacement.into_iter().rev() {
+        let token = match token {
+            Token::Operator(s, _) => Token::Operator(s, loc.clone()),
+            Token::Word(s, _) => Token::Word(s, loc.clone()),
+        };
+
+        input.push_front(PendingToken {
+            token,
+            expanded_from: expanded_from.clone(),
+            follows_blank_alias: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenize_str;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    fn expand(input: &str, aliases: &[(&str, &str)]) -> Result<Vec<String>> {
+        let aliases = aliases
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect();
+
+        let tokens = tokenize_str(input)?;
+        let expanded = expand_aliases(&tokens, &aliases, &TokenizerOptions::default());
+
+        Ok(expanded.iter().map(|t| t.to_str().to_owned()).collect())
+    }
+
+    #[test]
+    fn expand_command_position_only() -> Result<()> {
+        assert_eq!(
+            expand("ll ll; x=1 ll >ll", &[("ll", "ls -l")])?,
+            ["ls", "-l", "ll", ";", "x=1", "ls", "-l", ">", "ll"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn expand_recursive_and_trailing_blank() -> Result<()> {
+        assert_eq!(
+            expand("a b c", &[("a", "b "), ("b", "echo a x "), ("c", "see")])?,
+            ["echo", "a", "x", "echo", "a", "x", "see"]
+        );
+        Ok(())
+    }
+}
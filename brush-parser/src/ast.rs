@@ -4,9 +4,25 @@
 use std::fmt::{Display, Write};
 
 use crate::tokenizer;
+use crate::TokenLocation;
 
 const DISPLAY_INDENT: &str = "    ";
 
+/// Implemented by AST nodes that can report where in their source they were parsed from.
+pub trait SourceLocation {
+    /// Returns the location of the node in its source, if known.
+    fn location(&self) -> Option<TokenLocation>;
+}
+
+/// Combines the locations of the first and last elements of a node into a single span.
+fn span_of(first: Option<TokenLocation>, last: Option<TokenLocation>) -> Option<TokenLocation> {
+    match (first, last) {
+        (Some(first), Some(last)) => Some(TokenLocation::within(&first, &last)),
+        (Some(loc), None) | (None, Some(loc)) => Some(loc),
+        (None, None) => None,
+    }
+}
+
 /// Represents a complete shell program.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -16,6 +32,15 @@ pub struct Program {
     pub complete_commands: Vec<CompleteCommand>,
 }
 
+impl SourceLocation for Program {
+    fn location(&self) -> Option<TokenLocation> {
+        span_of(
+            self.complete_commands.first().and_then(|c| c.location()),
+            self.complete_commands.last().and_then(|c| c.location()),
+        )
+    }
+}
+
 impl Display for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for complete_command in &self.complete_commands {
@@ -62,6 +87,15 @@ pub struct AndOrList {
     pub additional: Vec<AndOr>,
 }
 
+impl SourceLocation for AndOrList {
+    fn location(&self) -> Option<TokenLocation> {
+        span_of(
+            self.first.location(),
+            self.additional.last().and_then(|a| a.location()),
+        )
+    }
+}
+
 impl Display for AndOrList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.first)?;
@@ -87,6 +121,14 @@ pub enum AndOr {
     Or(Pipeline),
 }
 
+impl SourceLocation for AndOr {
+    fn location(&self) -> Option<TokenLocation> {
+        match self {
+            AndOr::And(pipeline) | AndOr::Or(pipeline) => pipeline.location(),
+        }
+    }
+}
+
 impl Display for AndOr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -123,6 +165,15 @@ pub struct Pipeline {
     pub seq: Vec<Command>,
 }
 
+impl SourceLocation for Pipeline {
+    fn location(&self) -> Option<TokenLocation> {
+        span_of(
+            self.seq.first().and_then(|c| c.location()),
+            self.seq.last().and_then(|c| c.location()),
+        )
+    }
+}
+
 impl Display for Pipeline {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.bang {
@@ -152,7 +203,25 @@ pub enum Command {
     /// A command whose side effect is to define a shell function.
     Function(FunctionDefinition),
     /// A command that evaluates an extended test expression.
-    ExtendedTest(ExtendedTestExpr),
+    ExtendedTest(ExtendedTestExprCommand),
+    /// A coprocess, which runs a command asynchronously with its standard input and
+    /// output connected to the shell via pipes.
+    Coprocess(CoprocessCommand),
+}
+
+impl SourceLocation for Command {
+    fn location(&self) -> Option<TokenLocation> {
+        match self {
+            Command::Simple(simple_command) => simple_command.location(),
+            Command::Compound(compound_command, redirect_list) => span_of(
+                compound_command.location(),
+                redirect_list.as_ref().and_then(|r| r.location()),
+            ),
+            Command::Function(function_definition) => function_definition.location(),
+            Command::ExtendedTest(extended_test_command) => extended_test_command.location(),
+            Command::Coprocess(coprocess_command) => coprocess_command.location(),
+        }
+    }
 }
 
 impl Display for Command {
@@ -167,13 +236,44 @@ impl Display for Command {
                 Ok(())
             }
             Command::Function(function_definition) => write!(f, "{}", function_definition),
-            Command::ExtendedTest(extended_test_expr) => {
-                write!(f, "[[ {} ]]", extended_test_expr)
+            Command::ExtendedTest(extended_test_command) => {
+                write!(f, "{}", extended_test_command)
             }
+            Command::Coprocess(coprocess_command) => write!(f, "{}", coprocess_command),
         }
     }
 }
 
+/// A coprocess, which runs a command asynchronously with its standard input and
+/// output connected to the shell via pipes.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct CoprocessCommand {
+    /// The name of the coprocess, if one was explicitly given.
+    pub name: Option<String>,
+    /// The command run in the coprocess.
+    pub body: Box<Command>,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for CoprocessCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
+
+impl Display for CoprocessCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "coproc ")?;
+        if let Some(name) = &self.name {
+            write!(f, "{} ", name)?;
+        }
+        write!(f, "{}", self.body)
+    }
+}
+
 /// Represents a compound command, potentially made up of multiple nested commands.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -189,6 +289,8 @@ pub enum CompoundCommand {
     Subshell(SubshellCommand),
     /// A for clause, which loops over a set of values.
     ForClause(ForClauseCommand),
+    /// A select clause, which repeatedly prompts the user to choose from a menu of values.
+    SelectClause(SelectClauseCommand),
     /// A case clause, which selects a command based on a value and a set of
     /// pattern-based filters.
     CaseClause(CaseClauseCommand),
@@ -200,6 +302,22 @@ pub enum CompoundCommand {
     UntilClause(WhileOrUntilClauseCommand),
 }
 
+impl SourceLocation for CompoundCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        match self {
+            CompoundCommand::Arithmetic(c) => c.location(),
+            CompoundCommand::ArithmeticForClause(c) => c.location(),
+            CompoundCommand::BraceGroup(c) => c.location(),
+            CompoundCommand::Subshell(c) => c.location(),
+            CompoundCommand::ForClause(c) => c.location(),
+            CompoundCommand::SelectClause(c) => c.location(),
+            CompoundCommand::CaseClause(c) => c.location(),
+            CompoundCommand::IfClause(c) => c.location(),
+            CompoundCommand::WhileClause(c) | CompoundCommand::UntilClause(c) => c.location(),
+        }
+    }
+}
+
 impl Display for CompoundCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -212,6 +330,9 @@ impl Display for CompoundCommand {
             }
             CompoundCommand::Subshell(subshell_command) => write!(f, "{}", subshell_command),
             CompoundCommand::ForClause(for_clause_command) => write!(f, "{}", for_clause_command),
+            CompoundCommand::SelectClause(select_clause_command) => {
+                write!(f, "{}", select_clause_command)
+            }
             CompoundCommand::CaseClause(case_clause_command) => {
                 write!(f, "{}", case_clause_command)
             }
@@ -233,6 +354,14 @@ impl Display for CompoundCommand {
 pub struct ArithmeticCommand {
     /// The raw, unparsed and unexpanded arithmetic expression.
     pub expr: UnexpandedArithmeticExpr,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for ArithmeticCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for ArithmeticCommand {
@@ -245,12 +374,23 @@ impl Display for ArithmeticCommand {
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct SubshellCommand(pub CompoundList);
+pub struct SubshellCommand {
+    /// The commands to execute in the subshell.
+    pub list: CompoundList,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for SubshellCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
 
 impl Display for SubshellCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "( ")?;
-        write!(f, "{}", self.0)?;
+        write!(f, "{}", self.list)?;
         write!(f, " )")
     }
 }
@@ -266,6 +406,14 @@ pub struct ForClauseCommand {
     pub values: Option<Vec<Word>>,
     /// The command to run for each iteration of the loop.
     pub body: DoGroupCommand,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for ForClauseCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for ForClauseCommand {
@@ -288,6 +436,45 @@ impl Display for ForClauseCommand {
     }
 }
 
+/// A select clause, which repeatedly prompts the user to choose from a menu of values.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct SelectClauseCommand {
+    /// The name of the variable that receives the selected value.
+    pub variable_name: String,
+    /// The values offered in the menu.
+    pub values: Option<Vec<Word>>,
+    /// The command to run each time a selection is made.
+    pub body: DoGroupCommand,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for SelectClauseCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
+
+impl Display for SelectClauseCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "select {}", self.variable_name)?;
+
+        if let Some(values) = &self.values {
+            write!(f, " in")?;
+
+            for value in values {
+                write!(f, " {}", value)?;
+            }
+        }
+
+        writeln!(f, ";")?;
+
+        write!(f, "{}", self.body)
+    }
+}
+
 /// An arithmetic for clause, which loops until an arithmetic condition is reached.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -301,6 +488,14 @@ pub struct ArithmeticForClauseCommand {
     pub updater: Option<UnexpandedArithmeticExpr>,
     /// The command to run for each iteration of the loop.
     pub body: DoGroupCommand,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for ArithmeticForClauseCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for ArithmeticForClauseCommand {
@@ -339,6 +534,14 @@ pub struct CaseClauseCommand {
     pub value: Word,
     /// The individual case branches.
     pub cases: Vec<CaseItem>,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for CaseClauseCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for CaseClauseCommand {
@@ -358,6 +561,15 @@ impl Display for CaseClauseCommand {
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct CompoundList(pub Vec<CompoundListItem>);
 
+impl SourceLocation for CompoundList {
+    fn location(&self) -> Option<TokenLocation> {
+        span_of(
+            self.0.first().and_then(|item| item.location()),
+            self.0.last().and_then(|item| item.location()),
+        )
+    }
+}
+
 impl Display for CompoundList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for (i, item) in self.0.iter().enumerate() {
@@ -386,6 +598,12 @@ impl Display for CompoundList {
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct CompoundListItem(pub AndOrList, pub SeparatorOperator);
 
+impl SourceLocation for CompoundListItem {
+    fn location(&self) -> Option<TokenLocation> {
+        self.0.location()
+    }
+}
+
 impl Display for CompoundListItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)?;
@@ -405,6 +623,14 @@ pub struct IfClauseCommand {
     pub then: CompoundList,
     /// Optionally, `else` clauses that will be evaluated if the condition is false.
     pub elses: Option<Vec<ElseClause>>,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for IfClauseCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for IfClauseCommand {
@@ -437,6 +663,14 @@ pub struct ElseClause {
     pub condition: Option<CompoundList>,
     /// The commands to execute if this `else` clause is selected.
     pub body: CompoundList,
+    /// The location of the clause in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for ElseClause {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for ElseClause {
@@ -467,6 +701,14 @@ pub struct CaseItem {
     pub cmd: Option<CompoundList>,
     /// When the case branch is selected, the action to take after the command is executed.
     pub post_action: CaseItemPostAction,
+    /// The location of the case item in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for CaseItem {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for CaseItem {
@@ -517,11 +759,24 @@ impl Display for CaseItemPostAction {
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct WhileOrUntilClauseCommand(pub CompoundList, pub DoGroupCommand);
+pub struct WhileOrUntilClauseCommand {
+    /// The command whose execution result controls whether the loop continues.
+    pub condition: CompoundList,
+    /// The commands to execute on each iteration of the loop.
+    pub body: DoGroupCommand,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for WhileOrUntilClauseCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
 
 impl Display for WhileOrUntilClauseCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}; {}", self.0, self.1)
+        write!(f, "{}; {}", self.condition, self.body)
     }
 }
 
@@ -536,6 +791,14 @@ pub struct FunctionDefinition {
     pub body: FunctionBody,
     /// The source of the function definition.
     pub source: String,
+    /// The location of the definition in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for FunctionDefinition {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
 }
 
 impl Display for FunctionDefinition {
@@ -552,6 +815,15 @@ impl Display for FunctionDefinition {
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct FunctionBody(pub CompoundCommand, pub Option<RedirectList>);
 
+impl SourceLocation for FunctionBody {
+    fn location(&self) -> Option<TokenLocation> {
+        span_of(
+            self.0.location(),
+            self.1.as_ref().and_then(|r| r.location()),
+        )
+    }
+}
+
 impl Display for FunctionBody {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)?;
@@ -567,12 +839,27 @@ impl Display for FunctionBody {
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct BraceGroupCommand(pub CompoundList);
+pub struct BraceGroupCommand {
+    /// The commands in the group.
+    pub list: CompoundList,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for BraceGroupCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
 
 impl Display for BraceGroupCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "{{ ")?;
-        write!(indenter::indented(f).with_str(DISPLAY_INDENT), "{}", self.0)?;
+        write!(
+            indenter::indented(f).with_str(DISPLAY_INDENT),
+            "{}",
+            self.list
+        )?;
         writeln!(f)?;
         write!(f, "}}")?;
 
@@ -584,12 +871,27 @@ impl Display for BraceGroupCommand {
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct DoGroupCommand(pub CompoundList);
+pub struct DoGroupCommand {
+    /// The commands in the group.
+    pub list: CompoundList,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for DoGroupCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
 
 impl Display for DoGroupCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "do")?;
-        write!(indenter::indented(f).with_str(DISPLAY_INDENT), "{}", self.0)?;
+        write!(
+            indenter::indented(f).with_str(DISPLAY_INDENT),
+            "{}",
+            self.list
+        )?;
         writeln!(f)?;
         write!(f, "done")
     }
@@ -608,6 +910,23 @@ pub struct SimpleCommand {
     pub suffix: Option<CommandSuffix>,
 }
 
+impl SourceLocation for SimpleCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        let prefix = self
+            .prefix
+            .iter()
+            .flat_map(|p| p.0.iter().map(|i| i.location()));
+        let word = self.word_or_name.iter().map(|w| w.location());
+        let suffix = self
+            .suffix
+            .iter()
+            .flat_map(|s| s.0.iter().map(|i| i.location()));
+
+        let locs: Vec<_> = prefix.chain(word).chain(suffix).flatten().collect();
+        span_of(locs.first().cloned(), locs.last().cloned())
+    }
+}
+
 impl Display for SimpleCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut wrote_something = false;
@@ -715,6 +1034,19 @@ pub enum CommandPrefixOrSuffixItem {
     ProcessSubstitution(ProcessSubstitutionKind, SubshellCommand),
 }
 
+impl SourceLocation for CommandPrefixOrSuffixItem {
+    fn location(&self) -> Option<TokenLocation> {
+        match self {
+            CommandPrefixOrSuffixItem::IoRedirect(io_redirect) => io_redirect.location(),
+            CommandPrefixOrSuffixItem::Word(word)
+            | CommandPrefixOrSuffixItem::AssignmentWord(_, word) => word.location(),
+            CommandPrefixOrSuffixItem::ProcessSubstitution(_, subshell_command) => {
+                subshell_command.location()
+            }
+        }
+    }
+}
+
 impl Display for CommandPrefixOrSuffixItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -811,6 +1143,15 @@ impl Display for AssignmentValue {
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct RedirectList(pub Vec<IoRedirect>);
 
+impl SourceLocation for RedirectList {
+    fn location(&self) -> Option<TokenLocation> {
+        span_of(
+            self.0.first().and_then(|r| r.location()),
+            self.0.last().and_then(|r| r.location()),
+        )
+    }
+}
+
 impl Display for RedirectList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for item in &self.0 {
@@ -835,6 +1176,18 @@ pub enum IoRedirect {
     OutputAndError(Word, bool),
 }
 
+impl SourceLocation for IoRedirect {
+    fn location(&self) -> Option<TokenLocation> {
+        match self {
+            IoRedirect::File(_, _, target) => target.location(),
+            IoRedirect::HereDocument(_, here_doc) => here_doc.here_end.location(),
+            IoRedirect::HereString(_, word) | IoRedirect::OutputAndError(word, _) => {
+                word.location()
+            }
+        }
+    }
+}
+
 impl Display for IoRedirect {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -937,6 +1290,18 @@ pub enum IoFileRedirectTarget {
     ProcessSubstitution(ProcessSubstitutionKind, SubshellCommand),
 }
 
+impl SourceLocation for IoFileRedirectTarget {
+    fn location(&self) -> Option<TokenLocation> {
+        match self {
+            IoFileRedirectTarget::Filename(word) => word.location(),
+            IoFileRedirectTarget::Fd(_) => None,
+            IoFileRedirectTarget::ProcessSubstitution(_, subshell_command) => {
+                subshell_command.location()
+            }
+        }
+    }
+}
+
 impl Display for IoFileRedirectTarget {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1000,6 +1365,29 @@ impl Display for TestExpr {
     }
 }
 
+/// A command that evaluates an extended test expression.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct ExtendedTestExprCommand {
+    /// The expression to evaluate.
+    pub expr: ExtendedTestExpr,
+    /// The location of the command in its source.
+    pub loc: TokenLocation,
+}
+
+impl SourceLocation for ExtendedTestExprCommand {
+    fn location(&self) -> Option<TokenLocation> {
+        Some(self.loc.clone())
+    }
+}
+
+impl Display for ExtendedTestExprCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[[ {} ]]", self.expr)
+    }
+}
+
 /// An extended test expression.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -1205,6 +1593,14 @@ impl Display for BinaryPredicate {
 pub struct Word {
     /// Raw text of the word.
     pub value: String,
+    /// The location of the word in its source, if it was parsed from one.
+    pub loc: Option<TokenLocation>,
+}
+
+impl SourceLocation for Word {
+    fn location(&self) -> Option<TokenLocation> {
+        self.loc.clone()
+    }
 }
 
 impl Display for Word {
@@ -1216,11 +1612,13 @@ impl Display for Word {
 impl From<&tokenizer::Token> for Word {
     fn from(t: &tokenizer::Token) -> Word {
         match t {
-            tokenizer::Token::Word(value, _) => Word {
+            tokenizer::Token::Word(value, loc) => Word {
                 value: value.clone(),
+                loc: Some(loc.clone()),
             },
-            tokenizer::Token::Operator(value, _) => Word {
+            tokenizer::Token::Operator(value, loc) => Word {
                 value: value.clone(),
+                loc: Some(loc.clone()),
             },
         }
     }
@@ -1228,7 +1626,10 @@ impl From<&tokenizer::Token> for Word {
 
 impl From<String> for Word {
     fn from(s: String) -> Word {
-        Word { value: s }
+        Word {
+            value: s,
+            loc: None,
+        }
     }
 }
 
@@ -1237,6 +1638,7 @@ impl Word {
     pub fn new(s: &str) -> Self {
         Self {
             value: s.to_owned(),
+            loc: None,
         }
     }
 
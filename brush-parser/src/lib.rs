@@ -9,6 +9,7 @@ pub mod prompt;
 pub mod test_command;
 pub mod word;
 
+mod aliases;
 mod error;
 mod parser;
 mod tokenizer;
@@ -1,6 +1,12 @@
-use crate::ast::{self, SeparatorOperator};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::aliases;
+use crate::ast::{self, SeparatorOperator, SourceLocation};
 use crate::error;
-use crate::tokenizer::{Token, TokenEndReason, Tokenizer, TokenizerOptions, Tokens};
+use crate::tokenizer::{
+    SourcePosition, Token, TokenEndReason, TokenLocation, Tokenizer, TokenizerOptions, Tokens,
+};
 
 /// Options used to control the behavior of the parser.
 #[derive(Clone, Eq, Hash, PartialEq)]
@@ -13,6 +19,9 @@ pub struct ParserOptions {
     pub sh_mode: bool,
     /// Whether or not to perform tilde expansion.
     pub tilde_expansion: bool,
+    /// Aliases to substitute for the first words of simple commands, if alias
+    /// expansion is enabled; shared so that options can be cheaply built and cloned.
+    pub aliases: Option<Arc<BTreeMap<String, String>>>,
 }
 
 impl Default for ParserOptions {
@@ -22,6 +31,7 @@ impl Default for ParserOptions {
             posix_mode: false,
             sh_mode: false,
             tilde_expansion: true,
+            aliases: None,
         }
     }
 }
@@ -39,9 +49,15 @@ impl ParserOptions {
 
 /// Implements parsing for shell programs.
 pub struct Parser<R> {
-    reader: R,
+    reader: LineReader<R>,
     options: ParserOptions,
     source_info: SourceInfo,
+    /// Tokens from complete lines that do not yet form a complete command.
+    pending_tokens: Vec<Token>,
+    /// Number of compound commands opened but not yet closed by `pending_tokens`.
+    pending_depth: i32,
+    /// Position in the source of the next input to be tokenized.
+    position: SourcePosition,
 }
 
 impl<R: std::io::BufRead> Parser<R> {
@@ -54,9 +70,16 @@ impl<R: std::io::BufRead> Parser<R> {
     /// * `source_info` - Information about the source of the tokens.
     pub fn new(reader: R, options: &ParserOptions, source_info: &SourceInfo) -> Self {
         Parser {
-            reader,
+            reader: LineReader::new(reader),
             options: options.clone(),
             source_info: source_info.clone(),
+            pending_tokens: vec![],
+            pending_depth: 0,
+            position: SourcePosition {
+                index: 0,
+                line: source_info.start_line,
+                column: 1,
+            },
         }
     }
 
@@ -72,6 +95,7 @@ impl<R: std::io::BufRead> Parser<R> {
 
         // First we tokenize the input, according to the policy implied by provided options.
         let mut tokenizer = Tokenizer::new(&mut self.reader, &self.options.tokenizer_options());
+        tokenizer.set_start_position(&self.position);
 
         tracing::debug!(target: "tokenize", "Tokenizing...");
 
@@ -102,6 +126,241 @@ impl<R: std::io::BufRead> Parser<R> {
 
         parse_tokens(&tokens, &self.options, &self.source_info)
     }
+
+    /// Parses the next complete command from the input, reading only as many lines as
+    /// are needed to complete it. Each call uses the provided options, allowing changes
+    /// made by previously executed commands (e.g., `shopt -s extglob`) to take effect.
+    /// Returns `None` once the input has been exhausted.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options to use when parsing the command.
+    pub fn parse_next(
+        &mut self,
+        options: &ParserOptions,
+    ) -> Result<Option<ast::Program>, error::ParseError> {
+        self.options = options.clone();
+
+        let result = self.parse_next_impl();
+        if let Err(e) = &result {
+            // Discard whatever was left of the failing command.
+            if matches!(e, error::ParseError::Tokenizing { .. }) {
+                self.reader.discard_line();
+            }
+            self.pending_tokens.clear();
+            self.pending_depth = 0;
+        }
+
+        result
+    }
+
+    fn parse_next_impl(&mut self) -> Result<Option<ast::Program>, error::ParseError> {
+        // A single tokenizer is used for the whole command, so that tokens spanning many
+        // lines (e.g., quoted strings or here-documents) are only scanned once.
+        let mut tokenizer = Tokenizer::new(&mut self.reader, &self.options.tokenizer_options());
+        tokenizer.set_start_position(&self.position);
+
+        let result = parse_next_command(
+            &mut tokenizer,
+            &mut self.pending_tokens,
+            &mut self.pending_depth,
+            &self.options,
+            &self.source_info,
+        );
+
+        // The tokenizer may have read a character past the end of the command; hand it
+        // back so it's seen by the next one. (Input that couldn't be tokenized is discarded.)
+        let lookahead = if matches!(result, Err(error::ParseError::Tokenizing { .. })) {
+            None
+        } else {
+            tokenizer.peek_char().ok().flatten()
+        };
+        self.position = tokenizer.current_location().unwrap_or_default();
+
+        if let Some(c) = lookahead {
+            self.reader.unread(c);
+        }
+
+        result
+    }
+}
+
+/// Tokenizes lines until they form a complete command, and parses it.
+fn parse_next_command<R: std::io::BufRead>(
+    tokenizer: &mut Tokenizer<'_, R>,
+    pending_tokens: &mut Vec<Token>,
+    pending_depth: &mut i32,
+    options: &ParserOptions,
+    source_info: &SourceInfo,
+) -> Result<Option<ast::Program>, error::ParseError> {
+    loop {
+        let first_new_token = pending_tokens.len();
+        let at_end_of_input = tokenize_line(tokenizer, pending_tokens)?;
+        *pending_depth += compound_depth_change(pending_tokens, first_new_token);
+
+        if pending_tokens.is_empty() {
+            if at_end_of_input {
+                return Ok(None);
+            }
+            continue;
+        }
+
+        // Avoid repeatedly reparsing long compound commands (e.g., function definitions)
+        // line by line when they're still obviously open.
+        if !at_end_of_input && *pending_depth > 0 {
+            continue;
+        }
+
+        match parse_tokens(pending_tokens, options, source_info) {
+            Err(error::ParseError::ParsingAtEndOfInput) if !at_end_of_input => (),
+            result => {
+                pending_tokens.clear();
+                *pending_depth = 0;
+
+                let program = result?;
+                if !program.complete_commands.is_empty() {
+                    return Ok(Some(program));
+                } else if at_end_of_input {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Reads tokens through the end of the next line of input, appending them to the given
+/// tokens; returns whether the end of the input was reached. A line only ends at an
+/// unescaped newline outside of any quotes or here-documents.
+fn tokenize_line<R: std::io::BufRead>(
+    tokenizer: &mut Tokenizer<'_, R>,
+    tokens: &mut Vec<Token>,
+) -> Result<bool, error::ParseError> {
+    loop {
+        let result = tokenizer
+            .next_token()
+            .map_err(|e| error::ParseError::Tokenizing {
+                inner: e,
+                position: tokenizer.current_location(),
+            })?;
+
+        let at_end_of_line = matches!(&result.token, Some(Token::Operator(op, _)) if op == "\n")
+            && !tokenizer.has_pending_tokens();
+
+        if let Some(token) = result.token {
+            tokens.push(token);
+        }
+
+        if matches!(result.reason, TokenEndReason::EndOfInput) {
+            return Ok(true);
+        } else if at_end_of_line {
+            return Ok(false);
+        }
+    }
+}
+
+/// Heuristically computes how many more compound commands the tokens starting at the given
+/// index open than they close, looking only at reserved words in positions where they would
+/// be recognized. Tokens before that index are only consulted as context.
+fn compound_depth_change(tokens: &[Token], start: usize) -> i32 {
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        let Token::Word(w, _) = token else {
+            continue;
+        };
+
+        let preceding = |n: usize| i.checked_sub(n).map(|j| &tokens[j]);
+        let in_command_position = match preceding(1) {
+            None => true,
+            Some(Token::Operator(op, _)) => !op.contains(['<', '>']),
+            Some(Token::Word(prev, _)) => {
+                matches!(
+                    prev.as_str(),
+                    "!" | "{"
+                        | "coproc"
+                        | "do"
+                        | "elif"
+                        | "else"
+                        | "if"
+                        | "then"
+                        | "time"
+                        | "until"
+                        | "while"
+                ) || (w == "{"
+                    && matches!(
+                        preceding(2),
+                        Some(Token::Word(kw, _)) if kw == "function" || kw == "coproc"
+                    ))
+            }
+        };
+
+        if in_command_position {
+            match w.as_str() {
+                "{" | "case" | "do" | "if" => depth += 1,
+                "}" | "esac" | "done" | "fi" => depth -= 1,
+                _ => (),
+            }
+        }
+    }
+
+    depth
+}
+
+/// Reads input a line at a time, as it's needed, and allows a character to be handed back
+/// after reading it.
+struct LineReader<R> {
+    inner: R,
+    line: Vec<u8>,
+    consumed: usize,
+}
+
+impl<R: std::io::BufRead> LineReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            line: vec![],
+            consumed: 0,
+        }
+    }
+
+    /// Places the given character back at the front of the unread input.
+    fn unread(&mut self, c: char) {
+        let mut buf = [0; 4];
+        let bytes = c.encode_utf8(&mut buf).as_bytes();
+        self.line
+            .splice(self.consumed..self.consumed, bytes.iter().copied());
+    }
+
+    /// Discards the rest of the current line of input.
+    fn discard_line(&mut self) {
+        self.line.clear();
+        self.consumed = 0;
+    }
+}
+
+impl<R: std::io::BufRead> std::io::Read for LineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = std::io::BufRead::fill_buf(self)?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        std::io::BufRead::consume(self, len);
+        Ok(len)
+    }
+}
+
+impl<R: std::io::BufRead> std::io::BufRead for LineReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.consumed >= self.line.len() {
+            self.discard_line();
+            self.inner.read_until(b'\n', &mut self.line)?;
+        }
+
+        Ok(&self.line[self.consumed..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed = (self.consumed + amt).min(self.line.len());
+    }
 }
 
 /// Parses a sequence of tokens into the abstract syntax tree (AST) of a shell program.
@@ -116,6 +375,16 @@ pub fn parse_tokens(
     options: &ParserOptions,
     source_info: &SourceInfo,
 ) -> Result<ast::Program, error::ParseError> {
+    let expanded_tokens;
+    let tokens = match &options.aliases {
+        Some(alias_map) if !alias_map.is_empty() => {
+            expanded_tokens =
+                aliases::expand_aliases(tokens, alias_map, &options.tokenizer_options());
+            &expanded_tokens
+        }
+        _ => tokens,
+    };
+
     let parse_result = token_parser::program(&Tokens { tokens }, options, source_info);
 
     let result = match parse_result {
@@ -196,10 +465,21 @@ impl<'a> peg::ParseSlice<'a> for Tokens<'a> {
 }
 
 /// Information about the source of tokens.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct SourceInfo {
     /// The source of the tokens.
     pub source: String,
+    /// The 1-based line number at which the tokens start within their source.
+    pub start_line: i32,
+}
+
+impl Default for SourceInfo {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            start_line: 1,
+        }
+    }
 }
 
 peg::parser! {
@@ -276,6 +556,8 @@ peg::parser! {
         // N.B. We needed to move the function definition branch up to avoid conflicts with array assignment syntax.
         rule command() -> ast::Command =
             f:function_definition() { ast::Command::Function(f) } /
+            // N.B. Coprocesses are bash extensions.
+            non_posix_extensions_enabled() c:coprocess() { ast::Command::Coprocess(c) } /
             c:simple_command() { ast::Command::Simple(c) } /
             c:compound_command() r:redirect_list()? { ast::Command::Compound(c, r) } /
             // N.B. Extended test commands are bash extensions.
@@ -284,6 +566,7 @@ peg::parser! {
 
         // N.B. The arithmetic command is a non-sh extension.
         // N.B. The arithmetic for clause command is a non-sh extension.
+        // N.B. The select clause command is a non-sh extension.
         pub(crate) rule compound_command() -> ast::CompoundCommand =
             non_posix_extensions_enabled() a:arithmetic_command() { ast::CompoundCommand::Arithmetic(a) } /
             b:brace_group() { ast::CompoundCommand::BraceGroup(b) } /
@@ -294,11 +577,12 @@ peg::parser! {
             w:while_clause() { ast::CompoundCommand::WhileClause(w) } /
             u:until_clause() { ast::CompoundCommand::UntilClause(u) } /
             non_posix_extensions_enabled() c:arithmetic_for_clause() { ast::CompoundCommand::ArithmeticForClause(c) } /
+            non_posix_extensions_enabled() s:select_clause() { ast::CompoundCommand::SelectClause(s) } /
             expected!("compound command")
 
         pub(crate) rule arithmetic_command() -> ast::ArithmeticCommand =
-            specific_operator("(") specific_operator("(") expr:arithmetic_expression() specific_operator(")") specific_operator(")") {
-                ast::ArithmeticCommand { expr }
+            start:specific_operator("(") specific_operator("(") expr:arithmetic_expression() specific_operator(")") end:specific_operator(")") {
+                ast::ArithmeticCommand { expr, loc: TokenLocation::within(start.location(), end.location()) }
             }
 
         pub(crate) rule arithmetic_expression() -> ast::UnexpandedArithmeticExpr =
@@ -314,7 +598,9 @@ peg::parser! {
             specific_operator(";") {}
 
         rule subshell() -> ast::SubshellCommand =
-            specific_operator("(") c:compound_list() specific_operator(")") { ast::SubshellCommand(c) }
+            start:specific_operator("(") list:compound_list() end:specific_operator(")") {
+                ast::SubshellCommand { list, loc: TokenLocation::within(start.location(), end.location()) }
+            }
 
         rule compound_list() -> ast::CompoundList =
             linebreak() first:and_or() remainder:(s:separator() l:and_or() { (s, l) })* last_sep:separator()? {
@@ -339,16 +625,48 @@ peg::parser! {
             }
 
         rule for_clause() -> ast::ForClauseCommand =
-            specific_word("for") n:name() linebreak() _in() w:wordlist()? sequential_sep() d:do_group() {
-                ast::ForClauseCommand { variable_name: n.to_owned(), values: w, body: d }
+            s:specific_word("for") n:name() linebreak() _in() w:wordlist()? sequential_sep() d:do_group() {
+                let loc = TokenLocation::within(s.location(), &d.loc);
+                ast::ForClauseCommand { variable_name: n.to_owned(), values: w, body: d, loc }
             } /
-            specific_word("for") n:name() sequential_sep()? d:do_group() {
-                ast::ForClauseCommand { variable_name: n.to_owned(), values: None, body: d }
+            s:specific_word("for") n:name() sequential_sep()? d:do_group() {
+                let loc = TokenLocation::within(s.location(), &d.loc);
+                ast::ForClauseCommand { variable_name: n.to_owned(), values: None, body: d, loc }
+            }
+
+        // N.B. Only a compound command may follow an explicit coprocess name; otherwise, the
+        // first word is taken to be the start of a simple command.
+        rule coprocess() -> ast::CoprocessCommand =
+            s:specific_word("coproc") n:name() c:compound_command() r:redirect_list()? {
+                let body = ast::Command::Compound(c, r);
+                let loc = span_from_token(s, body.location());
+                ast::CoprocessCommand { name: Some(n.to_owned()), body: Box::new(body), loc }
+            } /
+            s:specific_word("coproc") c:compound_command() r:redirect_list()? {
+                let body = ast::Command::Compound(c, r);
+                let loc = span_from_token(s, body.location());
+                ast::CoprocessCommand { name: None, body: Box::new(body), loc }
+            } /
+            s:specific_word("coproc") c:simple_command() {
+                let body = ast::Command::Simple(c);
+                let loc = span_from_token(s, body.location());
+                ast::CoprocessCommand { name: None, body: Box::new(body), loc }
+            }
+
+        // N.B. The select loop is a non-sh extension.
+        pub(crate) rule select_clause() -> ast::SelectClauseCommand =
+            s:specific_word("select") n:name() linebreak() _in() w:wordlist()? sequential_sep() d:do_group() {
+                let loc = TokenLocation::within(s.location(), &d.loc);
+                ast::SelectClauseCommand { variable_name: n.to_owned(), values: Some(w.unwrap_or_default()), body: d, loc }
+            } /
+            s:specific_word("select") n:name() sequential_sep()? d:do_group() {
+                let loc = TokenLocation::within(s.location(), &d.loc);
+                ast::SelectClauseCommand { variable_name: n.to_owned(), values: None, body: d, loc }
             }
 
         // N.B. The arithmetic for loop is a non-sh extension.
         rule arithmetic_for_clause() -> ast::ArithmeticForClauseCommand =
-            specific_word("for")
+            s:specific_word("for")
             specific_operator("(") specific_operator("(")
                 initializer:arithmetic_expression()? specific_operator(";")
                 condition:arithmetic_expression()? specific_operator(";")
@@ -356,11 +674,14 @@ peg::parser! {
             specific_operator(")") specific_operator(")")
             sequential_sep()
             body:do_group() {
-                ast::ArithmeticForClauseCommand { initializer, condition, updater, body }
+                let loc = TokenLocation::within(s.location(), &body.loc);
+                ast::ArithmeticForClauseCommand { initializer, condition, updater, body, loc }
             }
 
-        rule extended_test_command() -> ast::ExtendedTestExpr =
-            specific_word("[[") linebreak() e:extended_test_expression() linebreak() specific_word("]]") { e }
+        rule extended_test_command() -> ast::ExtendedTestExprCommand =
+            s:specific_word("[[") linebreak() expr:extended_test_expression() linebreak() e:specific_word("]]") {
+                ast::ExtendedTestExprCommand { expr, loc: TokenLocation::within(s.location(), e.location()) }
+            }
 
         rule extended_test_expression() -> ast::ExtendedTestExpr = precedence! {
             left:(@) linebreak() specific_operator("||") linebreak() right:@ { ast::ExtendedTestExpr::Or(Box::from(left), Box::from(right)) }
@@ -431,17 +752,23 @@ peg::parser! {
         // N.B. For some reason we seem to need to allow a select subset
         // of unescaped operators in regex words.
         rule regex_word() -> ast::Word =
-            value:$((!specific_word("]]") regex_word_piece())+) {
-                ast::Word { value }
+            first:&[_] last:&regex_word_pieces() value:$(regex_word_pieces()) {
+                ast::Word { value, loc: Some(TokenLocation::within(first.location(), last.location())) }
+            }
+
+        // N.B. Yields the last token of the pieces.
+        rule regex_word_pieces() -> &'input Token =
+            pieces:(!specific_word("]]") p:regex_word_piece() { p })+ {?
+                pieces.last().copied().ok_or("regex word")
             }
 
-        rule regex_word_piece() =
-            word() {} /
-            specific_operator("|") {} /
-            specific_operator("(") parenthesized_regex_word()* specific_operator(")") {}
+        rule regex_word_piece() -> &'input Token =
+            w:word() { w } /
+            p:specific_operator("|") { p } /
+            specific_operator("(") parenthesized_regex_word()* p:specific_operator(")") { p }
 
         rule parenthesized_regex_word() =
-            regex_word_piece() /
+            regex_word_piece() {} /
             !specific_operator(")") !specific_operator("]]") [_]
 
         rule name() -> &'input str =
@@ -456,30 +783,35 @@ peg::parser! {
 
         // TODO: validate if this should call non_reserved_word() or word()
         pub(crate) rule case_clause() -> ast::CaseClauseCommand =
-            specific_word("case") w:non_reserved_word() linebreak() _in() linebreak() first_items:case_item()* last_item:case_item_ns()? specific_word("esac") {
+            s:specific_word("case") w:non_reserved_word() linebreak() _in() linebreak() first_items:case_item()* last_item:case_item_ns()? e:specific_word("esac") {
                 let mut cases = first_items;
 
                 if let Some(last_item) = last_item {
                     cases.push(last_item);
                 }
 
-                ast::CaseClauseCommand { value: ast::Word::from(w), cases }
+                let loc = TokenLocation::within(s.location(), e.location());
+                ast::CaseClauseCommand { value: ast::Word::from(w), cases, loc }
             }
 
         pub(crate) rule case_item_ns() -> ast::CaseItem =
-            specific_operator("(")? p:pattern() specific_operator(")") c:compound_list() {
-                ast::CaseItem { patterns: p, cmd: Some(c), post_action: ast::CaseItemPostAction::ExitCase }
+            s:&[_] specific_operator("(")? p:pattern() specific_operator(")") c:compound_list() {
+                let loc = span_from_token(s, c.location());
+                ast::CaseItem { patterns: p, cmd: Some(c), post_action: ast::CaseItemPostAction::ExitCase, loc }
             } /
-            specific_operator("(")? p:pattern() specific_operator(")") linebreak() {
-                ast::CaseItem { patterns: p, cmd: None, post_action: ast::CaseItemPostAction::ExitCase }
+            s:&[_] specific_operator("(")? p:pattern() e:specific_operator(")") linebreak() {
+                let loc = TokenLocation::within(s.location(), e.location());
+                ast::CaseItem { patterns: p, cmd: None, post_action: ast::CaseItemPostAction::ExitCase, loc }
             }
 
         pub(crate) rule case_item() -> ast::CaseItem =
-            specific_operator("(")? p:pattern() specific_operator(")") linebreak() post_action:case_item_post_action() linebreak() {
-                ast::CaseItem { patterns: p, cmd: None, post_action }
+            s:&[_] specific_operator("(")? p:pattern() specific_operator(")") linebreak() e:&[_] post_action:case_item_post_action() linebreak() {
+                let loc = TokenLocation::within(s.location(), e.location());
+                ast::CaseItem { patterns: p, cmd: None, post_action, loc }
             } /
-            specific_operator("(")? p:pattern() specific_operator(")") c:compound_list() post_action:case_item_post_action() linebreak() {
-                ast::CaseItem { patterns: p, cmd: Some(c), post_action }
+            s:&[_] specific_operator("(")? p:pattern() specific_operator(")") c:compound_list() e:&[_] post_action:case_item_post_action() linebreak() {
+                let loc = TokenLocation::within(s.location(), e.location());
+                ast::CaseItem { patterns: p, cmd: Some(c), post_action, loc }
             }
 
         rule case_item_post_action() -> ast::CaseItemPostAction =
@@ -497,12 +829,13 @@ peg::parser! {
         rule pattern() -> Vec<ast::Word> =
             (w:word() { ast::Word::from(w) }) ++ specific_operator("|")
 
-        rule if_clause() -> ast::IfClauseCommand =
-            specific_word("if") condition:compound_list() specific_word("then") then:compound_list() elses:else_part()? specific_word("fi") {
+        pub(crate) rule if_clause() -> ast::IfClauseCommand =
+            s:specific_word("if") condition:compound_list() specific_word("then") then:compound_list() elses:else_part()? e:specific_word("fi") {
                 ast::IfClauseCommand {
                     condition,
                     then,
                     elses,
+                    loc: TokenLocation::within(s.location(), e.location()),
                 }
             }
 
@@ -522,28 +855,38 @@ peg::parser! {
             e:_unconditional_else_part() { vec![e] }
 
         rule _conditional_else_part() -> ast::ElseClause =
-            specific_word("elif") condition:compound_list() specific_word("then") body:compound_list() {
-                ast::ElseClause { condition: Some(condition), body }
+            s:specific_word("elif") condition:compound_list() specific_word("then") body:compound_list() {
+                let loc = span_from_token(s, body.location());
+                ast::ElseClause { condition: Some(condition), body, loc }
             }
 
         rule _unconditional_else_part() -> ast::ElseClause =
-            specific_word("else") body:compound_list() {
-                ast::ElseClause { condition: None, body }
+            s:specific_word("else") body:compound_list() {
+                let loc = span_from_token(s, body.location());
+                ast::ElseClause { condition: None, body, loc }
              }
 
-        rule while_clause() -> ast::WhileOrUntilClauseCommand =
-            specific_word("while") c:compound_list() d:do_group() { ast::WhileOrUntilClauseCommand(c, d) }
+        pub(crate) rule while_clause() -> ast::WhileOrUntilClauseCommand =
+            s:specific_word("while") condition:compound_list() body:do_group() {
+                let loc = TokenLocation::within(s.location(), &body.loc);
+                ast::WhileOrUntilClauseCommand { condition, body, loc }
+            }
 
         rule until_clause() -> ast::WhileOrUntilClauseCommand =
-            specific_word("until") c:compound_list() d:do_group() { ast::WhileOrUntilClauseCommand(c, d) }
+            s:specific_word("until") condition:compound_list() body:do_group() {
+                let loc = TokenLocation::within(s.location(), &body.loc);
+                ast::WhileOrUntilClauseCommand { condition, body, loc }
+            }
 
         // N.B. Non-sh extensions allows use of the 'function' word to indicate a function definition.
-        rule function_definition() -> ast::FunctionDefinition =
-            specific_word("function")? fname:fname() specific_operator("(") specific_operator(")") linebreak() body:function_body() {
-                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone() }
+        pub(crate) rule function_definition() -> ast::FunctionDefinition =
+            s:&[_] specific_word("function")? fname:fname() specific_operator("(") specific_operator(")") linebreak() body:function_body() {
+                let loc = span_from_token(s, body.location());
+                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone(), loc }
             } /
-            specific_word("function") fname:fname() linebreak() body:function_body() {
-                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone() }
+            s:specific_word("function") fname:fname() linebreak() body:function_body() {
+                let loc = span_from_token(s, body.location());
+                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone(), loc }
             } /
             expected!("function definition")
 
@@ -557,10 +900,14 @@ peg::parser! {
             w:[Token::Word(word, _) if !word.ends_with('=')] { w.to_str() }
 
         rule brace_group() -> ast::BraceGroupCommand =
-            specific_word("{") c:compound_list() specific_word("}") { ast::BraceGroupCommand(c) }
+            s:specific_word("{") list:compound_list() e:specific_word("}") {
+                ast::BraceGroupCommand { list, loc: TokenLocation::within(s.location(), e.location()) }
+            }
 
         rule do_group() -> ast::DoGroupCommand =
-            specific_word("do") c:compound_list() specific_word("done") { ast::DoGroupCommand(c) }
+            s:specific_word("do") list:compound_list() e:specific_word("done") {
+                ast::DoGroupCommand { list, loc: TokenLocation::within(s.location(), e.location()) }
+            }
 
         rule simple_command() -> ast::SimpleCommand =
             prefix:cmd_prefix() word_and_suffix:(word_or_name:cmd_word() suffix:cmd_suffix()? { (word_or_name, suffix) })? {
@@ -736,6 +1083,7 @@ peg::parser! {
         rule non_posix_reserved_word_token() -> &'input Token =
             specific_word("[[") /
             specific_word("]]") /
+            specific_word("coproc") /
             specific_word("function") /
             specific_word("select")
 
@@ -744,7 +1092,7 @@ peg::parser! {
         }
 
         pub(crate) rule assignment_word() -> (ast::Assignment, ast::Word) =
-            non_posix_extensions_enabled() [Token::Word(w, _)] specific_operator("(") elements:array_elements() specific_operator(")") {?
+            non_posix_extensions_enabled() [Token::Word(w, start_loc)] specific_operator("(") elements:array_elements() end:specific_operator(")") {?
                 let parsed = parse_array_assignment(w.as_str(), elements.as_slice())?;
 
                 let mut all_as_word = w.to_owned();
@@ -757,11 +1105,12 @@ peg::parser! {
                 }
                 all_as_word.push(')');
 
-                Ok((parsed, ast::Word { value: all_as_word }))
+                let loc = TokenLocation::within(start_loc, end.location());
+                Ok((parsed, ast::Word { value: all_as_word, loc: Some(loc) }))
             } /
-            [Token::Word(w, _)] {?
+            [Token::Word(w, loc)] {?
                 let parsed = parse_assignment_word(w.as_str())?;
-                Ok((parsed, ast::Word { value: w.to_owned() }))
+                Ok((parsed, ast::Word { value: w.to_owned(), loc: Some(loc.clone()) }))
             }
 
         rule array_elements() -> Vec<&'input String> =
@@ -844,11 +1193,11 @@ peg::parser! {
             ['_' | 'a'..='z' | 'A'..='Z'] {}
 
         rule scalar_value() -> ast::AssignmentValue =
-            v:$([_]*) { ast::AssignmentValue::Scalar(ast::Word { value: v.to_owned() }) }
+            v:$([_]*) { ast::AssignmentValue::Scalar(ast::Word::new(v)) }
     }
 }
 
-fn parse_assignment_word(word: &str) -> Result<ast::Assignment, &'static str> {
+pub(crate) fn parse_assignment_word(word: &str) -> Result<ast::Assignment, &'static str> {
     let parse_result = assignments::name_and_scalar_value(word);
     parse_result.map_err(|_| "not assignment word")
 }
@@ -882,11 +1231,20 @@ fn add_pipe_extension_redirection(c: &mut ast::Command) -> Result<(), &'static s
         ast::Command::Compound(_, l) => add_to_redirect_list(l, r),
         ast::Command::Function(f) => add_to_redirect_list(&mut f.body.1, r),
         ast::Command::ExtendedTest(_) => return Err("|& unimplemented for extended tests"),
+        ast::Command::Coprocess(_) => return Err("|& unimplemented for coprocesses"),
     };
 
     Ok(())
 }
 
+// returns the location spanning from the given token to the end of the given location, if any
+fn span_from_token(start: &Token, end: Option<TokenLocation>) -> TokenLocation {
+    match end {
+        Some(end) => TokenLocation::within(start.location(), &end),
+        None => start.location().clone(),
+    }
+}
+
 fn locations_are_contiguous(
     loc_left: &crate::TokenLocation,
     loc_right: &crate::TokenLocation,
@@ -932,6 +1290,28 @@ mod tests {
     use anyhow::Result;
     use assert_matches::assert_matches;
 
+    fn loc(index: i32, line: i32, column: i32, len: i32) -> TokenLocation {
+        TokenLocation {
+            start: SourcePosition {
+                index,
+                line,
+                column,
+            },
+            end: SourcePosition {
+                index: index + len,
+                line,
+                column: column + len,
+            },
+        }
+    }
+
+    fn word(value: &str, loc: TokenLocation) -> ast::Word {
+        ast::Word {
+            value: value.to_owned(),
+            loc: Some(loc),
+        }
+    }
+
     #[test]
     fn parse_case() -> Result<()> {
         let input = r"\
@@ -982,6 +1362,94 @@ esac\
         Ok(())
     }
 
+    #[test]
+    fn parse_clause_locations() -> Result<()> {
+        let options = ParserOptions::default();
+        let source_info = SourceInfo::default();
+
+        let tokens = tokenize_str("if a; then b\nelif c; then d\nelse e\nfi")?;
+        let command = super::token_parser::if_clause(
+            &Tokens {
+                tokens: tokens.as_slice(),
+            },
+            &options,
+            &source_info,
+        )?;
+        let elses = command.elses.unwrap_or_default();
+        assert_eq!(elses.len(), 2);
+        assert_eq!((elses[0].loc.start.line, elses[0].loc.end.line), (2, 2));
+        assert_eq!((elses[1].loc.start.line, elses[1].loc.end.line), (3, 3));
+        assert_eq!(elses[1].loc.start.column, 1);
+
+        let tokens = tokenize_str("while a\ndo b; done")?;
+        let command = super::token_parser::while_clause(
+            &Tokens {
+                tokens: tokens.as_slice(),
+            },
+            &options,
+            &source_info,
+        )?;
+        assert_eq!(command.condition.to_string(), "a");
+        assert_eq!((command.loc.start.line, command.loc.end.line), (1, 2));
+
+        let tokens = tokenize_str("case x in\n(x) echo y;;\n  z)\nesac")?;
+        let command = super::token_parser::case_clause(
+            &Tokens {
+                tokens: tokens.as_slice(),
+            },
+            &options,
+            &source_info,
+        )?;
+        assert_eq!(command.cases.len(), 2);
+        assert_eq!(
+            command.cases[0].loc,
+            TokenLocation::within(&loc(10, 2, 1, 1), &loc(20, 2, 11, 2))
+        );
+        assert_eq!(
+            command.cases[1].loc,
+            TokenLocation::within(&loc(25, 3, 3, 1), &loc(26, 3, 4, 1))
+        );
+
+        let tokens = tokenize_str("function f\n{ :; }")?;
+        let command = super::token_parser::function_definition(
+            &Tokens {
+                tokens: tokens.as_slice(),
+            },
+            &options,
+            &source_info,
+        )?;
+        assert_eq!(
+            command.loc,
+            TokenLocation::within(&loc(0, 1, 1, 8), &loc(16, 2, 6, 1))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn display_select_clause() -> Result<()> {
+        let options = ParserOptions::default();
+        let source_info = SourceInfo::default();
+
+        for (input, expected) in [
+            ("select x in a b; do echo $x; done", "select x in a b;"),
+            ("select x in; do echo $x; done", "select x in;"),
+            ("select x; do echo $x; done", "select x;"),
+        ] {
+            let tokens = tokenize_str(input)?;
+            let command = super::token_parser::select_clause(
+                &Tokens {
+                    tokens: tokens.as_slice(),
+                },
+                &options,
+                &source_info,
+            )?;
+            assert_eq!(command.to_string().lines().next(), Some(expected));
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn parse_redirection() -> Result<()> {
         let input = r"echo |& wc";
@@ -1066,33 +1534,48 @@ for f in A B C; do
                         seq: vec![Command::Compound(
                             CompoundCommand::ForClause(ForClauseCommand {
                                 variable_name: "f".into(),
-                                values: Some(vec![Word::new("A"), Word::new("B"), Word::new("C")]),
-                                body: DoGroupCommand(CompoundList(vec![CompoundListItem(
-                                    AndOrList {
-                                        first: Pipeline {
-                                            timed: None,
-                                            bang: false,
-                                            seq: vec![Command::Simple(SimpleCommand {
-                                                prefix: None,
-                                                word_or_name: Some(Word::new("echo")),
-                                                suffix: Some(CommandSuffix(vec![
-                                                    CommandPrefixOrSuffixItem::Word(Word::new(
-                                                        r#""${f@L}""#,
+                                values: Some(vec![
+                                    word("A", loc(32, 5, 10, 1)),
+                                    word("B", loc(34, 5, 12, 1)),
+                                    word("C", loc(36, 5, 14, 1)),
+                                ]),
+                                body: DoGroupCommand {
+                                    list: CompoundList(vec![CompoundListItem(
+                                        AndOrList {
+                                            first: Pipeline {
+                                                timed: None,
+                                                bang: false,
+                                                seq: vec![Command::Simple(SimpleCommand {
+                                                    prefix: None,
+                                                    word_or_name: Some(word(
+                                                        "echo",
+                                                        loc(60, 8, 5, 4),
                                                     )),
-                                                    CommandPrefixOrSuffixItem::IoRedirect(
-                                                        IoRedirect::File(
-                                                            None,
-                                                            IoFileRedirectKind::DuplicateOutput,
-                                                            IoFileRedirectTarget::Fd(2),
+                                                    suffix: Some(CommandSuffix(vec![
+                                                        CommandPrefixOrSuffixItem::Word(word(
+                                                            r#""${f@L}""#,
+                                                            loc(65, 8, 10, 8),
+                                                        )),
+                                                        CommandPrefixOrSuffixItem::IoRedirect(
+                                                            IoRedirect::File(
+                                                                None,
+                                                                IoFileRedirectKind::DuplicateOutput,
+                                                                IoFileRedirectTarget::Fd(2),
+                                                            ),
                                                         ),
-                                                    ),
-                                                ])),
-                                            })],
+                                                    ])),
+                                                })],
+                                            },
+                                            additional: vec![],
                                         },
-                                        additional: vec![],
-                                    },
-                                    SeparatorOperator::Sequence,
-                                )])),
+                                        SeparatorOperator::Sequence,
+                                    )]),
+                                    loc: TokenLocation::within(
+                                        &loc(39, 5, 17, 2),
+                                        &loc(82, 10, 4, 4),
+                                    ),
+                                },
+                                loc: TokenLocation::within(&loc(23, 5, 1, 3), &loc(82, 10, 4, 4)),
                             }),
                             None,
                         )],
@@ -1113,6 +1596,110 @@ for f in A B C; do
         )?;
 
         assert_eq!(result, expected);
+        assert_eq!(
+            result.location(),
+            Some(TokenLocation::within(&loc(23, 5, 1, 3), &loc(82, 10, 4, 4)))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_next_command_by_command() -> Result<()> {
+        let input = "echo a; echo b\n\nf() {\n  echo 'c\nd'\n}\n# done\n";
+        let options = ParserOptions::default();
+        let mut reader = input.as_bytes();
+        let mut parser = Parser::new(&mut reader, &options, &SourceInfo::default());
+
+        let first = parser.parse_next(&options)?;
+        assert_matches!(&first, Some(p) if p.complete_commands.len() == 1);
+        assert_eq!(first.unwrap().complete_commands[0].0.len(), 2);
+
+        let second = parser.parse_next(&options)?.and_then(|p| p.location());
+        assert_matches!(second, Some(l) if l.start.line == 3 && l.end.line == 6);
+
+        assert!(parser.parse_next(&options)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_next_with_changing_options() -> Result<()> {
+        let input = "shopt -s extglob\necho @(a|b)\n";
+        let mut options = ParserOptions {
+            enable_extended_globbing: false,
+            ..ParserOptions::default()
+        };
+        let mut reader = input.as_bytes();
+        let mut parser = Parser::new(&mut reader, &options, &SourceInfo::default());
+
+        assert!(parser.parse_next(&options)?.is_some());
+
+        options.enable_extended_globbing = true;
+        let second = parser.parse_next(&options)?;
+        assert_eq!(
+            second.map(|p| p.to_string()),
+            Some("echo @(a|b)".to_owned())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_next_stops_at_syntax_error() -> Result<()> {
+        let input = "echo one\nif then\necho two\n";
+        let options = ParserOptions::default();
+        let mut reader = input.as_bytes();
+        let mut parser = Parser::new(&mut reader, &options, &SourceInfo::default());
+
+        assert!(parser.parse_next(&options)?.is_some());
+        assert_matches!(
+            parser.parse_next(&options),
+            Err(error::ParseError::ParsingNearToken(t)) if t.location().start.line == 2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_next_resumes_after_syntax_error() -> Result<()> {
+        let input = "echo one\nfi\necho two\n";
+        let options = ParserOptions::default();
+        let mut reader = input.as_bytes();
+        let mut parser = Parser::new(&mut reader, &options, &SourceInfo::default());
+
+        assert!(parser.parse_next(&options)?.is_some());
+        assert!(parser.parse_next(&options).is_err());
+
+        let third = parser.parse_next(&options)?;
+        assert_eq!(third.map(|p| p.to_string()), Some("echo two".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_next_large_here_document() -> Result<()> {
+        let body: String = (0..20000).map(|i| std::format!("line {i}\n")).collect();
+        let input = std::format!("cat <<EOF\n{body}EOF\necho 'a\nb' after\necho last\n");
+        let options = ParserOptions::default();
+        let mut reader = input.as_bytes();
+        let mut parser = Parser::new(&mut reader, &options, &SourceInfo::default());
+
+        assert!(parser.parse_next(&options)?.is_some());
+
+        let second = parser.parse_next(&options)?;
+        assert_matches!(
+            second.as_ref().and_then(|p| p.location()),
+            Some(l) if l.start.line == 20003 && l.end.line == 20004
+        );
+        assert_eq!(
+            second.map(|p| p.to_string()),
+            Some("echo 'a\nb' after".to_owned())
+        );
+
+        let third = parser.parse_next(&options)?;
+        assert_eq!(third.map(|p| p.to_string()), Some("echo last".to_owned()));
+        assert!(parser.parse_next(&options)?.is_none());
 
         Ok(())
     }
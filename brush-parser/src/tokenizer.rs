@@ -30,6 +30,7 @@ pub(crate) enum TokenEndReason {
 /// Represents a position in a source shell script.
 #[derive(Clone, Default, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct SourcePosition {
     /// The 0-based index of the character in the input stream.
     pub index: i32,
@@ -48,6 +49,7 @@ impl Display for SourcePosition {
 /// Represents the location of a token in its source shell script.
 #[derive(Clone, Default, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct TokenLocation {
     /// The start position of the token.
     pub start: SourcePosition,
@@ -55,6 +57,21 @@ pub struct TokenLocation {
     pub end: SourcePosition,
 }
 
+impl TokenLocation {
+    /// Returns a location spanning from the start of one location to the end of another.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The location whose start begins the span.
+    /// * `end` - The location whose end terminates the span.
+    pub fn within(start: &TokenLocation, end: &TokenLocation) -> Self {
+        Self {
+            start: start.start.clone(),
+            end: end.end.clone(),
+        }
+    }
+}
+
 /// Represents a token extracted from a shell script.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -217,6 +234,9 @@ struct CrossTokenParseState {
     queued_tokens: Vec<TokenizeResult>,
     /// Are we in an arithmetic expansion?
     arithmetic_expansion: bool,
+    /// Are we in an extended test command? Extended globbing patterns are always
+    /// recognized within one.
+    extended_test: bool,
 }
 
 /// Options controlling how the tokenizer operates.
@@ -455,6 +475,14 @@ impl TokenParseState {
         }
 
         let token = self.pop(&cross_token_state.cursor);
+        if let Token::Word(w, _) = &token {
+            match w.as_str() {
+                "[[" => cross_token_state.extended_test = true,
+                "]]" => cross_token_state.extended_test = false,
+                _ => (),
+            }
+        }
+
         let result = TokenizeResult {
             reason,
             token: Some(token),
@@ -539,14 +567,27 @@ impl<'a, R: ?Sized + std::io::BufRead> Tokenizer<'a, R> {
                 current_here_tags: vec![],
                 queued_tokens: vec![],
                 arithmetic_expansion: false,
+                extended_test: false,
             },
         }
     }
 
+    /// Sets the position reported for the first character of input.
+    pub fn set_start_position(&mut self, position: &SourcePosition) {
+        self.cross_state.cursor = position.clone();
+    }
+
     pub fn current_location(&self) -> Option<SourcePosition> {
         Some(self.cross_state.cursor.clone())
     }
 
+    /// Returns whether tokens have been read that are yet to be returned (e.g., ones
+    /// held back until the bodies of here-documents have been read).
+    pub fn has_pending_tokens(&self) -> bool {
+        !self.cross_state.queued_tokens.is_empty()
+            || !matches!(self.cross_state.here_state, HereState::None)
+    }
+
     fn next_char(&mut self) -> Result<Option<char>, TokenizerError> {
         let c = self
             .char_reader
@@ -572,7 +613,7 @@ impl<'a, R: ?Sized + std::io::BufRead> Tokenizer<'a, R> {
         Ok(())
     }
 
-    fn peek_char(&mut self) -> Result<Option<char>, TokenizerError> {
+    pub fn peek_char(&mut self) -> Result<Option<char>, TokenizerError> {
         match self.char_reader.peek() {
             Some(result) => match result {
                 Ok(c) => Ok(Some(*c)),
@@ -1027,7 +1068,7 @@ impl<'a, R: ?Sized + std::io::BufRead> Tokenizer<'a, R> {
             // unquoted start of an extglob pattern, *and* if the current character
             // is an open parenthesis, then this begins an extglob pattern.
             else if c == '('
-                && self.options.enable_extended_globbing
+                && (self.options.enable_extended_globbing || self.cross_state.extended_test)
                 && state.unquoted()
                 && !state.in_operator()
                 && state
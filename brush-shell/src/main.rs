@@ -184,6 +184,10 @@ async fn run_impl(
             shell.shell_mut().as_mut().shell_name = Some(script_path);
         }
         shell.shell_mut().as_mut().positional_parameters = args.script_args;
+        shell.shell_mut().as_mut().env.set_global(
+            "BASH_EXECUTION_STRING",
+            brush_core::ShellVariable::new(command.as_str().into()),
+        )?;
 
         // Execute the command string.
         let params = shell.shell().as_ref().default_exec_params();
@@ -208,6 +212,23 @@ async fn run_impl(
         shell.run_interactively().await?;
     }
 
+    // Give the shell a chance to run its EXIT trap before we go.
+    let params = shell.shell().as_ref().default_exec_params();
+    shell.shell_mut().as_mut().run_exit_trap(&params).await?;
+
+    // An interactive login shell that didn't exit via `exit` or `logout` still needs to
+    // source the user's logout files.
+    if shell.shell().as_ref().options.interactive {
+        shell
+            .shell_mut()
+            .as_mut()
+            .source_logout_files(&params)
+            .await?;
+    }
+
+    // Let any jobs know that we're going away, if requested.
+    shell.shell_mut().as_mut().hangup_jobs_on_exit();
+
     // Make sure to return the last result observed in the shell.
     let result = shell.shell().as_ref().last_result();
 
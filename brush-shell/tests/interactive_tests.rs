@@ -116,6 +116,50 @@ fn run_pipeline_interactively() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn hangup_reaches_every_process_in_job() -> anyhow::Result<()> {
+    let mut session = start_shell_session()?;
+
+    // Start a multi-process job; suspend it and resume it in the background.
+    session.expect_prompt()?;
+    session.send_line(r#"sleep 100 | sleep 100 | sh -c "echo sta''rted; exec sleep 100""#)?;
+    session
+        .expect("started")
+        .context("Last command in pipeline didn't start")?;
+    session.suspend()?;
+    session.expect_prompt()?;
+    session.send_line("bg")?;
+    session.expect_prompt()?;
+
+    // Find the job's process group.
+    let pgid_output = session.exec_output("jobs -p")?;
+    let pgid: i32 = pgid_output
+        .trim()
+        .parse()
+        .context("Unexpected output from jobs -p")?;
+
+    // Hang up the shell; it should pass SIGHUP on to the whole job before exiting.
+    session.send_line("kill -HUP $$")?;
+    session.expect(expectrl::Eof)?;
+
+    // Make sure no process in the job's process group survived.
+    let mut group_alive = true;
+    for _ in 0..50 {
+        group_alive = std::process::Command::new("kill")
+            .args(["-0", "--", format!("-{pgid}").as_str()])
+            .stderr(std::process::Stdio::null())
+            .status()?
+            .success();
+        if !group_alive {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert!(!group_alive, "processes in job's group survived hangup");
+
+    Ok(())
+}
+
 //
 // Helpers
 //